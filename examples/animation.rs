@@ -0,0 +1,148 @@
+//! Plays animations from a sprite sheet in the different playback modes.
+//! The sprite sheet is painted on the CPU, and shows a ball squashing as it bounces.
+//! A sprite below swaps its frame, flips and flashes whenever the animation starts over.
+
+use std::time::Duration;
+
+use cgmath::{Rad, Vector2};
+use ds2d::{
+    graphics::{self, Animation, AnimationEvent, Color, PlaybackMode, Quad, SpriteSheet},
+    timer, Context, GameResult,
+};
+use image::{Rgba, RgbaImage};
+use log::{error, info};
+
+const FRAME_SIZE: u32 = 32;
+const FRAMES: u32 = 8;
+
+pub struct AnimationGame {
+    batch: graphics::BatchRender,
+    animations: Vec<Animation>,
+    sprite: graphics::Sprite,
+    /// Time left until the flash of the sprite ends
+    flash: f32,
+}
+
+impl AnimationGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let batch = graphics::BatchRender::new(ctx)?;
+
+        let mut pixels = RgbaImage::new(FRAME_SIZE * FRAMES, FRAME_SIZE);
+        for (x, y, pixel) in pixels.enumerate_pixels_mut() {
+            let frame = x / FRAME_SIZE;
+            // The ball gets wider and flatter in later frames
+            let squash = 1.0 + frame as f32 * 0.08;
+            let dx = ((x % FRAME_SIZE) as f32 - 15.5) / squash;
+            let dy = (y as f32 - 31.5 + 16.0 / squash) * squash;
+            if dx * dx + dy * dy < 14.0 * 14.0 {
+                *pixel = Rgba([255, 255 - frame as u8 * 20, 64, 255]);
+            }
+        }
+        let texture = graphics::Texture2D::from_image(ctx, &pixels)?;
+        let sheet = SpriteSheet::from_grid(texture, Vector2::new(FRAME_SIZE, FRAME_SIZE));
+
+        let all_frames: Vec<usize> = (0..sheet.len()).collect();
+        let frame_duration = Duration::from_millis(80);
+        let animations = vec![
+            sheet
+                .animation(&all_frames, frame_duration, PlaybackMode::Loop)
+                .unwrap()
+                .with_event(0, "bounce"),
+            sheet
+                .animation(&all_frames, frame_duration, PlaybackMode::PingPong)
+                .unwrap(),
+            sheet
+                .animation(&all_frames, frame_duration, PlaybackMode::Once)
+                .unwrap(),
+        ];
+
+        let sprite = graphics::Sprite::build(sheet.texture().clone())
+            .with_source(sheet.frame(0).unwrap().source)
+            .with_size(Vector2::new(128.0, 128.0))
+            .with_origin(Vector2::new(0.5, 0.5))
+            .with_position(Vector2::new(400.0, 480.0))
+            .with_rotation(Rad(0.2))
+            .build(ctx)?;
+
+        Ok(Self {
+            batch,
+            animations,
+            sprite,
+            flash: 0.0,
+        })
+    }
+}
+
+impl ds2d::Game for AnimationGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::CORNFLOWER_BLUE);
+        for (i, animation) in self.animations.iter().enumerate() {
+            if let Some(frame) = animation.current_frame() {
+                self.batch.draw_quad(
+                    Quad::textured(frame.clone())
+                        .centered_at(Vector2::new(200.0 + i as f32 * 200.0, 300.0))
+                        .scale(4.0),
+                );
+            }
+        }
+        graphics::draw(ctx, &mut self.batch)?;
+        graphics::draw(ctx, &mut self.sprite)
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        for animation in self.animations.iter_mut() {
+            for event in animation.update(ctx) {
+                match event {
+                    AnimationEvent::Frame { name, .. } => info!("Event: {}", name),
+                    AnimationEvent::Finished => info!("Finished, restarting"),
+                    AnimationEvent::Looped => {
+                        let flipped = self.sprite.flip_x();
+                        self.sprite.set_flip_x(!flipped);
+                        self.sprite
+                            .set_rotation(Rad(if flipped { 0.2 } else { -0.2 }));
+                        self.flash = 0.1;
+                    }
+                }
+            }
+            if animation.is_finished() {
+                animation.reset();
+            }
+        }
+
+        // The sprite follows the looping animation
+        if let Some(frame) = self.animations[0].current_frame() {
+            self.sprite.set_texture_view(frame.clone());
+        }
+        self.flash -= timer::delta(ctx).as_secs_f32();
+        if self.flash > 0.0 {
+            self.sprite.set_tint(Color::RED);
+        } else {
+            self.sprite.set_tint(Color::WHITE);
+        }
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Animation")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = AnimationGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
@@ -0,0 +1,99 @@
+//! Packs several generated images and the face texture into a single atlas,
+//! so that all quads can be drawn in one batch.
+
+use cgmath::{Rad, Vector2};
+use ds2d::{
+    graphics::{self, Color, Quad, TextureAtlas, TextureAtlasBuilder},
+    Context, GameResult,
+};
+use image::{Rgba, RgbaImage};
+use log::{error, info};
+
+const NAMES: [&str; 4] = ["face", "red", "green", "blue"];
+
+pub struct AtlasGame {
+    batch: graphics::BatchRender,
+    atlas: TextureAtlas,
+    sprites: Vec<(&'static str, Vector2<f32>, Rad<f32>)>,
+}
+
+impl AtlasGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let batch = graphics::BatchRender::new(ctx)?;
+
+        let mut builder = TextureAtlasBuilder::new().with_padding(2).with_extrusion(1);
+        let face = image::load_from_memory(include_bytes!("face.png"))
+            .map_err(graphics::GraphicsError::from)?
+            .into_rgba();
+        builder.add_image("face", face);
+        builder.add_image("red", RgbaImage::from_pixel(32, 32, Rgba([255, 0, 0, 255])));
+        builder.add_image(
+            "green",
+            RgbaImage::from_pixel(48, 24, Rgba([0, 255, 0, 255])),
+        );
+        builder.add_image(
+            "blue",
+            RgbaImage::from_pixel(16, 64, Rgba([0, 0, 255, 255])),
+        );
+        let atlas = builder.build(ctx)?;
+        info!("Atlas description:\n{}", atlas.layout().to_description());
+
+        let sprites = (0..400)
+            .map(|i| {
+                let pos = rand::random::<Vector2<f32>>() * 800f32;
+                let angle = rand::random::<Rad<f32>>();
+                (NAMES[i % NAMES.len()], pos, angle)
+            })
+            .collect();
+        Ok(Self {
+            batch,
+            atlas,
+            sprites,
+        })
+    }
+}
+
+impl ds2d::Game for AtlasGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::CORNFLOWER_BLUE);
+        for (name, pos, angle) in self.sprites.iter().copied() {
+            let view = self.atlas.get(name).expect("image is part of the atlas");
+            let scale = if name == "face" { 0.25 } else { 1.0 };
+            self.batch.draw_quad(
+                Quad::textured(view)
+                    .centered_at(pos)
+                    .scale(scale)
+                    .with_rotation(angle),
+            )
+        }
+        graphics::draw(ctx, &mut self.batch)
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Texture Atlas")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = AtlasGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
@@ -0,0 +1,124 @@
+//! Measures how many textured quads `BatchRender` can draw per frame while staying at 60 FPS.
+//!
+//! Unless `--fixed` is given, the number of quads is adjusted every second until the frame
+//! rate settles around the target. Vsync is turned off so that the frame rate is not capped.
+//!
+//! Only the public API of `BatchRender` is used, so this file can also be copied into an older
+//! checkout of the crate to compare the results of both versions on the same machine.
+
+use std::time::Duration;
+
+use cgmath::{Rad, Vector2};
+use ds2d::{
+    graphics::{self, Color, Quad, Texture2D},
+    timer, Context, GameResult,
+};
+use log::{error, info};
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+struct Opt {
+    /// Number of quads drawn in the first frame.
+    #[structopt(long, default_value = "1000")]
+    quads: usize,
+    /// Keep the number of quads fixed instead of searching for the 60 FPS limit.
+    #[structopt(long)]
+    fixed: bool,
+}
+
+const TARGET_FPS: f64 = 60.0;
+
+pub struct Benchmark {
+    batch: graphics::BatchRender,
+    face: Texture2D,
+    faces: Vec<(Vector2<f32>, Rad<f32>)>,
+    fixed: bool,
+    since_report: Duration,
+}
+
+impl Benchmark {
+    fn new(ctx: &mut Context, opt: &Opt) -> GameResult<Self> {
+        let batch = graphics::BatchRender::new(ctx)?;
+        let face = graphics::Texture2D::from_memory(ctx, include_bytes!("face.png"))?;
+        let mut benchmark = Self {
+            batch,
+            face,
+            faces: Vec::new(),
+            fixed: opt.fixed,
+            since_report: Duration::default(),
+        };
+        benchmark.resize(opt.quads);
+        Ok(benchmark)
+    }
+
+    fn resize(&mut self, quads: usize) {
+        self.faces.truncate(quads);
+        while self.faces.len() < quads {
+            let pos = rand::random::<Vector2<f32>>() * 800f32;
+            let angle = rand::random::<Rad<f32>>();
+            self.faces.push((pos, angle));
+        }
+    }
+}
+
+impl ds2d::Game for Benchmark {
+    fn draw(&mut self, ctx: &mut ds2d::Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::CORNFLOWER_BLUE);
+        for (pos, angle) in self.faces.iter().copied() {
+            self.batch.draw_quad(
+                Quad::textured(self.face.clone())
+                    .centered_at(pos)
+                    .scale(0.25)
+                    .with_rotation(angle),
+            )
+        }
+        graphics::draw(ctx, &mut self.batch)
+    }
+
+    fn update(&mut self, ctx: &mut ds2d::Context) -> GameResult<()> {
+        self.since_report += timer::delta(ctx);
+        if self.since_report >= Duration::from_secs(1) {
+            self.since_report = Duration::default();
+            let fps = 1.0 / timer::average_delta(ctx).as_secs_f64();
+            info!("{} quads per frame at {:.1} FPS", self.faces.len(), fps);
+            if !self.fixed {
+                // Approach the target frame rate proportionally
+                let factor = (fps / TARGET_FPS).max(0.5).min(2.0);
+                let quads = (self.faces.len() as f64 * factor).max(1.0) as usize;
+                self.resize(quads);
+            }
+        }
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut ds2d::Context) -> GameResult<()> {
+        info!(
+            "Finished with {} quads per frame (target: {} FPS)",
+            self.faces.len(),
+            TARGET_FPS
+        );
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(2).init().unwrap();
+    let opt = Opt::from_args();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(false)
+        .vsync(false)
+        .title("Benchmark")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = Benchmark::new(&mut context, &opt).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
@@ -0,0 +1,95 @@
+//! Draws text with a BMFont bitmap font next to an outline font, which also serves as its fallback.
+//! The bitmap font is scaled by whole multiples of its native size to keep the pixels sharp.
+
+use cgmath::Vector2;
+use ds2d::{
+    graphics::{
+        self,
+        text::{Font, Rasterizer, Style, TextBuffer},
+        Color,
+    },
+    Context, GameResult,
+};
+use log::error;
+
+pub struct BitmapFontGame {
+    rasterizer: Rasterizer,
+    pixel_font: Font,
+    outline_font: Font,
+}
+
+impl BitmapFontGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let mut rasterizer = Rasterizer::new(ctx)?;
+        let pixel_font = rasterizer.load_bitmap_font(
+            ctx,
+            concat!(env!("CARGO_MANIFEST_DIR"), "/examples/pixel_font.fnt"),
+        )?;
+        let font_data: Vec<_> = include_bytes!("RobotoSlab-Regular.ttf").as_ref().to_owned();
+        let outline_font = rasterizer.create_font(font_data)?;
+        Ok(Self {
+            rasterizer,
+            pixel_font,
+            outline_font,
+        })
+    }
+}
+
+impl ds2d::Game for BitmapFontGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::CORNFLOWER_BLUE);
+
+        let mut text = TextBuffer::new();
+        for (i, &scale) in [1.0, 2.0, 4.0].iter().enumerate() {
+            // Characters beyond ASCII are taken from the outline font
+            let style = Style::new(self.pixel_font.clone(), 16.0 * scale, Color::WHITE)
+                .with_fallback(vec![self.outline_font.clone()]);
+            text.add(
+                &style,
+                Vector2::new(20.0, 40.0 + 80.0 * i as f32),
+                format!("Pixel font at {}x, AV To. Café!", scale),
+            );
+        }
+
+        let style = Style::new(
+            self.outline_font.clone(),
+            32.0,
+            Color::from_rgba(1.0, 1.0, 0.0, 1.0),
+        );
+        text.add(
+            &style,
+            Vector2::new(20.0, 320.0),
+            "Outline fonts share the same buffer.",
+        );
+
+        graphics::draw(ctx, &mut self.rasterizer.rasterize(&text))
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Bitmap Font")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = BitmapFontGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
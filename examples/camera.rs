@@ -0,0 +1,119 @@
+//! Scroll through a world larger than the window with a camera that follows the player.
+//!
+//! Move with WASD, zoom with Q and E, and press space to shake the camera.
+//! The world position under the mouse cursor is highlighted.
+
+use std::time::Duration;
+
+use cgmath::Vector2;
+use ds2d::{
+    graphics::{self, Camera2D, Color, Quad, Rect},
+    input::{keyboard, mouse},
+    timer, Context, GameResult,
+};
+use log::error;
+
+const WORLD_SIZE: f32 = 2000.0;
+const TILE_SIZE: f32 = 100.0;
+
+pub struct CameraGame {
+    batch: graphics::BatchRender,
+    camera: Camera2D,
+    player: Vector2<f32>,
+}
+
+impl CameraGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let batch = graphics::BatchRender::new(ctx)?;
+        let player = Vector2::new(WORLD_SIZE / 2.0, WORLD_SIZE / 2.0);
+        let camera = Camera2D::new()
+            .with_position(player)
+            .with_follow_lag(0.05)
+            .with_bounds(Rect {
+                top_left: Vector2::new(0.0, 0.0),
+                bottom_right: Vector2::new(WORLD_SIZE, WORLD_SIZE),
+            });
+        Ok(Self {
+            batch,
+            camera,
+            player,
+        })
+    }
+}
+
+impl ds2d::Game for CameraGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::BLACK);
+        let tiles = (WORLD_SIZE / TILE_SIZE) as u32;
+        for y in 0..tiles {
+            for x in 0..tiles {
+                let color = if (x + y) % 2 == 0 {
+                    Color::CORNFLOWER_BLUE
+                } else {
+                    Color::WHITE
+                };
+                self.batch.draw_quad(
+                    Quad::untextured(Vector2::new(TILE_SIZE, TILE_SIZE))
+                        .with_position(Vector2::new(x as f32, y as f32) * TILE_SIZE)
+                        .with_tint(color),
+                );
+            }
+        }
+        let mouse_position = mouse::position(ctx);
+        let cursor = self.camera.screen_to_world(ctx, mouse_position);
+        self.batch.draw_quad(
+            Quad::untextured(Vector2::new(10.0, 10.0))
+                .centered_at(cursor)
+                .with_tint(Color::GREEN),
+        );
+        self.batch.draw_quad(
+            Quad::untextured(Vector2::new(40.0, 40.0))
+                .centered_at(self.player)
+                .with_tint(Color::RED),
+        );
+        graphics::draw_with_camera(ctx, &self.camera, &mut self.batch)
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        use keyboard::KeyCode;
+
+        let dt = timer::delta(ctx).as_secs_f32();
+        let movement: Vector2<f32> =
+            keyboard::axis2d(ctx, KeyCode::A, KeyCode::D, KeyCode::W, KeyCode::S);
+        self.player += movement * 400.0 * dt;
+        let zoom: f32 = keyboard::axis1d(ctx, KeyCode::Q, KeyCode::E);
+        let zoom = self.camera.zoom() * (zoom * dt).exp();
+        self.camera.set_zoom(zoom);
+        if keyboard::is_down(ctx, KeyCode::Space) {
+            self.camera.shake(10.0, Duration::from_millis(300));
+        }
+
+        self.camera.follow(Some(self.player));
+        self.camera.update(ctx);
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Camera")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = CameraGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
@@ -0,0 +1,81 @@
+use cgmath::{Rad, Vector2};
+use ds2d::{
+    graphics::{self, Canvas, Color, Quad, Texture2D},
+    Context, GameResult,
+};
+use log::error;
+
+/// Renders a small scene into a low resolution canvas, and then upscales it to the screen.
+pub struct CanvasGame {
+    batch: graphics::BatchRender,
+    face: Texture2D,
+    canvas: Canvas,
+    angle: Rad<f32>,
+}
+
+impl CanvasGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let batch = graphics::BatchRender::new(ctx)?;
+        let face = graphics::Texture2D::from_memory(ctx, include_bytes!("face.png"))?;
+        let canvas = Canvas::new(ctx, 160, 120)?;
+        Ok(Self {
+            batch,
+            face,
+            canvas,
+            angle: Rad(0.0),
+        })
+    }
+}
+
+impl ds2d::Game for CanvasGame {
+    fn draw(&mut self, ctx: &mut ds2d::Context) -> GameResult<()> {
+        graphics::set_render_target(ctx, Some(&self.canvas))?;
+        graphics::clear(ctx, Color::CORNFLOWER_BLUE);
+        self.batch.draw_quad(
+            Quad::textured(self.face.clone())
+                .centered_at(Vector2::new(80.0, 60.0))
+                .with_size(Vector2::new(64.0, 64.0))
+                .with_rotation(self.angle),
+        );
+        graphics::draw(ctx, &mut self.batch)?;
+
+        graphics::set_render_target(ctx, None)?;
+        graphics::clear(ctx, Color::BLACK);
+        let screen = graphics::screen_size(ctx);
+        self.batch.draw_quad(
+            Quad::textured(&self.canvas)
+                .with_size(Vector2::new(screen.width as f32, screen.height as f32)),
+        );
+        graphics::draw(ctx, &mut self.batch)?;
+        Ok(())
+    }
+
+    fn update(&mut self, ctx: &mut ds2d::Context) -> GameResult<()> {
+        self.angle += Rad(ds2d::timer::delta(ctx).as_secs_f32());
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut ds2d::Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Canvas")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = CanvasGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
@@ -0,0 +1,116 @@
+//! Clips drawing to a rectangle with a scissor, and to a star shape with a mask.
+
+use cgmath::{Rad, Vector2};
+use ds2d::{
+    graphics::{self, primitives::BasicVertex2D, Color, Quad, Rect, Texture2D},
+    timer, Context, GameResult,
+};
+use log::error;
+
+pub struct ClippingGame {
+    batch: graphics::BatchRender,
+    face: Texture2D,
+    star: graphics::Mesh,
+    time: f32,
+}
+
+impl ClippingGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let batch = graphics::BatchRender::new(ctx)?;
+        let face = graphics::Texture2D::from_memory(ctx, include_bytes!("face.png"))?;
+
+        // A triangle fan around the center of the star
+        let center = Vector2::new(600.0, 300.0);
+        let mut vertices = vec![BasicVertex2D::with_position_color(center, Color::WHITE)];
+        for i in 0..10 {
+            let angle = i as f32 * std::f32::consts::PI / 5.0;
+            let radius = if i % 2 == 0 { 180.0 } else { 70.0 };
+            let corner = center + Vector2::new(angle.sin(), -angle.cos()) * radius;
+            vertices.push(BasicVertex2D::with_position_color(corner, Color::WHITE));
+        }
+        let mut indices = Vec::new();
+        for i in 0..10 {
+            indices.extend_from_slice(&[0, 1 + i, 1 + (i + 1) % 10]);
+        }
+        let star = graphics::Mesh::new(ctx, &vertices, &indices)?;
+
+        Ok(Self {
+            batch,
+            face,
+            star,
+            time: 0.0,
+        })
+    }
+
+    fn draw_faces(&mut self, center: Vector2<f32>) {
+        for i in 0..8 {
+            let angle = Rad(self.time + i as f32 * std::f32::consts::PI / 4.0);
+            let offset = Vector2::new(angle.0.cos(), angle.0.sin()) * 150.0;
+            self.batch.draw_quad(
+                Quad::textured(self.face.clone())
+                    .centered_at(center + offset)
+                    .scale(0.5)
+                    .with_rotation(angle),
+            );
+        }
+    }
+}
+
+impl ds2d::Game for ClippingGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::BLACK);
+
+        // A panel on the left, where everything outside is cut off
+        graphics::set_scissor(
+            ctx,
+            Some(Rect {
+                top_left: Vector2::new(50, 150),
+                bottom_right: Vector2::new(350, 450),
+            }),
+        )?;
+        graphics::clear(ctx, Color::CORNFLOWER_BLUE);
+        self.draw_faces(Vector2::new(200.0, 300.0));
+        graphics::set_scissor(ctx, None)?;
+        graphics::draw(ctx, &mut self.batch)?;
+
+        // The same on the right, but inside a star
+        graphics::set_mask(ctx, &mut self.star)?;
+        self.batch.draw_quad(
+            Quad::untextured(Vector2::new(400.0, 400.0))
+                .centered_at(Vector2::new(600.0, 300.0))
+                .with_tint(Color::WHITE),
+        );
+        self.draw_faces(Vector2::new(600.0, 300.0));
+        graphics::draw(ctx, &mut self.batch)?;
+        graphics::remove_mask(ctx)
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.time += timer::delta(ctx).as_secs_f32();
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Clipping")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = ClippingGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
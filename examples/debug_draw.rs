@@ -0,0 +1,101 @@
+//! Visualizes a bouncing ball with lines, using both a `LineBatch`
+//! and the immediate debug drawing functions.
+
+use cgmath::Vector2;
+use ds2d::{
+    graphics::{self, debug::LineBatch, Color, Rect},
+    timer, Context, GameResult,
+};
+use log::error;
+
+pub struct DebugDrawGame {
+    lines: LineBatch,
+    position: Vector2<f32>,
+    velocity: Vector2<f32>,
+    trail: Vec<Vector2<f32>>,
+}
+
+impl DebugDrawGame {
+    pub fn new(_ctx: &mut Context) -> GameResult<Self> {
+        Ok(Self {
+            lines: LineBatch::new(),
+            position: Vector2::new(400.0, 300.0),
+            velocity: Vector2::new(180.0, 120.0),
+            trail: Vec::new(),
+        })
+    }
+}
+
+impl ds2d::Game for DebugDrawGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::BLACK);
+
+        let bounds = Rect {
+            top_left: Vector2::new(0.0, 0.0),
+            bottom_right: Vector2::new(800.0, 600.0),
+        };
+        self.lines.set_thickness(1.0);
+        self.lines.grid(
+            bounds,
+            Vector2::new(50.0, 50.0),
+            Color::from_rgb(0.2, 0.2, 0.2),
+        );
+        self.lines.set_thickness(3.0);
+        self.lines
+            .polyline(&self.trail, Color::from_rgba(1.0, 1.0, 1.0, 0.5));
+        for point in &self.trail {
+            self.lines.point(*point, Color::CORNFLOWER_BLUE);
+        }
+        graphics::draw(ctx, &mut self.lines)?;
+
+        // Drawn on top of everything else at the end of the frame
+        graphics::debug::circle(ctx, self.position, 20.0, Color::GREEN);
+        graphics::debug::line(
+            ctx,
+            self.position,
+            self.position + self.velocity * 0.5,
+            Color::RED,
+        );
+        Ok(())
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let dt = timer::delta(ctx).as_secs_f32();
+        self.position += self.velocity * dt;
+        if self.position.x < 20.0 || self.position.x > 780.0 {
+            self.velocity.x = -self.velocity.x;
+        }
+        if self.position.y < 20.0 || self.position.y > 580.0 {
+            self.velocity.y = -self.velocity.y;
+        }
+        self.trail.push(self.position);
+        if self.trail.len() > 60 {
+            self.trail.remove(0);
+        }
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Debug Drawing")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = DebugDrawGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
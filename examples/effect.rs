@@ -0,0 +1,97 @@
+use cgmath::Vector2;
+use ds2d::{
+    graphics::{self, Color, Quad, ShaderEffect, Texture2D},
+    Context, GameResult,
+};
+use log::error;
+
+/// Fades the texture in and out with a noisy dissolve pattern.
+const DISSOLVE_SHADER: &str = r"#version 330 core
+in vec2 Vert_Frag_TexCoord;
+in vec4 Vert_Frag_Color;
+
+out vec4 FragColor;
+
+uniform sampler2D Texture0;
+uniform float Threshold;
+
+float noise(vec2 p) {
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+void main()
+{
+    if (noise(floor(Vert_Frag_TexCoord * 64.0)) < Threshold) {
+        discard;
+    }
+    FragColor = texture(Texture0, Vert_Frag_TexCoord) * Vert_Frag_Color;
+}";
+
+pub struct EffectGame {
+    batch: graphics::BatchRender,
+    face: Texture2D,
+    dissolve: ShaderEffect,
+    time: f32,
+}
+
+impl EffectGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let batch = graphics::BatchRender::new(ctx)?;
+        let face = graphics::Texture2D::from_memory(ctx, include_bytes!("face.png"))?;
+        let dissolve = ShaderEffect::from_fragment(ctx, DISSOLVE_SHADER)?;
+        Ok(Self {
+            batch,
+            face,
+            dissolve,
+            time: 0.0,
+        })
+    }
+}
+
+impl ds2d::Game for EffectGame {
+    fn draw(&mut self, ctx: &mut ds2d::Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::CORNFLOWER_BLUE);
+
+        // The left face uses the default shaders, the right one dissolves
+        self.batch
+            .draw_quad(Quad::textured(self.face.clone()).centered_at(Vector2::new(200.0, 300.0)));
+        self.dissolve
+            .set_uniform("Threshold", 0.5 + 0.5 * self.time.sin())?;
+        self.batch.set_effect(Some(self.dissolve.clone()));
+        self.batch
+            .draw_quad(Quad::textured(self.face.clone()).centered_at(Vector2::new(600.0, 300.0)));
+        self.batch.set_effect(None);
+
+        graphics::draw(ctx, &mut self.batch)?;
+        Ok(())
+    }
+
+    fn update(&mut self, ctx: &mut ds2d::Context) -> GameResult<()> {
+        self.time += ds2d::timer::delta(ctx).as_secs_f32();
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut ds2d::Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Shader effects")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = EffectGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
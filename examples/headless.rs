@@ -0,0 +1,67 @@
+use cgmath::Vector2;
+use ds2d::{
+    graphics::{self, Color, Quad},
+    Context, GameResult,
+};
+use log::{error, info};
+
+pub struct HeadlessGame {
+    batch: graphics::BatchRender,
+    frame: u32,
+}
+
+impl HeadlessGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let batch = graphics::BatchRender::new(ctx)?;
+        Ok(Self { batch, frame: 0 })
+    }
+}
+
+impl ds2d::Game for HeadlessGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::CORNFLOWER_BLUE);
+        self.batch.draw_quad(
+            Quad::untextured(Vector2::new(64.0, 64.0))
+                .centered_at(Vector2::new(128.0, 128.0))
+                .with_tint(Color::RED),
+        );
+        graphics::draw(ctx, &mut self.batch)
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        self.frame += 1;
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let mut context = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .build_headless(256, 256)
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let mut game = HeadlessGame::new(&mut context).unwrap();
+    for _ in 0..10 {
+        ds2d::step(&mut context, &mut game).unwrap();
+    }
+    info!("Rendered {} frames without a window", game.frame);
+
+    let output = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "headless.png".to_owned());
+    let image = graphics::screenshot(&mut context).unwrap();
+    image.save(&output).unwrap();
+    info!("Saved screenshot to {}", output);
+}
@@ -0,0 +1,91 @@
+//! Draws a small solar system where each body is positioned relative to its parent
+//! by nesting transforms.
+
+use cgmath::{Rad, Vector2};
+use ds2d::{
+    graphics::{self, transform, Color, Quad},
+    timer, Context, GameResult,
+};
+use log::error;
+
+pub struct HierarchyGame {
+    batch: graphics::BatchRender,
+    time: f32,
+}
+
+impl HierarchyGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let batch = graphics::BatchRender::new(ctx)?;
+        Ok(Self { batch, time: 0.0 })
+    }
+
+    fn draw_body(&mut self, size: f32, color: Color) {
+        self.batch.draw_quad(
+            Quad::untextured(Vector2::new(size, size))
+                .centered_at(Vector2::new(0.0, 0.0))
+                .with_tint(color),
+        );
+    }
+}
+
+impl ds2d::Game for HierarchyGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::BLACK);
+        let time = self.time;
+
+        let size = graphics::screen_size(ctx);
+        let center = Vector2::new(size.width as f32, size.height as f32) / 2.0;
+        graphics::push_transform(ctx, transform::translate(center));
+        graphics::push_transform(ctx, transform::rotate(Rad(time * 0.2)));
+        self.draw_body(80.0, Color::from_rgb(1.0, 0.8, 0.0));
+
+        // The planet orbits the sun
+        graphics::with_transform(
+            ctx,
+            transform::rotate(Rad(time)) * transform::translate(Vector2::new(200.0, 0.0)),
+            |ctx| {
+                self.draw_body(30.0, Color::CORNFLOWER_BLUE);
+                // And the moon orbits the planet
+                graphics::with_transform(
+                    ctx,
+                    transform::rotate(Rad(time * 3.0))
+                        * transform::translate(Vector2::new(40.0, 0.0)),
+                    |_ctx| self.draw_body(10.0, Color::WHITE),
+                );
+            },
+        );
+
+        graphics::pop_transform(ctx);
+        graphics::pop_transform(ctx);
+        graphics::draw(ctx, &mut self.batch)
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.time += timer::delta(ctx).as_secs_f32();
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Transform Hierarchy")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = HierarchyGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
@@ -0,0 +1,133 @@
+//! Builds textured meshes, one of which is updated every frame,
+//! and draws the outline of a mesh with a different topology.
+
+use cgmath::Vector2;
+use ds2d::{
+    graphics::{self, primitives::BasicVertex2D, Color, Mesh, Rect, Topology},
+    timer, Context, GameResult,
+};
+use log::error;
+
+/// Number of columns of the waving flag
+const COLUMNS: u32 = 32;
+
+pub struct MeshGame {
+    /// A static mesh of textured quads
+    tiles: graphics::Mesh,
+    /// A textured mesh whose vertices move every frame
+    flag: graphics::Mesh,
+    /// The outline of the flag
+    outline: graphics::Mesh,
+    time: f32,
+}
+
+impl MeshGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let face = graphics::Texture2D::from_memory(ctx, include_bytes!("face.png"))?;
+
+        let mut tiles = Mesh::build().with_texture(face.clone());
+        for i in 0..4 {
+            let top_left = Vector2::new(50.0 + i as f32 * 110.0, 50.0);
+            let tint = Color::from_rgb(1.0, 1.0 - i as f32 * 0.25, 1.0);
+            tiles.quad(
+                Rect {
+                    top_left,
+                    bottom_right: top_left + Vector2::new(100.0, 100.0),
+                },
+                Rect::unit_square(),
+                tint,
+            );
+        }
+        let mut tiles = tiles.build(ctx)?;
+        tiles.set_blend_mode(Some(graphics::BlendMode::default()));
+
+        // A triangle strip alternating between the top and bottom edge of the flag
+        let mut flag = Mesh::build()
+            .with_topology(Topology::TriangleStrip)
+            .with_texture(face);
+        for i in 0..=COLUMNS * 2 + 1 {
+            flag.vertex(BasicVertex2D::with_position_color([0.0, 0.0], Color::WHITE));
+            flag.indices(&[i]);
+        }
+        let flag = flag.build(ctx)?;
+
+        let mut outline = Mesh::build().with_topology(Topology::LineLoop);
+        for _ in 0..=COLUMNS * 2 + 1 {
+            outline.vertex(BasicVertex2D::with_position_color([0.0, 0.0], Color::RED));
+        }
+        // Along the top edge, then back along the bottom edge
+        let top = (0..=COLUMNS).map(|i| i * 2);
+        let bottom = (0..=COLUMNS).rev().map(|i| i * 2 + 1);
+        outline.indices(&top.chain(bottom).collect::<Vec<_>>());
+        let outline = outline.build(ctx)?;
+
+        Ok(Self {
+            tiles,
+            flag,
+            outline,
+            time: 0.0,
+        })
+    }
+
+    fn flag_vertices(&self) -> Vec<BasicVertex2D> {
+        let mut vertices = Vec::new();
+        for column in 0..=COLUMNS {
+            let u = column as f32 / COLUMNS as f32;
+            let wave = (self.time * 3.0 - u * 8.0).sin() * 20.0 * u;
+            let x = 150.0 + u * 500.0;
+            for &v in &[0.0, 1.0] {
+                vertices.push(BasicVertex2D {
+                    position: [x, 250.0 + v * 300.0 + wave],
+                    tex_coord: [u, v],
+                    color: Color::WHITE.into(),
+                });
+            }
+        }
+        vertices
+    }
+}
+
+impl ds2d::Game for MeshGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::CORNFLOWER_BLUE);
+        graphics::draw(ctx, &mut self.tiles)?;
+        graphics::draw(ctx, &mut self.flag)?;
+        graphics::draw(ctx, &mut self.outline)
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.time += timer::delta(ctx).as_secs_f32();
+        let vertices = self.flag_vertices();
+        self.flag.set_vertices(&vertices)?;
+        let outline: Vec<_> = vertices
+            .into_iter()
+            .map(|vertex| BasicVertex2D::with_position_color(vertex.position, Color::RED))
+            .collect();
+        self.outline.set_vertices(&outline)?;
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Mesh")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = MeshGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
@@ -0,0 +1,79 @@
+//! Paints an animated pattern on the CPU and streams it into a texture every frame.
+
+use cgmath::Vector2;
+use ds2d::{
+    graphics::{self, Color, SamplerOptions, TextureFormat},
+    timer, Context, GameResult,
+};
+use image::{Rgba, RgbaImage};
+use log::error;
+
+const SIZE: u32 = 128;
+
+pub struct ProceduralGame {
+    sprite: graphics::Sprite,
+    pixels: RgbaImage,
+    time: f32,
+}
+
+impl ProceduralGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let texture = graphics::Texture2D::new_empty(ctx, SIZE, SIZE, TextureFormat::Rgba8)?;
+        let sprite = graphics::Sprite::build(texture)
+            .with_size(Vector2::new(512.0, 512.0))
+            .with_position(Vector2::new(16.0, 16.0))
+            .with_sampler(SamplerOptions::nearest())
+            .build(ctx)?;
+        Ok(Self {
+            sprite,
+            pixels: RgbaImage::new(SIZE, SIZE),
+            time: 0.0,
+        })
+    }
+}
+
+impl ds2d::Game for ProceduralGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::BLACK);
+        let time = self.time;
+        for (x, y, pixel) in self.pixels.enumerate_pixels_mut() {
+            let (x, y) = (x as f32 / 16.0, y as f32 / 16.0);
+            let value = (x + time).sin() + (y - time).cos() + ((x + y) * 0.5 + time).sin();
+            let channel = |phase: f32| (((value + phase).sin() * 0.5 + 0.5) * 255.0) as u8;
+            *pixel = Rgba([channel(0.0), channel(2.0), channel(4.0), 255]);
+        }
+        self.sprite
+            .texture()
+            .update_region(ctx, 0, 0, &self.pixels)?;
+        graphics::draw(ctx, &mut self.sprite)
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.time += timer::delta(ctx).as_secs_f32();
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Procedural Texture")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = ProceduralGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
@@ -0,0 +1,121 @@
+//! Draws text from a signed distance field font, which stays crisp while the camera zooms in and out.
+//! Each line shows one of the effects: plain, outlined, glowing and with a drop shadow.
+
+use cgmath::Vector2;
+use ds2d::{
+    graphics::{
+        self,
+        text::{Glow, Outline, SdfFont, Shadow, Style, TextBuffer},
+        Camera2D, Color,
+    },
+    timer, Context, GameResult,
+};
+use log::error;
+
+pub struct SdfGame {
+    sdf: SdfFont,
+    camera: Camera2D,
+    time: f32,
+}
+
+impl SdfGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let font_data: Vec<_> = include_bytes!("RobotoSlab-Regular.ttf").as_ref().to_owned();
+        let mut rasterizer = graphics::text::Rasterizer::new(ctx)?;
+        let font = rasterizer.create_font(font_data)?;
+        let sdf = SdfFont::build()
+            .with_size(48.0)
+            .with_spread(6.0)
+            .build(ctx, &font)?;
+        let camera = Camera2D::new().with_position(Vector2::new(400.0, 300.0));
+        Ok(Self {
+            sdf,
+            camera,
+            time: 0.0,
+        })
+    }
+}
+
+impl ds2d::Game for SdfGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::CORNFLOWER_BLUE);
+
+        let style = Style::new(self.sdf.font().clone(), 48.0, Color::WHITE);
+        let line = |y: f32, text: &str| TextBuffer::singleton(&style, Vector2::new(150.0, y), text);
+
+        self.sdf.set_outline(None);
+        self.sdf.set_glow(None);
+        self.sdf.set_shadow(None);
+        graphics::draw_with_camera(
+            ctx,
+            &self.camera,
+            &mut self.sdf.render(&line(180.0, "Plain")),
+        )?;
+
+        self.sdf.set_outline(Some(Outline {
+            color: Color::BLACK,
+            width: 2.0,
+        }));
+        graphics::draw_with_camera(
+            ctx,
+            &self.camera,
+            &mut self.sdf.render(&line(280.0, "Outlined")),
+        )?;
+
+        self.sdf.set_outline(None);
+        self.sdf.set_glow(Some(Glow {
+            color: Color::from_rgba(1.0, 1.0, 0.0, 0.8),
+            radius: 5.0,
+        }));
+        graphics::draw_with_camera(
+            ctx,
+            &self.camera,
+            &mut self.sdf.render(&line(380.0, "Glowing")),
+        )?;
+
+        self.sdf.set_glow(None);
+        self.sdf.set_shadow(Some(Shadow {
+            color: Color::from_rgba(0.0, 0.0, 0.0, 0.6),
+            offset: Vector2::new(3.0, 3.0),
+            softness: 2.0,
+        }));
+        graphics::draw_with_camera(
+            ctx,
+            &self.camera,
+            &mut self.sdf.render(&line(480.0, "Shadowed")),
+        )
+    }
+
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.time += timer::delta(ctx).as_secs_f32();
+        // Zoom far beyond the size the glyphs were generated at
+        self.camera
+            .set_zoom(1.0 + 1.5 * (self.time * 0.5).sin().abs());
+        self.camera.update(ctx);
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("SDF Text")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = SdfGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
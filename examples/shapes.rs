@@ -0,0 +1,127 @@
+//! Draws filled and stroked shapes produced by the tessellation module.
+
+use cgmath::{Deg, Vector2};
+use ds2d::{
+    graphics::{
+        self,
+        tessellation::{Geometry, LineCap, LineJoin, StrokeStyle},
+        Color, Rect,
+    },
+    Context, GameResult,
+};
+use log::error;
+
+pub struct ShapesGame {
+    batch: graphics::BatchRender,
+    /// Shapes that don't change are uploaded once.
+    background: graphics::Mesh,
+    shapes: Geometry,
+}
+
+impl ShapesGame {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        let batch = graphics::BatchRender::new(ctx)?;
+
+        let mut background = Geometry::new();
+        background.fill_rounded_rect(
+            Rect {
+                top_left: Vector2::new(20.0, 20.0),
+                bottom_right: Vector2::new(780.0, 580.0),
+            },
+            40.0,
+            Color::from_rgb(0.2, 0.2, 0.3),
+        );
+        let background = background.build_mesh(ctx)?;
+
+        let mut shapes = Geometry::new();
+        shapes.fill_circle(Vector2::new(150.0, 150.0), 80.0, Color::RED);
+        shapes.stroke_circle(
+            Vector2::new(150.0, 150.0),
+            95.0,
+            &StrokeStyle::new(6.0),
+            Color::WHITE,
+        );
+        shapes.fill_ellipse(
+            Vector2::new(400.0, 150.0),
+            Vector2::new(120.0, 60.0),
+            Color::GREEN,
+        );
+        shapes.fill_arc(
+            Vector2::new(650.0, 150.0),
+            90.0,
+            Deg(30.0),
+            Deg(300.0),
+            Color::from_rgb(1.0, 0.8, 0.0),
+        );
+        shapes.fill_polygon(
+            &[
+                Vector2::new(80.0, 300.0),
+                Vector2::new(250.0, 300.0),
+                Vector2::new(250.0, 480.0),
+                Vector2::new(170.0, 380.0),
+                Vector2::new(80.0, 480.0),
+            ],
+            Color::CORNFLOWER_BLUE,
+        );
+        let zigzag = [
+            Vector2::new(320.0, 480.0),
+            Vector2::new(380.0, 320.0),
+            Vector2::new(440.0, 480.0),
+            Vector2::new(500.0, 320.0),
+        ];
+        let styles = [
+            (LineJoin::Miter, LineCap::Butt),
+            (LineJoin::Bevel, LineCap::Square),
+            (LineJoin::Round, LineCap::Round),
+        ];
+        for (i, (join, cap)) in styles.iter().enumerate() {
+            let offset = Vector2::new(i as f32 * 100.0, 0.0);
+            let points: Vec<_> = zigzag.iter().map(|p| p + offset).collect();
+            let style = StrokeStyle::new(16.0).with_join(*join).with_cap(*cap);
+            shapes.stroke_polyline(&points, &style, Color::WHITE);
+        }
+
+        Ok(Self {
+            batch,
+            background,
+            shapes,
+        })
+    }
+}
+
+impl ds2d::Game for ShapesGame {
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, Color::BLACK);
+        graphics::draw(ctx, &mut self.background)?;
+        self.batch.draw_geometry(&self.shapes);
+        graphics::draw(ctx, &mut self.batch)
+    }
+
+    fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+
+    fn exit(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    stderrlog::new().quiet(false).verbosity(3).init().unwrap();
+
+    let (event_loop, mut context) = match ds2d::ContextBuilder::new()
+        .debug(true)
+        .title("Shapes")
+        .build()
+    {
+        Ok(ok) => ok,
+        Err(err) => {
+            error!("Could not create context: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let game = ShapesGame::new(&mut context).unwrap();
+
+    ds2d::run(event_loop, context, game)
+}
@@ -13,7 +13,10 @@ pub struct HelloGame {
 impl HelloGame {
     pub fn new(ctx: &mut Context) -> GameResult<Self> {
         let font_data: Vec<_> = include_bytes!("RobotoSlab-Regular.ttf").as_ref().to_owned();
-        let mut rasterizer = graphics::text::Rasterizer::new(ctx)?;
+        // Start with a small glyph cache, which grows as soon as the glyphs don't fit anymore
+        let mut rasterizer = graphics::text::Rasterizer::build()
+            .with_cache_size(256)
+            .build(ctx)?;
         let font = rasterizer.create_font(font_data)?;
         Ok(Self { rasterizer, font })
     }
@@ -25,16 +28,12 @@ impl ds2d::Game for HelloGame {
 
         let font_scale = graphics::scale_factor(ctx) as f32;
 
-        let style1 = graphics::text::Style {
-            font: self.font.clone(),
-            size: 20.0 * font_scale,
-            color: Color::WHITE,
-        };
-        let style2 = graphics::text::Style {
-            font: self.font.clone(),
-            size: 30.0 * font_scale,
-            color: Color::from_rgba(1.0, 1.0, 0.0, 1.0),
-        };
+        let style1 = graphics::text::Style::new(self.font.clone(), 20.0 * font_scale, Color::WHITE);
+        let style2 = graphics::text::Style::new(
+            self.font.clone(),
+            30.0 * font_scale,
+            Color::from_rgba(1.0, 1.0, 0.0, 1.0),
+        );
 
         let mut text = graphics::text::TextBuffer::new();
         text.add(
@@ -47,6 +46,43 @@ impl ds2d::Game for HelloGame {
             Vector2::new(200.0, 200.0),
             "Yellow text on a blue background, splendid!",
         );
+        // A dialogue box with wrapped and justified text
+        let dialogue = graphics::text::Layout::new()
+            .with_max_width(360.0 * font_scale)
+            .with_alignment(graphics::text::Alignment::Justify)
+            .with_line_spacing(1.2);
+        text.add_layout(
+            &style1,
+            &dialogue,
+            Vector2::new(20.0, 300.0),
+            "This paragraph is wrapped to fit into a box, and its lines are stretched \
+             to fill the whole width.\nA newline starts a new paragraph.",
+        );
+
+        // A label centered on the screen
+        let label = "Centered";
+        let size = graphics::screen_size(ctx);
+        let bounds = graphics::text::TextBuffer::measure(&style2, label);
+        let center = Vector2::new(size.width as f32, size.height as f32) / 2.0;
+        text.add_layout(
+            &style2,
+            &graphics::text::Layout::new(),
+            center - bounds.size() / 2.0,
+            label,
+        );
+
+        // Mixed styles within a single sentence
+        let runs = graphics::text::Markup::new().parse(
+            &style1,
+            "Press [color=#ff0][size=30]Space[/size][/color] to [u]jump[/u], \
+             [s]not[/s] to [color=#f88]fall[/color].",
+        )?;
+        text.add_runs(
+            &runs,
+            &graphics::text::Layout::new(),
+            Vector2::new(20.0, 120.0),
+        );
+
         let fps = 1.0 / ds2d::timer::average_delta(ctx).as_secs_f64();
         text.add(
             &style1,
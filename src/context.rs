@@ -1,14 +1,14 @@
 use glutin::{
-    dpi::LogicalSize,
+    dpi::{LogicalSize, PhysicalSize},
     event::{ElementState, Event, WindowEvent},
     event_loop::ControlFlow,
     event_loop::EventLoop,
 };
 use log::error;
 
-use crate::{graphics::Color, GameError};
+use crate::{graphics::Color, GameError, GameResult};
 
-use crate::graphics::context::GraphicsContext;
+use crate::graphics::context::{GraphicsContext, Surface};
 use crate::input::context::{KeyboardContext, MouseContext};
 use crate::timer::context::TimerContext;
 
@@ -106,16 +106,77 @@ impl ContextBuilder {
         let windowed_context = glutin::ContextBuilder::new()
             .with_vsync(self.vsync)
             .with_gl_debug_flag(self.debug)
+            // Needed for masking with `graphics::set_mask`
+            .with_stencil_buffer(8)
             .build_windowed(window_builder, &event_loop)?;
         // The window is dropped in case of an error
         let windowed_context = unsafe { windowed_context.make_current().map_err(|(_, err)| err)? };
-        let mut context = Context::new(windowed_context);
+        let mut context = Context::new(Surface::Windowed(windowed_context))?;
         if self.debug {
             context.graphics.init_debug();
         }
 
         Ok((event_loop, context))
     }
+
+    /// Create an OpenGL context without a window that renders into an offscreen
+    /// framebuffer of the given physical size.
+    /// The title and size of the builder are ignored, and since there is no event loop
+    /// to drive the game, frames need to be advanced manually with `ds2d::step`.
+    ///
+    /// On Linux, the BSDs and Windows, headless contexts can be created on any thread,
+    /// e.g. in tests, which don't run on the main thread. On other platforms, they
+    /// can only be created on the main thread.
+    pub fn build_headless(self, width: u32, height: u32) -> Result<Context, InitError> {
+        let event_loop = any_thread_event_loop();
+        let size = PhysicalSize::new(width, height);
+        let headless_context = glutin::ContextBuilder::new()
+            .with_gl_debug_flag(self.debug)
+            .build_headless(&event_loop, size)?;
+        let headless_context = unsafe { headless_context.make_current().map_err(|(_, err)| err)? };
+        let mut context = Context::new(Surface::Headless {
+            context: headless_context,
+            size,
+            event_loop: Box::new(event_loop),
+        })?;
+        if self.debug {
+            context.graphics.init_debug();
+        }
+
+        Ok(context)
+    }
+}
+
+/// Create an event loop that is allowed to exist outside of the main thread,
+/// where the platform supports it.
+fn any_thread_event_loop() -> EventLoop<()> {
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    {
+        use glutin::platform::unix::EventLoopExtUnix;
+        EventLoop::new_any_thread()
+    }
+    #[cfg(target_os = "windows")]
+    {
+        use glutin::platform::windows::EventLoopExtWindows;
+        EventLoop::new_any_thread()
+    }
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "windows"
+    )))]
+    {
+        EventLoop::new()
+    }
 }
 
 /// Run the game and never return.
@@ -129,6 +190,13 @@ pub fn run(
     })
 }
 
+/// Advance the game by a single frame, without an event loop.
+/// This is mostly useful for headless contexts, e.g. in automated rendering tests.
+pub fn step(context: &mut Context, game: &mut impl super::Game) -> GameResult<()> {
+    context.update_frame(game)?;
+    context.draw_frame(game)
+}
+
 /// A collection of the various systems of the game engine.
 /// This will be passed to each call into the actual game.
 #[derive(Debug)]
@@ -140,13 +208,40 @@ pub struct Context {
 }
 
 impl Context {
-    pub(crate) fn new(windowed_context: glutin::WindowedContext<glutin::PossiblyCurrent>) -> Self {
-        Self {
-            graphics: GraphicsContext::new(windowed_context),
+    pub(crate) fn new(surface: Surface) -> Result<Self, GameError> {
+        Ok(Self {
+            graphics: GraphicsContext::new(surface)?,
             keyboard: KeyboardContext::default(),
             mouse: MouseContext::default(),
             timer: TimerContext::new(),
-        }
+        })
+    }
+
+    /// Update the game state once and clear the transient input state afterwards.
+    fn update_frame(&mut self, game: &mut impl super::Game) -> GameResult<()> {
+        self.timer.tick();
+        let result = game.update(self);
+
+        // Clear transient event state
+        self.keyboard.unicode_text.clear();
+        self.mouse.scroll_x = 0.0;
+        self.mouse.scroll_y = 0.0;
+
+        result
+    }
+
+    /// Draw the game once and present the result.
+    fn draw_frame(&mut self, game: &mut impl super::Game) -> GameResult<()> {
+        // Every frame starts out drawing to the screen without any transform
+        self.graphics.set_render_target(None)?;
+        self.graphics.reset_transform();
+        // Clear the screen in a hideous magenta so that its clear if the Game forgot to clear it
+        crate::graphics::clear(self, Color::MAGENTA);
+        let result = game.draw(self);
+        // Debug lines are drawn even if the game failed to draw, since they might help finding out why
+        let debug_result = crate::graphics::debug::flush(self);
+        self.graphics.present();
+        result.and(debug_result)
     }
 
     pub(crate) fn handle_event(
@@ -230,28 +325,19 @@ impl Context {
             Event::Suspended => {}
             Event::Resumed => {}
             Event::MainEventsCleared => {
-                self.timer.tick();
-                if let Err(err) = game.update(self) {
+                if let Err(err) = self.update_frame(game) {
                     error!("Game::update failed: {}", err);
                     *control_flow = ControlFlow::Exit;
                 }
 
-                // Clear transient event state
-                self.keyboard.unicode_text.clear();
-                self.mouse.scroll_x = 0.0;
-                self.mouse.scroll_y = 0.0;
-
                 // Keep the animation running
-                self.graphics.windowed_context.window().request_redraw();
+                self.graphics.request_redraw();
             }
             Event::RedrawRequested(_) => {
-                // Clear the screen in a hideous magenta so that its clear if the Game forgot to clear it
-                crate::graphics::clear(self, Color::MAGENTA);
-                if let Err(err) = game.draw(self) {
+                if let Err(err) = self.draw_frame(game) {
                     error!("Game::draw failed: {}", err);
                     *control_flow = ControlFlow::Exit;
                 }
-                self.graphics.windowed_context.swap_buffers().unwrap();
             }
             Event::RedrawEventsCleared => {
                 *control_flow = ControlFlow::Poll;
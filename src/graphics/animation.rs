@@ -0,0 +1,267 @@
+//! Playing a sequence of texture views, e.g. the frames of a `SpriteSheet`.
+
+use std::time::Duration;
+
+use super::TextureView2D;
+use crate::Context;
+
+/// How an animation continues after its last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlaybackMode {
+    /// Start over at the first frame.
+    Loop,
+    /// Play the frames backwards down to the first frame, then forwards again.
+    PingPong,
+    /// Stay at the last frame.
+    Once,
+}
+
+/// Something that happened while advancing an animation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnimationEvent {
+    /// A frame with an event attached to it was entered.
+    Frame { frame: usize, name: String },
+    /// A looping animation started over.
+    Looped,
+    /// An animation that is played once reached its end.
+    Finished,
+}
+
+#[derive(Debug, Clone)]
+struct AnimationFrame<T> {
+    view: T,
+    duration: Duration,
+    event: Option<String>,
+}
+
+/// A sequence of frames that are shown for a certain duration each.
+///
+/// The frames are usually parts of a texture that can be drawn with `Quad::textured`,
+/// but can be anything else that changes over time.
+#[derive(Debug, Clone)]
+pub struct Animation<T = TextureView2D> {
+    frames: Vec<AnimationFrame<T>>,
+    mode: PlaybackMode,
+    /// Index of the frame that is currently shown
+    current: usize,
+    /// How long the current frame has been shown
+    elapsed: Duration,
+    /// Whether the frames are currently played backwards, only used for `PingPong`.
+    reverse: bool,
+    finished: bool,
+}
+
+impl<T> Animation<T> {
+    /// Create an animation without any frames.
+    pub fn new(mode: PlaybackMode) -> Self {
+        Self {
+            frames: Vec::new(),
+            mode,
+            current: 0,
+            elapsed: Duration::default(),
+            reverse: false,
+            finished: false,
+        }
+    }
+
+    /// Create an animation where every frame is shown for the same duration.
+    pub fn from_frames<I: IntoIterator<Item = T>>(
+        frames: I,
+        frame_duration: Duration,
+        mode: PlaybackMode,
+    ) -> Self {
+        frames.into_iter().fold(Self::new(mode), |animation, view| {
+            animation.with_frame(view, frame_duration)
+        })
+    }
+
+    /// Append a frame that is shown for the given duration.
+    /// Durations are at least one millisecond, so that an animation always makes progress.
+    pub fn with_frame(mut self, view: T, duration: Duration) -> Self {
+        self.frames.push(AnimationFrame {
+            view,
+            duration: duration.max(Duration::from_millis(1)),
+            event: None,
+        });
+        self
+    }
+
+    /// Attach an event to the frame with the given index,
+    /// which is reported by `advance` whenever that frame is entered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the frame doesn't exist.
+    pub fn with_event<S: Into<String>>(mut self, frame: usize, name: S) -> Self {
+        self.frames[frame].event = Some(name.into());
+        self
+    }
+
+    pub fn mode(&self) -> PlaybackMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: PlaybackMode) {
+        self.mode = mode
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The index of the frame that is currently shown.
+    pub fn frame_index(&self) -> usize {
+        self.current
+    }
+
+    /// The frame that is currently shown, or `None` if the animation has no frames.
+    pub fn current_frame(&self) -> Option<&T> {
+        self.frames.get(self.current).map(|frame| &frame.view)
+    }
+
+    /// The total duration of a single pass through all frames.
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.duration).sum()
+    }
+
+    /// Whether an animation that is played once has reached its end.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Start again at the first frame.
+    pub fn reset(&mut self) {
+        self.current = 0;
+        self.elapsed = Duration::default();
+        self.reverse = false;
+        self.finished = false;
+    }
+
+    /// Advance the animation by the time that passed since the last frame (see `timer::delta`).
+    pub fn update(&mut self, ctx: &mut Context) -> Vec<AnimationEvent> {
+        let delta = crate::timer::delta(ctx);
+        self.advance(delta)
+    }
+
+    /// Advance the animation by the given time,
+    /// returning the events that happened in the meantime in order.
+    pub fn advance(&mut self, delta: Duration) -> Vec<AnimationEvent> {
+        let mut events = Vec::new();
+        if self.frames.is_empty() || self.finished {
+            return events;
+        }
+        self.elapsed += delta;
+        while !self.finished && self.elapsed >= self.frames[self.current].duration {
+            self.elapsed -= self.frames[self.current].duration;
+            self.step(&mut events);
+        }
+        events
+    }
+
+    /// Move to the next frame according to the playback mode.
+    fn step(&mut self, events: &mut Vec<AnimationEvent>) {
+        let last = self.frames.len() - 1;
+        match self.mode {
+            PlaybackMode::Once => {
+                if self.current < last {
+                    self.current += 1;
+                } else {
+                    self.finished = true;
+                    self.elapsed = Duration::default();
+                    events.push(AnimationEvent::Finished);
+                    return;
+                }
+            }
+            PlaybackMode::Loop => {
+                if self.current < last {
+                    self.current += 1;
+                } else {
+                    self.current = 0;
+                    events.push(AnimationEvent::Looped);
+                }
+            }
+            PlaybackMode::PingPong => {
+                if (self.reverse && self.current == 0) || (!self.reverse && self.current == last) {
+                    self.reverse = !self.reverse;
+                    if !self.reverse {
+                        events.push(AnimationEvent::Looped);
+                    }
+                }
+                // A single frame is simply repeated
+                if last > 0 {
+                    if self.reverse {
+                        self.current -= 1;
+                    } else {
+                        self.current += 1;
+                    }
+                }
+            }
+        }
+        if let Some(name) = &self.frames[self.current].event {
+            events.push(AnimationEvent::Frame {
+                frame: self.current,
+                name: name.clone(),
+            });
+        }
+    }
+}
+
+#[test]
+fn test_animation_loop() {
+    let mut animation =
+        Animation::from_frames(0..3, Duration::from_millis(100), PlaybackMode::Loop)
+            .with_event(0, "start");
+    assert_eq!(animation.current_frame(), Some(&0));
+    assert!(animation.advance(Duration::from_millis(150)).is_empty());
+    assert_eq!(animation.frame_index(), 1);
+    assert_eq!(
+        animation.advance(Duration::from_millis(150)),
+        vec![
+            AnimationEvent::Looped,
+            AnimationEvent::Frame {
+                frame: 0,
+                name: "start".to_owned()
+            }
+        ]
+    );
+    assert_eq!(animation.frame_index(), 0);
+    // Skipping several frames at once
+    animation.advance(Duration::from_millis(1000));
+    assert_eq!(animation.frame_index(), 1);
+}
+
+#[test]
+fn test_animation_ping_pong() {
+    let mut animation =
+        Animation::from_frames(0..3, Duration::from_millis(10), PlaybackMode::PingPong);
+    let mut indices = Vec::new();
+    for _ in 0..6 {
+        animation.advance(Duration::from_millis(10));
+        indices.push(animation.frame_index());
+    }
+    assert_eq!(indices, vec![1, 2, 1, 0, 1, 2]);
+}
+
+#[test]
+fn test_animation_once() {
+    let mut animation = Animation::new(PlaybackMode::Once)
+        .with_frame('a', Duration::from_millis(10))
+        .with_frame('b', Duration::from_millis(30));
+    assert_eq!(animation.duration(), Duration::from_millis(40));
+    assert!(animation.advance(Duration::from_millis(20)).is_empty());
+    assert_eq!(
+        animation.advance(Duration::from_millis(20)),
+        vec![AnimationEvent::Finished]
+    );
+    assert!(animation.is_finished());
+    assert_eq!(animation.current_frame(), Some(&'b'));
+    assert!(animation.advance(Duration::from_millis(20)).is_empty());
+
+    animation.reset();
+    assert_eq!(animation.current_frame(), Some(&'a'));
+    assert!(!animation.is_finished());
+}
@@ -0,0 +1,519 @@
+//! Combining many small images into a single texture, so that they can be drawn in one batch.
+
+use std::{collections::HashMap, path::Path};
+
+use cgmath::Vector2;
+use image::RgbaImage;
+
+use super::{GraphicsError, Rect, SamplerOptions, Texture2D, TextureView2D};
+use crate::{Context, GameResult};
+
+/// Packs images into a single texture atlas using a skyline bottom-left packer.
+pub struct TextureAtlasBuilder {
+    images: Vec<(String, RgbaImage)>,
+    /// Empty space between neighboring images.
+    padding: u32,
+    /// Number of times the border pixels of each image are repeated around it,
+    /// to avoid bleeding of neighboring images when sampling with linear filtering.
+    extrusion: u32,
+    /// The maximum width and height of the atlas.
+    max_size: u32,
+    /// How the atlas texture is sampled.
+    sampler: SamplerOptions,
+}
+
+impl Default for TextureAtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextureAtlasBuilder {
+    pub fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            padding: 1,
+            extrusion: 0,
+            max_size: 4096,
+            // Mipmaps would blend neighboring images at small scales
+            sampler: SamplerOptions::linear(),
+        }
+    }
+
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_extrusion(mut self, extrusion: u32) -> Self {
+        self.extrusion = extrusion;
+        self
+    }
+
+    pub fn with_max_size(mut self, max_size: u32) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn with_sampler(mut self, sampler: SamplerOptions) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Add an image under the given name.
+    /// Adding another image with the same name replaces the previous one.
+    pub fn add_image<S: Into<String>>(&mut self, name: S, image: RgbaImage) {
+        let name = name.into();
+        self.images.retain(|(existing, _)| *existing != name);
+        self.images.push((name, image));
+    }
+
+    /// Add an image file under the given name.
+    pub fn add_file<S: Into<String>, P: AsRef<Path>>(
+        &mut self,
+        name: S,
+        filename: P,
+    ) -> Result<(), GraphicsError> {
+        log::trace!("Loading image {}", filename.as_ref().display());
+        let image = image::open(filename)?.into_rgba();
+        self.add_image(name, image);
+        Ok(())
+    }
+
+    /// Compute where each image is placed in the atlas, without creating the atlas image.
+    pub fn pack(&self) -> Result<AtlasLayout, GraphicsError> {
+        let border = self
+            .extrusion
+            .checked_mul(2)
+            .and_then(|border| border.checked_add(self.padding))
+            .ok_or(GraphicsError::InsufficientAtlasSize)?;
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        // Placing tall images first leaves a flatter skyline
+        order.sort_by_key(|&index| {
+            let (_, image) = &self.images[index];
+            std::cmp::Reverse((image.height(), image.width()))
+        });
+        let sizes: Option<Vec<Vector2<u32>>> = order
+            .iter()
+            .map(|&index| {
+                let (_, image) = &self.images[index];
+                Some(Vector2::new(
+                    image.width().checked_add(border)?,
+                    image.height().checked_add(border)?,
+                ))
+            })
+            .collect();
+        let sizes = sizes.ok_or(GraphicsError::InsufficientAtlasSize)?;
+
+        let (size, positions) =
+            pack_growing(&sizes, self.max_size).ok_or(GraphicsError::InsufficientAtlasSize)?;
+        let mut regions = HashMap::new();
+        for (&index, position) in order.iter().zip(positions) {
+            let (name, image) = &self.images[index];
+            let top_left = position + Vector2::new(self.extrusion, self.extrusion);
+            let region = Rect {
+                top_left,
+                bottom_right: top_left + Vector2::new(image.width(), image.height()),
+            };
+            regions.insert(name.clone(), region);
+        }
+        Ok(AtlasLayout {
+            width: size.x,
+            height: size.y,
+            regions,
+        })
+    }
+
+    /// Pack the images and compose them into a single image.
+    /// The layout and image can be saved, e.g. for packing atlases at build time.
+    pub fn pack_image(&self) -> Result<(AtlasLayout, RgbaImage), GraphicsError> {
+        let layout = self.pack()?;
+        let mut atlas = RgbaImage::new(layout.width, layout.height);
+        for (name, image) in &self.images {
+            let region = layout.regions[name];
+            image::imageops::replace(&mut atlas, image, region.top_left.x, region.top_left.y);
+            extrude(&mut atlas, region, self.extrusion);
+        }
+        Ok((layout, atlas))
+    }
+
+    /// Pack the images and upload the resulting atlas into a texture.
+    pub fn build(&self, ctx: &mut Context) -> Result<TextureAtlas, GraphicsError> {
+        let (layout, image) = self.pack_image()?;
+        let texture = Texture2D::from_image_with_sampler(ctx, &image, self.sampler)?;
+        Ok(TextureAtlas::new(texture, layout))
+    }
+}
+
+/// Where the images are placed in an atlas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtlasLayout {
+    pub width: u32,
+    pub height: u32,
+    /// The pixel coordinates of each named image in the atlas, excluding the extruded border.
+    pub regions: HashMap<String, Rect<u32>>,
+}
+
+impl AtlasLayout {
+    /// Parse an atlas description of the form
+    ///
+    /// ```text
+    /// atlas <width> <height>
+    /// <x> <y> <width> <height> <name>
+    /// ...
+    /// ```
+    ///
+    /// where empty lines and lines starting with `#` are ignored.
+    pub fn parse(description: &str) -> Result<Self, GraphicsError> {
+        let mut lines = description
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (line, header) = lines.next().ok_or(GraphicsError::InvalidAtlasDescription {
+            line: 1,
+            message: "missing atlas header",
+        })?;
+        let mut fields = header.split_whitespace();
+        if fields.next() != Some("atlas") {
+            return Err(GraphicsError::InvalidAtlasDescription {
+                line,
+                message: "expected atlas header",
+            });
+        }
+        let width = parse_number(line, fields.next())?;
+        let height = parse_number(line, fields.next())?;
+
+        let mut regions = HashMap::new();
+        for (line, entry) in lines {
+            let mut fields = entry.split_whitespace();
+            let x = parse_number(line, fields.next())?;
+            let y = parse_number(line, fields.next())?;
+            let w = parse_number(line, fields.next())?;
+            let h = parse_number(line, fields.next())?;
+            // The name is the rest of the line, which may contain whitespace itself
+            let mut name = entry;
+            for _ in 0..4 {
+                name = name
+                    .trim_start()
+                    .trim_start_matches(|c: char| !c.is_whitespace());
+            }
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(GraphicsError::InvalidAtlasDescription {
+                    line,
+                    message: "missing image name",
+                });
+            }
+            let bottom_right = match (x.checked_add(w), y.checked_add(h)) {
+                (Some(right), Some(bottom)) if right <= width && bottom <= height => {
+                    Vector2::new(right, bottom)
+                }
+                _ => {
+                    return Err(GraphicsError::InvalidAtlasDescription {
+                        line,
+                        message: "image outside of atlas",
+                    })
+                }
+            };
+            let region = Rect {
+                top_left: Vector2::new(x, y),
+                bottom_right,
+            };
+            regions.insert(name.to_owned(), region);
+        }
+        Ok(AtlasLayout {
+            width,
+            height,
+            regions,
+        })
+    }
+
+    /// Render the layout in the format understood by `parse`.
+    pub fn to_description(&self) -> String {
+        let mut names: Vec<&String> = self.regions.keys().collect();
+        names.sort();
+        let mut description = format!("atlas {} {}\n", self.width, self.height);
+        for name in names {
+            let region = self.regions[name];
+            let size = region.size();
+            description.push_str(&format!(
+                "{} {} {} {} {}\n",
+                region.top_left.x, region.top_left.y, size.x, size.y, name
+            ));
+        }
+        description
+    }
+
+    pub fn load<P: AsRef<Path>>(filename: P) -> GameResult<Self> {
+        let description = std::fs::read_to_string(filename)?;
+        Ok(Self::parse(&description)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, filename: P) -> GameResult<()> {
+        std::fs::write(filename, self.to_description())?;
+        Ok(())
+    }
+}
+
+/// A texture containing many named images.
+#[derive(Debug, Clone)]
+pub struct TextureAtlas {
+    texture: Texture2D,
+    layout: AtlasLayout,
+}
+
+impl TextureAtlas {
+    /// Combine a texture with the layout of the images it contains.
+    pub fn new(texture: Texture2D, layout: AtlasLayout) -> Self {
+        Self { texture, layout }
+    }
+
+    /// Load an atlas that has been packed ahead of time from its image and description files.
+    pub fn from_files<P: AsRef<Path>, Q: AsRef<Path>>(
+        ctx: &mut Context,
+        image_filename: P,
+        description_filename: Q,
+    ) -> GameResult<Self> {
+        let layout = AtlasLayout::load(description_filename)?;
+        let texture = Texture2D::from_file(ctx, image_filename)?;
+        Ok(Self::new(texture, layout))
+    }
+
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    pub fn layout(&self) -> &AtlasLayout {
+        &self.layout
+    }
+
+    /// The part of the atlas texture that holds the image with the given name.
+    pub fn get(&self, name: &str) -> Option<TextureView2D> {
+        let region = self.layout.regions.get(name)?;
+        Some(TextureView2D::from_pixels(self.texture.clone(), *region))
+    }
+}
+
+fn parse_number(line: usize, field: Option<&str>) -> Result<u32, GraphicsError> {
+    field
+        .and_then(|field| field.parse().ok())
+        .ok_or(GraphicsError::InvalidAtlasDescription {
+            line,
+            message: "expected a number",
+        })
+}
+
+/// Repeat the border pixels of the region `extrusion` times into its surroundings.
+fn extrude(atlas: &mut RgbaImage, region: Rect<u32>, extrusion: u32) {
+    let Rect {
+        top_left,
+        bottom_right,
+    } = region;
+    if extrusion == 0 || top_left.x == bottom_right.x || top_left.y == bottom_right.y {
+        return;
+    }
+    let x_start = top_left.x.saturating_sub(extrusion);
+    let x_end = (bottom_right.x + extrusion).min(atlas.width());
+    let y_start = top_left.y.saturating_sub(extrusion);
+    let y_end = (bottom_right.y + extrusion).min(atlas.height());
+    for y in y_start..y_end {
+        for x in x_start..x_end {
+            let source_x = x.max(top_left.x).min(bottom_right.x - 1);
+            let source_y = y.max(top_left.y).min(bottom_right.y - 1);
+            if source_x != x || source_y != y {
+                let pixel = *atlas.get_pixel(source_x, source_y);
+                atlas.put_pixel(x, y, pixel);
+            }
+        }
+    }
+}
+
+/// Pack rectangles of the given sizes into the smallest power of two area that they fit into,
+/// returning the size of the area and the top-left corner of each rectangle,
+/// or `None` if they don't fit into an area of the maximum size.
+pub(crate) fn pack_growing(
+    rects: &[Vector2<u32>],
+    max_size: u32,
+) -> Option<(Vector2<u32>, Vec<Vector2<u32>>)> {
+    if max_size == 0 {
+        return None;
+    }
+    let area = rects
+        .iter()
+        .fold(0u64, |area, s| area.saturating_add(s.x as u64 * s.y as u64));
+    let widest = rects.iter().map(|s| s.x).max().unwrap_or(1);
+    let tallest = rects.iter().map(|s| s.y).max().unwrap_or(1);
+    // Double the shorter side, giving up once the area would exceed the maximum size
+    let grow = |size: Vector2<u32>| {
+        let size = if size.x <= size.y {
+            Vector2::new(size.x.checked_mul(2)?, size.y)
+        } else {
+            Vector2::new(size.x, size.y.checked_mul(2)?)
+        };
+        if size.x <= max_size && size.y <= max_size {
+            Some(size)
+        } else {
+            None
+        }
+    };
+    // Start with the smallest power of two area that could hold all rectangles, and grow from there
+    let mut size = Vector2::new(1u32, 1u32);
+    while (size.x as u64 * size.y as u64) < area || size.x < widest || size.y < tallest {
+        size = grow(size)?;
+    }
+
+    loop {
+        if let Some(positions) = pack_skyline(size, rects) {
+            return Some((size, positions));
+        }
+        size = grow(size)?;
+    }
+}
+
+/// A horizontal segment of the skyline, i.e. the upper boundary of the area used so far.
+#[derive(Debug, Clone, Copy)]
+struct SkylineSegment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// Place rectangles of the given sizes in an area of the given size, in order,
+/// each at the lowest possible position of the skyline, preferring positions to the left.
+/// Returns the top-left corner of each rectangle, or `None` if they don't fit.
+fn pack_skyline(size: Vector2<u32>, rects: &[Vector2<u32>]) -> Option<Vec<Vector2<u32>>> {
+    let mut skyline = vec![SkylineSegment {
+        x: 0,
+        y: 0,
+        width: size.x,
+    }];
+    let mut positions = Vec::with_capacity(rects.len());
+    for rect in rects {
+        // Find the lowest position where the rectangle can rest on the skyline
+        let mut best: Option<(usize, u32)> = None;
+        for start in 0..skyline.len() {
+            let x = skyline[start].x;
+            if x + rect.x > size.x {
+                break;
+            }
+            let mut y = 0;
+            let mut covered = 0;
+            for segment in &skyline[start..] {
+                if covered >= rect.x {
+                    break;
+                }
+                y = y.max(segment.y);
+                covered += segment.width;
+            }
+            if y + rect.y <= size.y
+                && match best {
+                    None => true,
+                    Some((_, best_y)) => y < best_y,
+                }
+            {
+                best = Some((start, y));
+            }
+        }
+        let (start, y) = best?;
+        let x = skyline[start].x;
+        positions.push(Vector2::new(x, y));
+
+        // Raise the skyline below the rectangle
+        let new_segment = SkylineSegment {
+            x,
+            y: y + rect.y,
+            width: rect.x,
+        };
+        let end = x + rect.x;
+        let i = start;
+        while i < skyline.len() && skyline[i].x < end {
+            let segment_end = skyline[i].x + skyline[i].width;
+            if segment_end <= end {
+                skyline.remove(i);
+            } else {
+                skyline[i].width = segment_end - end;
+                skyline[i].x = end;
+                break;
+            }
+        }
+        skyline.insert(start, new_segment);
+        // Merge neighboring segments of the same height
+        let mut i = 0;
+        while i + 1 < skyline.len() {
+            if skyline[i].y == skyline[i + 1].y {
+                skyline[i].width += skyline[i + 1].width;
+                skyline.remove(i + 1);
+            } else {
+                i += 1;
+            }
+        }
+    }
+    Some(positions)
+}
+
+#[test]
+fn test_pack_no_overlap() {
+    let mut builder = TextureAtlasBuilder::new().with_padding(2).with_extrusion(1);
+    for i in 0..40u32 {
+        builder.add_image(
+            format!("{}", i),
+            RgbaImage::new(3 + i % 7 * 5, 2 + i % 5 * 9),
+        );
+    }
+    let layout = builder.pack().unwrap();
+    let regions: Vec<Rect<u32>> = layout.regions.values().copied().collect();
+    assert_eq!(regions.len(), 40);
+    for (i, a) in regions.iter().enumerate() {
+        assert!(a.bottom_right.x <= layout.width && a.bottom_right.y <= layout.height);
+        for b in &regions[i + 1..] {
+            // Two extruded borders of 1 pixel and the padding of 2 pixels are in between
+            let gap = 4;
+            let separated = a.bottom_right.x + gap <= b.top_left.x
+                || b.bottom_right.x + gap <= a.top_left.x
+                || a.bottom_right.y + gap <= b.top_left.y
+                || b.bottom_right.y + gap <= a.top_left.y;
+            assert!(separated, "{:?} overlaps {:?}", a, b);
+        }
+    }
+}
+
+#[test]
+fn test_pack_too_large() {
+    let mut builder = TextureAtlasBuilder::new().with_max_size(64);
+    builder.add_image("large", RgbaImage::new(65, 1));
+    assert!(builder.pack().is_err());
+
+    // Sizes beyond the range of u32 are rejected instead of overflowing
+    let max = std::u32::MAX;
+    assert!(pack_growing(&[Vector2::new(max, 1)], max).is_none());
+    assert!(pack_growing(&[Vector2::new(1 << 31, 1 << 31); 4], max).is_none());
+}
+
+#[test]
+fn test_layout_description_roundtrip() {
+    let mut builder = TextureAtlasBuilder::new();
+    builder.add_image("a face", RgbaImage::new(10, 12));
+    builder.add_image("b", RgbaImage::new(3, 30));
+    let layout = builder.pack().unwrap();
+    let parsed = AtlasLayout::parse(&layout.to_description()).unwrap();
+    assert_eq!(parsed, layout);
+}
+
+#[test]
+fn test_layout_parse_malformed() {
+    // Fields may be separated by any amount of whitespace
+    let layout = AtlasLayout::parse("atlas 64 64\n1  2\t3 4   a face \n").unwrap();
+    assert_eq!(
+        layout.regions["a face"],
+        Rect {
+            top_left: Vector2::new(1, 2),
+            bottom_right: Vector2::new(4, 6),
+        }
+    );
+    // Overflowing regions are rejected instead of wrapping around
+    assert!(AtlasLayout::parse("atlas 64 64\n4294967295 0 2 2 a\n").is_err());
+    assert!(AtlasLayout::parse("atlas 64 64\n0 1 2 4294967295 a\n").is_err());
+    assert!(AtlasLayout::parse("atlas 64 64\n0 0 1 1\n").is_err());
+}
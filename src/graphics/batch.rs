@@ -1,36 +1,54 @@
 //! Implements batched rendering of sprites.
 
-use cgmath::{ElementWise, Rad, Vector2};
+use std::{cell::Cell, rc::Rc};
+
+use cgmath::{ElementWise, Matrix3, Rad, SquareMatrix, Vector2};
 
 use super::{
-    context::{Buffer, Program, Texture, VertexArray},
+    context::{BackendError, Buffer, Texture, VertexArray},
     primitives::BasicPipeline2D,
     primitives::BasicVertex2D,
     primitives::{Pipeline, VertexData},
-    BlendMode, Color, Drawable, GraphicsError, Rect, RenderState, Texture2D, TextureView2D,
+    tessellation::Geometry,
+    BlendMode, Color, Drawable, GraphicsError, Rect, RenderState, ShaderEffect, Texture2D,
+    TextureView2D,
 };
 use crate::{CheckGl, Context, GameResult};
 
 pub struct BatchRender {
     pipeline: BasicPipeline2D,
+    /// Streaming vertex buffer that is refilled every frame.
     vbo: Buffer,
-    // TODO: try out whether using an index buffer is an improvement
-    //ebo: Buffer,
+    /// Size of the storage of the vertex buffer in bytes.
+    vbo_capacity: usize,
+    /// The index buffer for drawing quads, shared between all batch renderers.
+    #[allow(unused)]
+    ebo: Rc<Buffer>,
     vao: VertexArray,
 
     batches: Vec<Batch>,
     vertices: Vec<BasicVertex2D>,
+    /// The effect used for primitives that are added subsequently.
+    effect: Option<ShaderEffect>,
+    /// The current transform of the graphics context, which is applied to primitives
+    /// when they are added.
+    transform: Rc<Cell<Matrix3<f32>>>,
+    /// The current scissor rectangle of the graphics context,
+    /// which is recorded for primitives when they are added.
+    scissor: Rc<Cell<Option<Rect<u32>>>>,
 }
 
 impl BatchRender {
     pub fn new(ctx: &mut Context) -> Result<Self, GraphicsError> {
         let pipeline = BasicPipeline2D::new(ctx)?;
         let vbo = Buffer::new()?;
-        //let ebo = Buffer::new()?;
+        let ebo = ctx.graphics.quad_index_buffer()?;
         let vao = VertexArray::new()?;
 
         VertexArray::bind(&vao)?;
         Buffer::bind(gl::ARRAY_BUFFER, &vbo)?;
+        // NOTE: the ELEMENT_ARRAY_BUFFER binding point is a property of the VAO
+        Buffer::bind(gl::ELEMENT_ARRAY_BUFFER, &ebo)?;
         unsafe {
             for attrib in BasicVertex2D::attributes() {
                 attrib.set_pointer()?;
@@ -42,54 +60,129 @@ impl BatchRender {
         Buffer::unbind(gl::ARRAY_BUFFER)?;
         VertexArray::unbind()?;
 
-        // for attrib in BasicVertex2D::attributes() {
-        //     attrib.set_pointer()?;
-        //     attrib.enable()?;
-        // }
-
         Ok(Self {
             pipeline,
             vbo,
-            //ebo,
+            vbo_capacity: 0,
+            ebo,
             vao,
 
             batches: Vec::new(),
             vertices: Vec::new(),
+            effect: None,
+            transform: ctx.graphics.transform.clone(),
+            scissor: ctx.graphics.scissor.clone(),
         })
     }
 
+    /// The shader effect that primitives drawn afterwards are rendered with.
+    pub fn effect(&self) -> Option<&ShaderEffect> {
+        self.effect.as_ref()
+    }
+
+    /// Render all primitives that are drawn afterwards with the given shader effect,
+    /// or the default shaders if `None`.
+    pub fn set_effect(&mut self, effect: Option<ShaderEffect>) {
+        self.effect = effect;
+    }
+
+    /// Add a quad, transformed by the current transform of the graphics context
+    /// (see `graphics::push_transform`).
     pub fn draw_quad<Q: Into<Quad>>(&mut self, quad: Q) {
         let quad: Quad = quad.into();
-        // TODO: use index buffer?
-        self.vertices.push(quad.vertices[0]);
-        self.vertices.push(quad.vertices[2]);
-        self.vertices.push(quad.vertices[1]);
-        self.vertices.push(quad.vertices[0]);
-        self.vertices.push(quad.vertices[3]);
-        self.vertices.push(quad.vertices[2]);
-        self.update_batch(quad.key, 6);
+        let start = self.vertices.len();
+        // The triangles are formed by the shared quad index buffer
+        self.vertices.extend_from_slice(&quad.vertices);
+        self.transform_vertices(start);
+        self.update_batch(quad.key, 4);
     }
 
+    /// Add a triangle, transformed by the current transform of the graphics context
+    /// (see `graphics::push_transform`).
     pub fn draw_triangle(&mut self, corners: [Vector2<f32>; 3], color: Color) {
+        let start = self.vertices.len();
         for corner in &corners {
-            self.vertices.push(BasicVertex2D {
-                position: *corner.as_ref(),
-                color: color.into(),
-                // no texture
-                tex_coord: [0.0; 2],
-            });
+            self.push_untextured(*corner, color);
         }
+        self.transform_vertices(start);
+        self.update_batch(BatchKey::untextured_triangles(), 3);
+    }
 
-        let key = BatchKey {
-            texture: None,
-            blend: None,
-        };
-        self.update_batch(key, 3);
+    /// Draw a filled convex polygon with the corners given in clockwise or counter-clockwise order.
+    /// Polygons with less than three corners are ignored.
+    pub fn draw_polygon(&mut self, corners: &[Vector2<f32>], color: Color) {
+        if corners.len() < 3 {
+            return;
+        }
+        let start = self.vertices.len();
+        // Emit the triangle fan around the first corner as individual triangles
+        for pair in corners[1..].windows(2) {
+            self.push_untextured(corners[0], color);
+            self.push_untextured(pair[0], color);
+            self.push_untextured(pair[1], color);
+        }
+        self.transform_vertices(start);
+        self.update_batch(BatchKey::untextured_triangles(), 3 * (corners.len() - 2));
+    }
+
+    /// Add the triangles of a tessellated shape, transformed by the current transform
+    /// of the graphics context.
+    pub fn draw_geometry(&mut self, geometry: &Geometry) {
+        let start = self.vertices.len();
+        self.vertices.extend(
+            geometry
+                .indices
+                .iter()
+                .map(|index| geometry.vertices[*index as usize]),
+        );
+        self.transform_vertices(start);
+        let num_vertices = self.vertices.len() - start;
+        self.update_batch(BatchKey::untextured_triangles(), num_vertices);
+    }
+
+    fn push_untextured(&mut self, position: Vector2<f32>, color: Color) {
+        self.vertices.push(BasicVertex2D {
+            position: position.into(),
+            color: color.into(),
+            // no texture
+            tex_coord: [0.0; 2],
+        });
+    }
+
+    /// Apply the current transform to the positions of the vertices starting at the given index.
+    fn transform_vertices(&mut self, start: usize) {
+        let transform = self.transform.get();
+        if transform.is_identity() {
+            return;
+        }
+        for vertex in &mut self.vertices[start..] {
+            let position = Vector2::from(vertex.position).extend(1.0);
+            vertex.position = (transform * position).truncate().into();
+        }
     }
 
-    /// Ensures that the most last batch in the `batches` vector matches
-    /// the given key, creating a new batch if necessary.
-    fn update_batch(&mut self, key: BatchKey, num_vertices: usize) {
+    /// Upload all queued vertices into the bound vertex buffer, growing its storage if necessary.
+    /// The previous storage is orphaned, so that the driver doesn't need to wait for
+    /// draw calls of the previous frame that might still be using it.
+    unsafe fn upload_vertices(&mut self) -> Result<(), BackendError> {
+        let max_size = std::i32::MAX as usize;
+        let size = std::mem::size_of_val(&self.vertices[..]);
+        if size > max_size {
+            return Err(BackendError::TooLarge);
+        }
+        if size > self.vbo_capacity {
+            self.vbo_capacity = size.next_power_of_two().min(max_size);
+            log::debug!("Growing batch vertex buffer to {} bytes", self.vbo_capacity);
+        }
+        Buffer::alloc(gl::ARRAY_BUFFER, self.vbo_capacity as i32, gl::STREAM_DRAW)?;
+        Buffer::sub_data(gl::ARRAY_BUFFER, 0, &self.vertices)
+    }
+
+    /// Ensures that the most last batch in the `batches` vector matches the given key
+    /// combined with the current effect and scissor, creating a new batch if necessary.
+    fn update_batch(&mut self, mut key: BatchKey, num_vertices: usize) {
+        key.effect = self.effect.clone();
+        key.scissor = self.scissor.get();
         let max_len = std::i32::MAX as usize;
         let (new_batch, next_vertex) = self.batches.last().map_or((true, 0), |batch| {
             let same_key = batch.key == key;
@@ -237,6 +330,9 @@ impl QuadBuilder {
             key: BatchKey {
                 texture: self.texture.map(|view| view.texture),
                 blend: Some(BlendMode::alpha()),
+                effect: None,
+                scissor: None,
+                primitive: Primitive::Quads,
             },
             vertices,
         }
@@ -254,6 +350,30 @@ impl From<QuadBuilder> for Quad {
 struct BatchKey {
     texture: Option<Texture2D>,
     blend: Option<BlendMode>,
+    effect: Option<ShaderEffect>,
+    scissor: Option<Rect<u32>>,
+    primitive: Primitive,
+}
+
+impl BatchKey {
+    fn untextured_triangles() -> Self {
+        BatchKey {
+            texture: None,
+            blend: None,
+            effect: None,
+            scissor: None,
+            primitive: Primitive::Triangles,
+        }
+    }
+}
+
+/// How the vertices of a batch form triangles.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Primitive {
+    /// Every three vertices form a triangle.
+    Triangles,
+    /// Every four vertices form a quad, which is drawn through the quad index buffer.
+    Quads,
 }
 
 struct Batch {
@@ -270,6 +390,22 @@ impl Batch {
     }
 }
 
+/// The number of quads covered by the shared quad index buffer,
+/// chosen such that all vertex indices fit into an `u16`.
+pub(crate) const QUAD_INDEX_BUFFER_QUADS: usize = (std::u16::MAX as usize + 1) / 4;
+
+/// Generate the indices for drawing each group of four consecutive vertices
+/// as a quad consisting of two triangles.
+pub(crate) fn quad_indices(num_quads: usize) -> Vec<u16> {
+    let mut indices = Vec::with_capacity(num_quads * 6);
+    for quad in 0..num_quads {
+        let first = (quad * 4) as u16;
+        // The corners are given in clockwise order starting at the top-left
+        indices.extend_from_slice(&[first, first + 2, first + 1, first, first + 3, first + 2]);
+    }
+    indices
+}
+
 impl Drawable for BatchRender {
     fn draw(&mut self, ctx: &mut Context, state: RenderState) -> GameResult<()> {
         let mut last_tex_id = 0;
@@ -281,8 +417,19 @@ impl Drawable for BatchRender {
 
         self.vao.bind()?;
         Buffer::bind(gl::ARRAY_BUFFER, &self.vbo)?;
+        if !self.vertices.is_empty() {
+            // The vertices of all batches are uploaded at once
+            unsafe { self.upload_vertices()? };
+        }
 
+        // Batches are clipped to the scissor rectangle that was current when they were added
+        let current_scissor = ctx.graphics.scissor.get();
+        let mut last_scissor = current_scissor;
         for batch in self.batches.drain(..) {
+            if batch.key.scissor != last_scissor {
+                last_scissor = batch.key.scissor;
+                ctx.graphics.apply_scissor(last_scissor)?;
+            }
             if let Some(ref tex) = batch.key.texture {
                 let cur_tex_id = tex.raw().id();
                 if last_tex_id != cur_tex_id {
@@ -291,24 +438,43 @@ impl Drawable for BatchRender {
                 }
                 // 0 refers to the texture unit, not the texture id
                 self.pipeline.set_texture(Some(0));
+                self.pipeline.set_texture_format(tex.format());
             } else {
                 self.pipeline.set_texture(None);
             }
             self.pipeline.set_blend_mode(batch.key.blend);
+            self.pipeline.set_effect(batch.key.effect.clone());
             self.pipeline.apply(ctx)?;
-            // TODO: better buffer handling
-            unsafe {
-                Buffer::data(
-                    gl::ARRAY_BUFFER,
-                    &self.vertices[batch.start..batch.end],
-                    gl::STATIC_DRAW,
-                )?;
-                // Batches are limited so that the len always fits into i32
-                gl::DrawArrays(gl::TRIANGLES, 0, batch.len() as i32);
+            // The whole vertex buffer is limited to i32::MAX bytes,
+            // so the vertex indices always fit into i32
+            match batch.key.primitive {
+                Primitive::Triangles => unsafe {
+                    gl::DrawArrays(gl::TRIANGLES, batch.start as i32, batch.len() as i32);
+                },
+                Primitive::Quads => {
+                    // The quad index buffer only covers a limited number of quads
+                    let max_vertices = QUAD_INDEX_BUFFER_QUADS * 4;
+                    let mut first = batch.start;
+                    while first < batch.end {
+                        let num_vertices = (batch.end - first).min(max_vertices);
+                        unsafe {
+                            gl::DrawElementsBaseVertex(
+                                gl::TRIANGLES,
+                                (num_vertices / 4 * 6) as i32,
+                                gl::UNSIGNED_SHORT,
+                                std::ptr::null(),
+                                first as i32,
+                            );
+                        }
+                        first += num_vertices;
+                    }
+                }
             }
         }
+        if last_scissor != current_scissor {
+            ctx.graphics.apply_scissor(current_scissor)?;
+        }
         VertexArray::unbind()?;
-        Program::unbind()?;
         unsafe {
             Texture::unbind(gl::TEXTURE_2D)?;
         }
@@ -317,3 +483,10 @@ impl Drawable for BatchRender {
         Ok(())
     }
 }
+
+#[test]
+fn test_quad_indices() {
+    assert_eq!(quad_indices(2), vec![0, 2, 1, 0, 3, 2, 4, 6, 5, 4, 7, 6]);
+    let all = quad_indices(QUAD_INDEX_BUFFER_QUADS);
+    assert_eq!(all.iter().copied().max(), Some(std::u16::MAX));
+}
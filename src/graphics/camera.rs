@@ -0,0 +1,335 @@
+//! A camera for scrolling, zooming and rotating the view of a 2D world.
+
+use std::time::Duration;
+
+use cgmath::{Matrix3, Rad, Vector2};
+use glutin::dpi::{PhysicalPosition, PhysicalSize};
+
+use super::{transform, Rect, RenderState};
+use crate::Context;
+
+/// Determines which part of the world is visible and where on the render target it is shown.
+///
+/// The camera position is the world coordinate that appears in the center of the viewport.
+/// A zoom factor of 2 makes everything appear twice as large, and rotating the camera
+/// rotates the world in the opposite direction.
+#[derive(Debug, Clone)]
+pub struct Camera2D {
+    position: Vector2<f32>,
+    zoom: f32,
+    rotation: Rad<f32>,
+    /// The part of the render target in pixels the camera maps to,
+    /// or `None` for the whole render target.
+    viewport: Option<Rect<f32>>,
+    /// The area of the world the visible part is confined to.
+    bounds: Option<Rect<f32>>,
+
+    /// The position the camera is moving towards in `update`.
+    follow_target: Option<Vector2<f32>>,
+    /// How quickly the camera catches up with the followed position,
+    /// as the fraction of the remaining distance that is left after one second.
+    follow_lag: f32,
+
+    shake_intensity: f32,
+    shake_duration: Duration,
+    shake_remaining: Duration,
+    shake_offset: Vector2<f32>,
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Camera2D {
+    /// A camera at the world origin that covers the whole render target without zoom or rotation.
+    pub fn new() -> Self {
+        Self {
+            position: Vector2::new(0.0, 0.0),
+            zoom: 1.0,
+            rotation: Rad(0.0),
+            viewport: None,
+            bounds: None,
+            follow_target: None,
+            follow_lag: 0.01,
+            shake_intensity: 0.0,
+            shake_duration: Duration::default(),
+            shake_remaining: Duration::default(),
+            shake_offset: Vector2::new(0.0, 0.0),
+        }
+    }
+
+    pub fn with_position(mut self, position: Vector2<f32>) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    pub fn with_rotation(mut self, rotation: Rad<f32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_viewport(mut self, viewport: Rect<f32>) -> Self {
+        self.viewport = Some(viewport);
+        self
+    }
+
+    pub fn with_bounds(mut self, bounds: Rect<f32>) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Set the fraction of the distance to a followed position that remains after one second,
+    /// e.g. `0.0` to snap to it immediately and values close to `1.0` for a very slow camera.
+    pub fn with_follow_lag(mut self, lag: f32) -> Self {
+        self.follow_lag = lag;
+        self
+    }
+
+    pub fn position(&self) -> Vector2<f32> {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Vector2<f32>) {
+        self.position = position
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom
+    }
+
+    pub fn rotation(&self) -> Rad<f32> {
+        self.rotation
+    }
+
+    pub fn set_rotation(&mut self, rotation: Rad<f32>) {
+        self.rotation = rotation
+    }
+
+    /// The part of the render target in pixels the camera maps to,
+    /// or `None` if it covers the whole render target.
+    pub fn viewport(&self) -> Option<Rect<f32>> {
+        self.viewport
+    }
+
+    /// Note that drawing is not clipped to the viewport.
+    pub fn set_viewport(&mut self, viewport: Option<Rect<f32>>) {
+        self.viewport = viewport
+    }
+
+    pub fn bounds(&self) -> Option<Rect<f32>> {
+        self.bounds
+    }
+
+    /// Confine the visible part of the world to the given area when updating the camera.
+    /// The rotation of the camera is not taken into account.
+    pub fn set_bounds(&mut self, bounds: Option<Rect<f32>>) {
+        self.bounds = bounds
+    }
+
+    /// Smoothly move towards the given position in subsequent updates,
+    /// or stop following if `None`.
+    pub fn follow(&mut self, target: Option<Vector2<f32>>) {
+        self.follow_target = target
+    }
+
+    /// Shake the camera by up to `intensity` world units,
+    /// fading out over the given duration.
+    /// The shake never moves the view beyond the bounds of the camera.
+    pub fn shake(&mut self, intensity: f32, duration: Duration) {
+        self.shake_intensity = intensity;
+        self.shake_duration = duration;
+        self.shake_remaining = duration;
+    }
+
+    /// Advance following and shaking by the time since the last update,
+    /// and keep the view within the bounds.
+    pub fn update(&mut self, ctx: &mut Context) {
+        let delta = crate::timer::delta(ctx);
+        let viewport = self.resolve_viewport(ctx.graphics.target_size());
+        self.advance(delta, viewport.size());
+    }
+
+    /// The transformation from world coordinates to pixel coordinates of the render target.
+    pub fn view_matrix(&self, target_size: PhysicalSize<u32>) -> Matrix3<f32> {
+        let viewport = self.resolve_viewport(target_size);
+        transform::translate(viewport.center())
+            * transform::rotate(-self.rotation)
+            * transform::scale(Vector2::new(self.zoom, self.zoom))
+            * transform::translate(-(self.position + self.shake_offset))
+    }
+
+    /// The transformation from pixel coordinates of the render target to world coordinates.
+    pub fn inverse_view_matrix(&self, target_size: PhysicalSize<u32>) -> Matrix3<f32> {
+        let viewport = self.resolve_viewport(target_size);
+        transform::translate(self.position + self.shake_offset)
+            * transform::scale(Vector2::new(1.0 / self.zoom, 1.0 / self.zoom))
+            * transform::rotate(self.rotation)
+            * transform::translate(-viewport.center())
+    }
+
+    /// The render state for drawing the world as seen through this camera
+    /// onto the current render target.
+    pub fn render_state(&self, ctx: &mut Context) -> RenderState {
+        RenderState {
+            transform: ctx.graphics.pixel_projection * self.view_matrix(ctx.graphics.target_size()),
+        }
+    }
+
+    /// Convert a position on the current render target, e.g. from `input::mouse::position`,
+    /// to world coordinates.
+    pub fn screen_to_world(
+        &self,
+        ctx: &mut Context,
+        position: PhysicalPosition<f64>,
+    ) -> Vector2<f32> {
+        let screen = Vector2::new(position.x as f32, position.y as f32);
+        (self.inverse_view_matrix(ctx.graphics.target_size()) * screen.extend(1.0)).truncate()
+    }
+
+    /// Convert world coordinates to a position on the current render target.
+    pub fn world_to_screen(
+        &self,
+        ctx: &mut Context,
+        position: Vector2<f32>,
+    ) -> PhysicalPosition<f64> {
+        let screen = (self.view_matrix(ctx.graphics.target_size()) * position.extend(1.0)).xy();
+        PhysicalPosition::new(screen.x as f64, screen.y as f64)
+    }
+
+    fn resolve_viewport(&self, target_size: PhysicalSize<u32>) -> Rect<f32> {
+        self.viewport.unwrap_or_else(|| Rect {
+            top_left: Vector2::new(0.0, 0.0),
+            bottom_right: Vector2::new(target_size.width as f32, target_size.height as f32),
+        })
+    }
+
+    fn advance(&mut self, delta: Duration, viewport_size: Vector2<f32>) {
+        let dt = delta.as_secs_f32();
+        if let Some(target) = self.follow_target {
+            let remaining = self.follow_lag.powf(dt);
+            self.position = target + (self.position - target) * remaining;
+        }
+
+        self.shake_remaining = self.shake_remaining.checked_sub(delta).unwrap_or_default();
+        self.shake_offset = if self.shake_remaining > Duration::default() {
+            let fade = self.shake_remaining.as_secs_f32() / self.shake_duration.as_secs_f32();
+            // Incommensurable frequencies make the motion look irregular
+            let t = self.shake_remaining.as_secs_f32();
+            Vector2::new((t * 97.0).sin(), (t * 71.0 + 1.3).sin()) * self.shake_intensity * fade
+        } else {
+            Vector2::new(0.0, 0.0)
+        };
+
+        if let Some(bounds) = self.bounds {
+            let size = viewport_size / self.zoom;
+            self.position = clamp_to_bounds(self.position, size, bounds);
+            // Near the edges, the shake is damped so that it doesn't reveal what lies beyond
+            self.shake_offset =
+                clamp_to_bounds(self.position + self.shake_offset, size, bounds) - self.position;
+        }
+    }
+}
+
+/// Move the center of a view of the given size so that the view lies within the bounds,
+/// or center it on the bounds along an axis where it is larger than the bounds.
+fn clamp_to_bounds(center: Vector2<f32>, size: Vector2<f32>, bounds: Rect<f32>) -> Vector2<f32> {
+    let clamp = |center: f32, half: f32, min: f32, max: f32| {
+        if max - min <= 2.0 * half {
+            (min + max) / 2.0
+        } else {
+            center.max(min + half).min(max - half)
+        }
+    };
+    Vector2::new(
+        clamp(
+            center.x,
+            size.x / 2.0,
+            bounds.top_left.x,
+            bounds.bottom_right.x,
+        ),
+        clamp(
+            center.y,
+            size.y / 2.0,
+            bounds.top_left.y,
+            bounds.bottom_right.y,
+        ),
+    )
+}
+
+#[test]
+fn test_camera_roundtrip() {
+    let camera = Camera2D::new()
+        .with_position(Vector2::new(100.0, -50.0))
+        .with_zoom(2.0)
+        .with_rotation(Rad(0.5));
+    let size = PhysicalSize::new(800, 600);
+    // The camera position ends up in the center of the viewport
+    let center = (camera.view_matrix(size) * Vector2::new(100.0, -50.0).extend(1.0)).xy();
+    assert!((center - Vector2::new(400.0, 300.0)).x.abs() < 1e-3);
+    assert!((center - Vector2::new(400.0, 300.0)).y.abs() < 1e-3);
+
+    let world = Vector2::new(37.0, 12.0);
+    let screen = camera.view_matrix(size) * world.extend(1.0);
+    let back = (camera.inverse_view_matrix(size) * screen).xy();
+    assert!((back - world).x.abs() < 1e-3 && (back - world).y.abs() < 1e-3);
+}
+
+#[test]
+fn test_clamp_to_bounds() {
+    let bounds = Rect {
+        top_left: Vector2::new(0.0, 0.0),
+        bottom_right: Vector2::new(100.0, 50.0),
+    };
+    let size = Vector2::new(20.0, 80.0);
+    assert_eq!(
+        clamp_to_bounds(Vector2::new(-10.0, 10.0), size, bounds),
+        Vector2::new(10.0, 25.0)
+    );
+    assert_eq!(
+        clamp_to_bounds(Vector2::new(95.0, 10.0), size, bounds),
+        Vector2::new(90.0, 25.0)
+    );
+    assert_eq!(
+        clamp_to_bounds(Vector2::new(50.0, 10.0), size, bounds),
+        Vector2::new(50.0, 25.0)
+    );
+}
+
+#[test]
+fn test_camera_follow() {
+    let mut camera = Camera2D::new().with_follow_lag(0.25);
+    camera.follow(Some(Vector2::new(100.0, 0.0)));
+    camera.advance(Duration::from_millis(500), Vector2::new(800.0, 600.0));
+    // Half of the remaining distance is left after half a second
+    assert!((camera.position().x - 50.0).abs() < 1e-3);
+    camera.advance(Duration::from_millis(500), Vector2::new(800.0, 600.0));
+    assert!((camera.position().x - 75.0).abs() < 1e-3);
+}
+
+#[test]
+fn test_camera_shake_within_bounds() {
+    let bounds = Rect {
+        top_left: Vector2::new(0.0, 0.0),
+        bottom_right: Vector2::new(100.0, 100.0),
+    };
+    let size = Vector2::new(20.0, 20.0);
+    let mut camera = Camera2D::new().with_bounds(bounds);
+    camera.set_position(Vector2::new(10.0, 10.0));
+    camera.shake(5.0, Duration::from_secs(1));
+    for _ in 0..50 {
+        camera.advance(Duration::from_millis(10), size);
+        let center = camera.position() + camera.shake_offset;
+        assert_eq!(clamp_to_bounds(center, size, bounds), center);
+    }
+}
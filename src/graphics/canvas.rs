@@ -0,0 +1,122 @@
+//! Render targets that can be drawn to instead of the screen.
+
+use glutin::dpi::PhysicalSize;
+use std::rc::Rc;
+
+use super::{
+    context::{BackendError, Framebuffer, Renderbuffer, Texture},
+    GraphicsError, SamplerOptions, Texture2D, TextureFormat, TextureView2D,
+};
+use crate::Context;
+
+/// A reference counted render target backed by a framebuffer with a texture as color attachment.
+///
+/// While a canvas is set as render target with `graphics::set_render_target`, all drawing
+/// ends up in its texture, which can then be drawn like any other texture.
+#[derive(Debug, Clone)]
+pub struct Canvas {
+    inner: Rc<CanvasImpl>,
+}
+
+#[derive(Debug)]
+struct CanvasImpl {
+    framebuffer: Framebuffer,
+    texture: Texture2D,
+    /// Stencil buffer for masking, with an unused depth buffer because that's the common format.
+    #[allow(unused)]
+    depth_stencil: Renderbuffer,
+}
+
+impl PartialEq for Canvas {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+            || self.inner.framebuffer.id() == other.inner.framebuffer.id()
+    }
+}
+
+impl Eq for Canvas {}
+
+impl Canvas {
+    /// Create a canvas with the given size in pixels and undefined contents.
+    ///
+    /// Its texture is sampled linearly without mipmaps,
+    /// which can be changed through `Texture2D::set_sampler`.
+    pub fn new(ctx: &mut Context, width: u32, height: u32) -> Result<Self, GraphicsError> {
+        if width.max(height) >= std::i32::MAX as u32 {
+            return Err(GraphicsError::Backend(BackendError::TooLarge));
+        }
+
+        let raw = Texture::new()?;
+        unsafe {
+            Texture::bind(gl::TEXTURE_2D, &raw)?;
+            Texture::alloc2d_rgba(gl::TEXTURE_2D, width as i32, height as i32)?;
+            Texture::unbind(gl::TEXTURE_2D)?;
+        }
+
+        let depth_stencil = Renderbuffer::new()?;
+        unsafe {
+            Renderbuffer::bind(&depth_stencil)?;
+            Renderbuffer::storage(gl::DEPTH24_STENCIL8, width as i32, height as i32)?;
+            Renderbuffer::unbind()?;
+        }
+
+        let framebuffer = Framebuffer::new()?;
+        let result = unsafe {
+            Framebuffer::bind(gl::FRAMEBUFFER, &framebuffer)?;
+            Framebuffer::texture2d(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, &raw)
+                .and_then(|_| {
+                    Framebuffer::renderbuffer(
+                        gl::FRAMEBUFFER,
+                        gl::DEPTH_STENCIL_ATTACHMENT,
+                        &depth_stencil,
+                    )
+                })
+                .and_then(|_| Framebuffer::check_status(gl::FRAMEBUFFER))
+        };
+        // Restore whatever was bound before, even if the framebuffer turned out to be unusable
+        ctx.graphics.bind_render_target()?;
+        result?;
+
+        Ok(Canvas {
+            inner: Rc::new(CanvasImpl {
+                framebuffer,
+                // There are no mipmaps until they are requested explicitly
+                texture: Texture2D::from_raw(
+                    raw,
+                    width,
+                    height,
+                    TextureFormat::Rgba8,
+                    SamplerOptions::linear(),
+                )?,
+                depth_stencil,
+            }),
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.inner.texture.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.inner.texture.height()
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.width(), self.height())
+    }
+
+    /// The texture that holds the contents of this canvas.
+    pub fn texture(&self) -> &Texture2D {
+        &self.inner.texture
+    }
+
+    pub(crate) fn framebuffer(&self) -> &Framebuffer {
+        &self.inner.framebuffer
+    }
+}
+
+impl From<&Canvas> for TextureView2D {
+    fn from(canvas: &Canvas) -> Self {
+        canvas.texture().clone().into()
+    }
+}
@@ -1,5 +1,5 @@
 /// A color represented as normalized 32 bit float RGBA value.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 #[repr(C)]
 pub struct Color {
     /// red component
@@ -21,6 +21,7 @@ impl Color {
     pub const CORNFLOWER_BLUE: Color =
         Color::from_rgba(100.0 / 255.0, 149.0 / 255.0, 237.0 / 255.0, 1.0);
     pub const MAGENTA: Color = Color::from_rgba(1.0, 0.0, 1.0, 1.0);
+    pub const TRANSPARENT: Color = Color::from_rgba(0.0, 0.0, 0.0, 0.0);
 
     pub const fn from_rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
         Self { r, g, b, a }
@@ -43,6 +44,28 @@ impl Color {
         Self::from_rgba_u8(r, g, b, 255)
     }
 
+    /// Parse a color in hexadecimal notation, i.e. `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`,
+    /// where the leading `#` is optional.
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digits = match hex.len() {
+            3 | 4 => 1,
+            6 | 8 => 2,
+            _ => return None,
+        };
+        let mut components = [255u8; 4];
+        for (index, component) in components.iter_mut().enumerate().take(hex.len() / digits) {
+            let value = u8::from_str_radix(&hex[index * digits..(index + 1) * digits], 16).ok()?;
+            // A single digit is repeated, so that `f` becomes `ff`
+            *component = if digits == 1 { value * 17 } else { value };
+        }
+        let [r, g, b, a] = components;
+        Some(Self::from_rgba_u8(r, g, b, a))
+    }
+
     pub fn to_rgba_u8(self) -> [u8; 4] {
         [
             component_f32_to_u8(self.r),
@@ -80,3 +103,24 @@ impl From<Color> for cgmath::Vector4<f32> {
         cgmath::Vector4::new(color.r, color.g, color.b, color.a)
     }
 }
+
+#[test]
+fn test_color_from_hex() {
+    assert_eq!(Color::from_hex("#ff0000"), Some(Color::RED));
+    assert_eq!(Color::from_hex("fff"), Some(Color::WHITE));
+    assert_eq!(
+        Color::from_hex("#0f08").map(Color::to_rgba_u8),
+        Some([0, 255, 0, 136])
+    );
+    assert_eq!(
+        Color::from_hex("#11223344").map(Color::to_rgba_u8),
+        Some([0x11, 0x22, 0x33, 0x44])
+    );
+    assert_eq!(
+        Color::from_hex("#ff00"),
+        Some(Color::from_rgba_u8(255, 255, 0, 0))
+    );
+    assert_eq!(Color::from_hex("#12345"), None);
+    assert_eq!(Color::from_hex("#ggg"), None);
+    assert_eq!(Color::from_hex("+f+f+f"), None);
+}
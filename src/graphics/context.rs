@@ -1,22 +1,128 @@
 //! Implementation of the graphics stack.
 //! A lot of this assumes the presence of the global and all-encompassing GL context.
 
-use cgmath::{Matrix3, Vector2, Vector4};
+use cgmath::{Matrix3, SquareMatrix, Vector2, Vector3, Vector4};
 use gl::types::{GLboolean, GLenum, GLint, GLsizei, GLuint};
-use glutin::{dpi::PhysicalSize, WindowedContext};
-use std::{collections::HashMap, fmt::Display, rc::Rc};
+use glutin::{dpi::PhysicalSize, event_loop::EventLoop, PossiblyCurrent, WindowedContext};
+use std::{cell::Cell, collections::HashMap, fmt::Display, rc::Rc};
 
-use super::Color;
+use super::{Color, TextureFormat};
 
 #[derive(Debug)]
 pub(crate) struct GraphicsContext {
-    pub windowed_context: Rc<WindowedContext<glutin::PossiblyCurrent>>,
     pub screen_size: PhysicalSize<u32>,
     pub scale_factor: f64,
     pub can_debug: bool,
+    /// The largest width and height of a texture supported by the driver.
+    pub max_texture_size: u32,
     /// Converting pixel coordinates to normalized device coordinates
     pub pixel_projection: Matrix3<f32>,
     pub blend_mode: Option<super::blend::BlendMode>,
+    /// The framebuffer that is rendered to in place of the default framebuffer
+    /// when there is no window.
+    pub offscreen: Option<OffscreenTarget>,
+    /// The canvas that is currently drawn to instead of the screen.
+    pub render_target: Option<super::Canvas>,
+    /// Linked programs, keyed by the source of their vertex and fragment shader.
+    pub programs: HashMap<(&'static str, &'static str), Rc<Program>>,
+    /// The id of the program that is currently in use.
+    pub current_program: u32,
+    /// The next id handed out to an object setting uniforms of a shared program.
+    pub next_program_user: u64,
+    /// The index buffer for drawing quads, created on first use.
+    pub quad_index_buffer: Option<Rc<Buffer>>,
+    /// The transform applied to everything that is drawn, in addition to the render state.
+    /// It is shared with batch renderers, which apply it when primitives are added.
+    pub transform: Rc<Cell<Matrix3<f32>>>,
+    /// The transforms that are restored by `pop_transform`.
+    pub transform_stack: Vec<Matrix3<f32>>,
+    /// The rectangle in pixel coordinates of the render target that drawing is restricted to.
+    /// It is shared with batch renderers, which record it for the primitives added to them.
+    pub scissor: Rc<Cell<Option<super::Rect<u32>>>>,
+    /// Whether drawing is restricted to the shape in the stencil buffer.
+    pub mask_active: bool,
+    /// Lines added with the functions in `graphics::debug`, drawn at the end of the frame.
+    pub debug_lines: super::debug::LineBatch,
+    /// Fields are dropped in declaration order, so this is declared last to release
+    /// the GL objects owned by the context, like the offscreen framebuffer and the
+    /// cached programs, while the GL context they belong to still exists.
+    pub surface: Rc<Surface>,
+}
+
+/// The OpenGL context together with the surface it is presented on.
+#[derive(Debug)]
+pub(crate) enum Surface {
+    /// Rendering to the default framebuffer of a window.
+    Windowed(WindowedContext<PossiblyCurrent>),
+    /// Rendering without a window, into an offscreen framebuffer.
+    Headless {
+        context: glutin::Context<PossiblyCurrent>,
+        size: PhysicalSize<u32>,
+        /// Some platforms need an event loop for creating a headless context.
+        /// It is declared last so that it outlives the context.
+        #[allow(unused)]
+        event_loop: Box<EventLoop<()>>,
+    },
+}
+
+impl Surface {
+    pub fn get_proc_address(&self, addr: &str) -> *const std::ffi::c_void {
+        match self {
+            Surface::Windowed(windowed_context) => windowed_context.get_proc_address(addr),
+            Surface::Headless { context, .. } => context.get_proc_address(addr),
+        }
+    }
+
+    /// The window that is drawn to, unless the context is headless.
+    pub fn window(&self) -> Option<&glutin::window::Window> {
+        match self {
+            Surface::Windowed(windowed_context) => Some(windowed_context.window()),
+            Surface::Headless { .. } => None,
+        }
+    }
+}
+
+/// A framebuffer with a color attachment that replaces the default framebuffer
+/// for headless contexts.
+#[derive(Debug)]
+pub(crate) struct OffscreenTarget {
+    pub framebuffer: Framebuffer,
+    #[allow(unused)]
+    color: Renderbuffer,
+    #[allow(unused)]
+    depth_stencil: Renderbuffer,
+}
+
+impl OffscreenTarget {
+    pub fn new(size: PhysicalSize<u32>) -> Result<Self, BackendError> {
+        if size.width.max(size.height) >= std::i32::MAX as u32 {
+            return Err(BackendError::TooLarge);
+        }
+        let framebuffer = Framebuffer::new()?;
+        let color = Renderbuffer::new()?;
+        let depth_stencil = Renderbuffer::new()?;
+        unsafe {
+            Renderbuffer::bind(&color)?;
+            Renderbuffer::storage(gl::RGBA8, size.width as i32, size.height as i32)?;
+            Renderbuffer::bind(&depth_stencil)?;
+            Renderbuffer::storage(gl::DEPTH24_STENCIL8, size.width as i32, size.height as i32)?;
+            Renderbuffer::unbind()?;
+
+            Framebuffer::bind(gl::FRAMEBUFFER, &framebuffer)?;
+            Framebuffer::renderbuffer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, &color)?;
+            Framebuffer::renderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_STENCIL_ATTACHMENT,
+                &depth_stencil,
+            )?;
+            Framebuffer::check_status(gl::FRAMEBUFFER)?;
+        }
+        Ok(Self {
+            framebuffer,
+            color,
+            depth_stencil,
+        })
+    }
 }
 
 #[macro_export]
@@ -54,11 +160,11 @@ macro_rules! CheckGlNonZero {
 }
 
 impl GraphicsContext {
-    pub fn new(windowed_context: WindowedContext<glutin::PossiblyCurrent>) -> Self {
-        let windowed_context = Rc::new(windowed_context);
+    pub fn new(surface: Surface) -> Result<Self, BackendError> {
+        let surface = Rc::new(surface);
         gl::load_with({
-            let windowed_context = windowed_context.clone();
-            move |s| windowed_context.get_proc_address(s) as *const _
+            let surface = surface.clone();
+            move |s| surface.get_proc_address(s) as *const _
         });
 
         let version = unsafe { gl::GetString(gl::VERSION) };
@@ -90,17 +196,50 @@ impl GraphicsContext {
                 log::debug!("Found extension {}", ext);
             }
         }
-        let screen_size = windowed_context.window().inner_size();
-        let scale_factor = windowed_context.window().scale_factor();
+        let mut max_texture_size = 0;
+        unsafe { gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_texture_size as *mut _) };
+        log::debug!("Maximum texture size is {}", max_texture_size);
+        // Rows of single-channel textures are tightly packed,
+        // while the rows of RGBA textures are always aligned anyway
+        unsafe { CheckGl!(gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1))? };
+
+        let (screen_size, scale_factor, offscreen) = match &*surface {
+            Surface::Windowed(windowed_context) => {
+                let window = windowed_context.window();
+                (window.inner_size(), window.scale_factor(), None)
+            }
+            Surface::Headless { size, .. } => (*size, 1.0, Some(OffscreenTarget::new(*size)?)),
+        };
+        // The initial viewport of a headless context without any surface is empty
+        unsafe {
+            CheckGl!(gl::Viewport(
+                0,
+                0,
+                screen_size.width as i32,
+                screen_size.height as i32
+            ))?;
+        }
 
-        Self {
-            windowed_context,
+        Ok(Self {
             screen_size,
             scale_factor,
             can_debug,
+            max_texture_size: max_texture_size.max(0) as u32,
             pixel_projection: compute_pixel_projection(screen_size),
             blend_mode: None,
-        }
+            offscreen,
+            render_target: None,
+            programs: HashMap::new(),
+            current_program: 0,
+            next_program_user: 1,
+            quad_index_buffer: None,
+            transform: Rc::new(Cell::new(Matrix3::identity())),
+            transform_stack: Vec::new(),
+            scissor: Rc::new(Cell::new(None)),
+            mask_active: false,
+            debug_lines: super::debug::LineBatch::new(),
+            surface,
+        })
     }
 
     pub fn init_debug(&mut self) {
@@ -159,10 +298,228 @@ impl GraphicsContext {
     }
 
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
-        self.windowed_context.resize(new_size);
-        unsafe { gl::Viewport(0, 0, new_size.width as i32, new_size.height as i32) };
+        if let Surface::Windowed(windowed_context) = &*self.surface {
+            windowed_context.resize(new_size);
+        }
         self.screen_size = new_size;
-        self.pixel_projection = compute_pixel_projection(new_size);
+        if self.render_target.is_none() {
+            unsafe { gl::Viewport(0, 0, new_size.width as i32, new_size.height as i32) };
+            self.pixel_projection = compute_pixel_projection(new_size);
+            // The scissor rectangle is relative to the bottom of the screen
+            if let Err(err) = self.apply_scissor(self.scissor.get()) {
+                log::error!("Failed to update scissor rectangle: {}", err);
+            }
+        }
+    }
+
+    /// Redirect all subsequent drawing to the given canvas, or back to the screen if `None`.
+    /// The viewport and pixel projection are adjusted to the size of the target,
+    /// and the scissor rectangle and mask are reset.
+    pub fn set_render_target(&mut self, target: Option<super::Canvas>) -> Result<(), BackendError> {
+        let size = target
+            .as_ref()
+            .map_or(self.screen_size, |canvas| canvas.size());
+        unsafe {
+            CheckGl!(gl::Viewport(0, 0, size.width as i32, size.height as i32))?;
+        }
+        self.pixel_projection = if target.is_some() {
+            compute_canvas_projection(size)
+        } else {
+            compute_pixel_projection(size)
+        };
+        self.render_target = target;
+        self.bind_render_target()?;
+        self.set_scissor(None)?;
+        self.disable_mask()
+    }
+
+    /// Restrict drawing to the given rectangle in pixel coordinates of the render target.
+    pub fn set_scissor(&mut self, scissor: Option<super::Rect<u32>>) -> Result<(), BackendError> {
+        self.scissor.set(scissor);
+        self.apply_scissor(scissor)
+    }
+
+    /// Configure the scissor test for the given rectangle, without changing the current scissor.
+    pub fn apply_scissor(&self, scissor: Option<super::Rect<u32>>) -> Result<(), BackendError> {
+        unsafe {
+            match scissor {
+                Some(rect) => {
+                    // Canvases are rendered upside down, so only the screen needs flipping
+                    let flip = self.render_target.is_none();
+                    let [x, y, width, height] = scissor_box(rect, self.target_size().height, flip);
+                    CheckGl!(gl::Enable(gl::SCISSOR_TEST))?;
+                    CheckGl!(gl::Scissor(x, y, width, height))
+                }
+                None => CheckGl!(gl::Disable(gl::SCISSOR_TEST)),
+            }
+        }
+    }
+
+    /// Clear the stencil buffer and prepare it for drawing the shape of a mask,
+    /// without affecting the color buffer.
+    pub fn begin_mask(&mut self) -> Result<(), BackendError> {
+        unsafe {
+            // The whole mask is cleared regardless of the scissor rectangle
+            CheckGl!(gl::Disable(gl::SCISSOR_TEST))?;
+            CheckGl!(gl::ClearStencil(0))?;
+            CheckGl!(gl::Clear(gl::STENCIL_BUFFER_BIT))?;
+            self.apply_scissor(self.scissor.get())?;
+
+            CheckGl!(gl::Enable(gl::STENCIL_TEST))?;
+            CheckGl!(gl::StencilFunc(gl::ALWAYS, 1, 0xff))?;
+            CheckGl!(gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE))?;
+            CheckGl!(gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE))?;
+        }
+        self.mask_active = true;
+        Ok(())
+    }
+
+    /// Restrict subsequent drawing to the shape drawn since `begin_mask`.
+    pub fn end_mask(&mut self) -> Result<(), BackendError> {
+        unsafe {
+            CheckGl!(gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE))?;
+            CheckGl!(gl::StencilFunc(gl::EQUAL, 1, 0xff))?;
+            CheckGl!(gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP))?;
+        }
+        Ok(())
+    }
+
+    pub fn disable_mask(&mut self) -> Result<(), BackendError> {
+        if self.mask_active {
+            unsafe {
+                CheckGl!(gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE))?;
+                CheckGl!(gl::Disable(gl::STENCIL_TEST))?;
+            }
+            self.mask_active = false;
+        }
+        Ok(())
+    }
+
+    /// Return the program consisting of the given vertex and fragment shader,
+    /// compiling and linking it only the first time it is requested.
+    pub fn cached_program(
+        &mut self,
+        vertex_shader: &'static str,
+        fragment_shader: &'static str,
+    ) -> Result<Rc<Program>, BackendError> {
+        let key = (vertex_shader, fragment_shader);
+        if let Some(program) = self.programs.get(&key) {
+            return Ok(program.clone());
+        }
+        let program = Rc::new(Program::from_source(vertex_shader, fragment_shader)?);
+        self.programs.insert(key, program.clone());
+        Ok(program)
+    }
+
+    /// Hand out a unique id for use with `Program::claim`.
+    pub fn new_program_user(&mut self) -> u64 {
+        let user = self.next_program_user;
+        self.next_program_user += 1;
+        user
+    }
+
+    /// Return the index buffer for drawing quads that is shared between all batch renderers,
+    /// see `batch::quad_indices`.
+    pub fn quad_index_buffer(&mut self) -> Result<Rc<Buffer>, BackendError> {
+        if let Some(buffer) = &self.quad_index_buffer {
+            return Ok(buffer.clone());
+        }
+        let buffer = Buffer::new()?;
+        let indices = super::batch::quad_indices(super::batch::QUAD_INDEX_BUFFER_QUADS);
+        // Binding an ELEMENT_ARRAY_BUFFER would modify the currently bound VAO
+        Buffer::bind(gl::ARRAY_BUFFER, &buffer)?;
+        unsafe {
+            Buffer::data(gl::ARRAY_BUFFER, &indices, gl::STATIC_DRAW)?;
+        }
+        Buffer::unbind(gl::ARRAY_BUFFER)?;
+        let buffer = Rc::new(buffer);
+        self.quad_index_buffer = Some(buffer.clone());
+        Ok(buffer)
+    }
+
+    /// Make the program the current one, unless it already is.
+    pub fn use_program(&mut self, program: &Program) -> Result<(), BackendError> {
+        if self.current_program != program.id {
+            program.bind()?;
+            self.current_program = program.id;
+        }
+        Ok(())
+    }
+
+    /// Save the current transform and multiply it with the given transform from the right,
+    /// so that the given transform is applied to vertices before the saved one.
+    /// The saved transform is restored by `pop_transform`.
+    pub fn push_transform(&mut self, transform: Matrix3<f32>) {
+        let current = self.transform.get();
+        self.transform_stack.push(current);
+        self.transform.set(current * transform);
+    }
+
+    /// Restore the transform that was current before the last `push_transform`.
+    /// Returns `false` if there was nothing to restore.
+    pub fn pop_transform(&mut self) -> bool {
+        match self.transform_stack.pop() {
+            Some(previous) => {
+                self.transform.set(previous);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop all saved transforms and reset the current transform to the identity.
+    pub fn reset_transform(&mut self) {
+        if !self.transform_stack.is_empty() {
+            log::warn!(
+                "{} transforms were pushed without being popped",
+                self.transform_stack.len()
+            );
+            self.transform_stack.clear();
+        }
+        self.transform.set(Matrix3::identity());
+    }
+
+    /// The size of the framebuffer that is currently rendered to.
+    pub fn target_size(&self) -> PhysicalSize<u32> {
+        self.render_target
+            .as_ref()
+            .map_or(self.screen_size, |canvas| canvas.size())
+    }
+
+    /// Bind the framebuffer of the current render target,
+    /// e.g. after another framebuffer has been bound temporarily.
+    pub fn bind_render_target(&self) -> Result<(), BackendError> {
+        unsafe {
+            match &self.render_target {
+                Some(canvas) => Framebuffer::bind(gl::FRAMEBUFFER, canvas.framebuffer()),
+                None => self.bind_default_framebuffer(),
+            }
+        }
+    }
+
+    /// Bind the framebuffer that is presented on screen,
+    /// which is the offscreen framebuffer for headless contexts.
+    unsafe fn bind_default_framebuffer(&self) -> Result<(), BackendError> {
+        match &self.offscreen {
+            Some(offscreen) => Framebuffer::bind(gl::FRAMEBUFFER, &offscreen.framebuffer),
+            None => Framebuffer::unbind(gl::FRAMEBUFFER),
+        }
+    }
+
+    /// Ask the window to be redrawn. Does nothing for headless contexts.
+    pub fn request_redraw(&self) {
+        if let Some(window) = self.surface.window() {
+            window.request_redraw();
+        }
+    }
+
+    /// Present the rendered frame, by swapping buffers for windowed contexts,
+    /// and flushing pending commands for headless contexts.
+    pub fn present(&self) {
+        match &*self.surface {
+            Surface::Windowed(windowed_context) => windowed_context.swap_buffers().unwrap(),
+            Surface::Headless { .. } => unsafe { gl::Flush() },
+        }
     }
 
     pub fn set_blend_mode(
@@ -223,6 +580,8 @@ pub enum BackendError {
     /// Attempt to write to a buffer, texture, etc. from a client buffer
     /// that had the wrong size.
     InvalidSize,
+    /// A framebuffer is not complete and cannot be rendered to.
+    IncompleteFramebuffer { status: GLenum },
 }
 
 impl Display for BackendError {
@@ -266,6 +625,9 @@ impl Display for BackendError {
             ),
             BackendError::TooLarge => write!(f, "Object too large"),
             BackendError::InvalidSize => write!(f, "Client buffer size doesn't match"),
+            BackendError::IncompleteFramebuffer { status } => {
+                write!(f, "Incomplete framebuffer (status: {})", status)
+            }
         }
     }
 }
@@ -279,6 +641,38 @@ pub fn compute_pixel_projection(screen_size: PhysicalSize<u32>) -> cgmath::Matri
     translate * scale
 }
 
+/// Convert a rectangle in pixel coordinates with the origin in the top-left corner
+/// to the `[x, y, width, height]` expected by `glScissor`.
+/// If `flip` is set, the origin of the render target is in the bottom-left corner.
+fn scissor_box(rect: super::Rect<u32>, target_height: u32, flip: bool) -> [i32; 4] {
+    let to_gl = |value: u32| value.min(std::i32::MAX as u32) as i32;
+    let width = rect.bottom_right.x.saturating_sub(rect.top_left.x);
+    let height = rect.bottom_right.y.saturating_sub(rect.top_left.y);
+    let y = if flip {
+        target_height.saturating_sub(rect.bottom_right.y)
+    } else {
+        rect.top_left.y
+    };
+    [
+        to_gl(rect.top_left.x),
+        to_gl(y),
+        to_gl(width),
+        to_gl(height),
+    ]
+}
+
+/// Like `compute_pixel_projection`, but upside down, so that the top row of pixels
+/// ends up in the first row of a texture rendered to through a framebuffer.
+/// That way, canvas textures can be used in the same way as textures loaded from images.
+pub fn compute_canvas_projection(canvas_size: PhysicalSize<u32>) -> cgmath::Matrix3<f32> {
+    let scale = super::transform::scale(Vector2::new(
+        2.0 / canvas_size.width as f32,
+        2.0 / canvas_size.height as f32,
+    ));
+    let translate = super::transform::translate(Vector2::new(-1.0, -1.0));
+    translate * scale
+}
+
 /// Obtain the latest error from OpenGL. Should be used through the CheckGl! macro.
 ///
 /// If `is_error` is true, then this function will always generate an error,
@@ -380,6 +774,8 @@ impl Drop for Shader {
 pub struct Program {
     id: u32,
     uniforms: HashMap<String, UniformInfo>,
+    /// The user that last set the uniforms of this program, see `claim`.
+    last_user: Cell<u64>,
 }
 
 impl Program {
@@ -390,6 +786,7 @@ impl Program {
         let mut program = Program {
             id: program,
             uniforms: HashMap::new(),
+            last_user: Cell::new(0),
         };
 
         unsafe {
@@ -513,12 +910,24 @@ impl Program {
         }
     }
 
-    pub fn bind(&self) -> Result<(), BackendError> {
+    /// Information about an active uniform of this program, if it exists.
+    pub fn uniform_info(&self, uniform: &str) -> Option<&UniformInfo> {
+        self.uniforms.get(uniform)
+    }
+
+    /// Should only be called through `GraphicsContext::use_program`,
+    /// which keeps track of the current program.
+    fn bind(&self) -> Result<(), BackendError> {
         unsafe { CheckGl!(gl::UseProgram(self.id)) }
     }
 
-    pub fn unbind() -> Result<(), BackendError> {
-        unsafe { CheckGl!(gl::UseProgram(0)) }
+    /// Record that the uniforms of this program are about to be set by the given user,
+    /// where users are identified by an id obtained from `GraphicsContext::new_program_user`.
+    ///
+    /// Returns true if a different user set the uniforms before, meaning that
+    /// the uniform values currently stored in the program are not the ones this user expects.
+    pub fn claim(&self, user: u64) -> bool {
+        self.last_user.replace(user) != user
     }
 }
 
@@ -532,10 +941,16 @@ impl Drop for Program {
     }
 }
 
+/// A value that can be assigned to a uniform variable of a shader program.
 pub trait UniformValue {
     /// Return whether this value can be assigned to a uniform of the given type.
     fn is_allowed_type(&self, gl_type: GLenum) -> bool;
     /// Assign this value to the uniform at the given location.
+    ///
+    /// # Safety
+    ///
+    /// The program containing the uniform must be bound, and the uniform must have
+    /// a type for which `is_allowed_type` returns true.
     unsafe fn set_uniform(&self, location: i32) -> Result<(), BackendError>;
 }
 
@@ -585,7 +1000,37 @@ impl UniformValue for i32 {
     }
 }
 
-impl<T: UniformValue> UniformValue for &T {
+impl UniformValue for f32 {
+    fn is_allowed_type(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT
+    }
+
+    unsafe fn set_uniform(&self, location: i32) -> Result<(), BackendError> {
+        CheckGl!(gl::Uniform1f(location, *self))
+    }
+}
+
+impl UniformValue for Vector2<f32> {
+    fn is_allowed_type(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT_VEC2
+    }
+
+    unsafe fn set_uniform(&self, location: i32) -> Result<(), BackendError> {
+        CheckGl!(gl::Uniform2f(location, self.x, self.y))
+    }
+}
+
+impl UniformValue for Vector3<f32> {
+    fn is_allowed_type(&self, gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT_VEC3
+    }
+
+    unsafe fn set_uniform(&self, location: i32) -> Result<(), BackendError> {
+        CheckGl!(gl::Uniform3f(location, self.x, self.y, self.z))
+    }
+}
+
+impl<T: UniformValue + ?Sized> UniformValue for &T {
     fn is_allowed_type(&self, gl_type: GLenum) -> bool {
         (*self).is_allowed_type(gl_type)
     }
@@ -680,6 +1125,29 @@ impl Buffer {
         ))
     }
 
+    /// Overwrite a part of the existing buffer data storage with the given array,
+    /// starting at the given offset in bytes.
+    ///
+    /// # Safety
+    ///
+    /// Highly unsafe. Make sure that `T` is a `repr(C)` type.
+    pub unsafe fn sub_data<T>(
+        target: GLenum,
+        offset: usize,
+        data: &[T],
+    ) -> Result<(), BackendError> {
+        let size = std::mem::size_of_val(data);
+        if size > std::isize::MAX as usize || offset > std::isize::MAX as usize {
+            return Err(BackendError::TooLarge);
+        }
+        CheckGl!(gl::BufferSubData(
+            target,
+            offset as isize,
+            size as isize,
+            data.as_ptr() as *const _
+        ))
+    }
+
     /// Create the buffer data storage with undefined contents.
     ///
     /// # Safety
@@ -764,20 +1232,58 @@ impl Texture {
         CheckGl!(gl::BindTexture(target, 0))
     }
 
-    /// Upload data to a 2D texture.
+    pub unsafe fn parameteri(
+        target: GLenum,
+        name: GLenum,
+        value: GLint,
+    ) -> Result<(), BackendError> {
+        CheckGl!(gl::TexParameteri(target, name, value))
+    }
+
+    /// Regenerate all mipmap levels from the base level of the currently bound texture.
+    pub unsafe fn generate_mipmap(target: GLenum) -> Result<(), BackendError> {
+        CheckGl!(gl::GenerateMipmap(target))
+    }
+
+    /// Upload RGBA data to a 2D texture.
     pub unsafe fn image2d_rgba(
         target: GLenum,
         width: GLsizei,
         height: GLsizei,
         data: &[u8],
     ) -> Result<(), BackendError> {
-        let num_samples = width
-            .checked_mul(height)
-            .and_then(|pixels| pixels.checked_mul(4))
-            .ok_or(BackendError::TooLarge)?;
-        if num_samples < 0 || num_samples as usize != data.len() {
-            return Err(BackendError::InvalidSize);
-        }
+        Self::image2d(target, TextureFormat::Rgba8, width, height, data)
+    }
+
+    /// Upload data in the given format to a 2D texture.
+    pub unsafe fn image2d(
+        target: GLenum,
+        format: TextureFormat,
+        width: GLsizei,
+        height: GLsizei,
+        data: &[u8],
+    ) -> Result<(), BackendError> {
+        check_image_size(format, width, height, data)?;
+        let (internal_format, pixel_format) = format.to_gl();
+        CheckGl!(gl::TexImage2D(
+            target,
+            0,
+            internal_format,
+            width,
+            height,
+            0,
+            pixel_format,
+            gl::UNSIGNED_BYTE,
+            data.as_ptr() as _
+        ))
+    }
+
+    /// Create the storage of a 2D RGBA texture with undefined contents.
+    pub unsafe fn alloc2d_rgba(
+        target: GLenum,
+        width: GLsizei,
+        height: GLsizei,
+    ) -> Result<(), BackendError> {
         CheckGl!(gl::TexImage2D(
             target,
             0,
@@ -787,7 +1293,7 @@ impl Texture {
             0,
             gl::RGBA,
             gl::UNSIGNED_BYTE,
-            data.as_ptr() as _
+            std::ptr::null()
         ))
     }
 
@@ -800,13 +1306,32 @@ impl Texture {
         height: GLsizei,
         data: &[u8],
     ) -> Result<(), BackendError> {
-        let num_samples = width
-            .checked_mul(height)
-            .and_then(|pixels| pixels.checked_mul(4))
-            .ok_or(BackendError::TooLarge)?;
-        if num_samples < 0 || num_samples as usize != data.len() {
-            return Err(BackendError::InvalidSize);
-        }
+        Self::subimage2d(
+            target,
+            level,
+            TextureFormat::Rgba8,
+            xoff,
+            yoff,
+            width,
+            height,
+            data,
+        )
+    }
+
+    /// Replace a region of a 2D texture with data in the given format,
+    /// which must match the format the texture was created with.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn subimage2d(
+        target: GLenum,
+        level: GLint,
+        format: TextureFormat,
+        xoff: GLsizei,
+        yoff: GLsizei,
+        width: GLsizei,
+        height: GLsizei,
+        data: &[u8],
+    ) -> Result<(), BackendError> {
+        check_image_size(format, width, height, data)?;
         CheckGl!(gl::TexSubImage2D(
             target,
             level,
@@ -814,13 +1339,30 @@ impl Texture {
             yoff,
             width,
             height,
-            gl::RGBA,
+            format.to_gl().1,
             gl::UNSIGNED_BYTE,
             data.as_ptr() as _
         ))
     }
 }
 
+/// Ensure that the data contains exactly the pixels of an image of the given size.
+fn check_image_size(
+    format: TextureFormat,
+    width: GLsizei,
+    height: GLsizei,
+    data: &[u8],
+) -> Result<(), BackendError> {
+    let num_samples = width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(format.bytes_per_pixel() as GLsizei))
+        .ok_or(BackendError::TooLarge)?;
+    if num_samples < 0 || num_samples as usize != data.len() {
+        return Err(BackendError::InvalidSize);
+    }
+    Ok(())
+}
+
 impl Drop for Texture {
     fn drop(&mut self) {
         log::trace!("DeleteTextures() = {}", self.id);
@@ -829,3 +1371,146 @@ impl Drop for Texture {
         }
     }
 }
+
+#[derive(Debug)]
+pub struct Renderbuffer {
+    id: u32,
+}
+
+impl Renderbuffer {
+    pub fn new() -> Result<Renderbuffer, BackendError> {
+        let mut id = 0;
+        unsafe {
+            CheckGl!(gl::GenRenderbuffers(1, &mut id))?;
+            log::trace!("GenRenderbuffers() = {}", id);
+        }
+        Ok(Renderbuffer { id })
+    }
+
+    pub fn bind(renderbuffer: &Renderbuffer) -> Result<(), BackendError> {
+        unsafe { CheckGl!(gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer.id)) }
+    }
+
+    pub fn unbind() -> Result<(), BackendError> {
+        unsafe { CheckGl!(gl::BindRenderbuffer(gl::RENDERBUFFER, 0)) }
+    }
+
+    /// Create the storage of the currently bound renderbuffer.
+    pub unsafe fn storage(
+        internal_format: GLenum,
+        width: GLsizei,
+        height: GLsizei,
+    ) -> Result<(), BackendError> {
+        CheckGl!(gl::RenderbufferStorage(
+            gl::RENDERBUFFER,
+            internal_format,
+            width,
+            height
+        ))
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        log::trace!("DeleteRenderbuffers() = {}", self.id);
+        unsafe {
+            gl::DeleteRenderbuffers(1, &self.id);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Framebuffer {
+    id: u32,
+}
+
+impl Framebuffer {
+    pub fn new() -> Result<Framebuffer, BackendError> {
+        let mut id = 0;
+        unsafe {
+            CheckGl!(gl::GenFramebuffers(1, &mut id))?;
+            log::trace!("GenFramebuffers() = {}", id);
+        }
+        Ok(Framebuffer { id })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub unsafe fn bind(target: GLenum, framebuffer: &Framebuffer) -> Result<(), BackendError> {
+        CheckGl!(gl::BindFramebuffer(target, framebuffer.id))
+    }
+
+    /// Bind the default framebuffer of the window.
+    pub unsafe fn unbind(target: GLenum) -> Result<(), BackendError> {
+        CheckGl!(gl::BindFramebuffer(target, 0))
+    }
+
+    /// Attach level 0 of a 2D texture to the currently bound framebuffer.
+    pub unsafe fn texture2d(
+        target: GLenum,
+        attachment: GLenum,
+        texture: &Texture,
+    ) -> Result<(), BackendError> {
+        CheckGl!(gl::FramebufferTexture2D(
+            target,
+            attachment,
+            gl::TEXTURE_2D,
+            texture.id,
+            0
+        ))
+    }
+
+    /// Attach a renderbuffer to the currently bound framebuffer.
+    pub unsafe fn renderbuffer(
+        target: GLenum,
+        attachment: GLenum,
+        renderbuffer: &Renderbuffer,
+    ) -> Result<(), BackendError> {
+        CheckGl!(gl::FramebufferRenderbuffer(
+            target,
+            attachment,
+            gl::RENDERBUFFER,
+            renderbuffer.id
+        ))
+    }
+
+    /// Check whether the currently bound framebuffer can be rendered to.
+    pub unsafe fn check_status(target: GLenum) -> Result<(), BackendError> {
+        let status = CheckGl!(gl::CheckFramebufferStatus(target))?;
+        if status == gl::FRAMEBUFFER_COMPLETE {
+            Ok(())
+        } else {
+            Err(BackendError::IncompleteFramebuffer { status })
+        }
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        log::trace!("DeleteFramebuffers() = {}", self.id);
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.id);
+        }
+    }
+}
+
+#[test]
+fn test_scissor_box() {
+    let rect = super::Rect {
+        top_left: Vector2::new(10, 20),
+        bottom_right: Vector2::new(110, 70),
+    };
+    assert_eq!(scissor_box(rect, 600, false), [10, 20, 100, 50]);
+    assert_eq!(scissor_box(rect, 600, true), [10, 530, 100, 50]);
+}
+
+#[test]
+fn test_check_image_size() {
+    assert!(check_image_size(TextureFormat::Rgba8, 3, 2, &[0; 24]).is_ok());
+    assert!(check_image_size(TextureFormat::R8, 3, 2, &[0; 6]).is_ok());
+    assert!(check_image_size(TextureFormat::R8, 3, 2, &[0; 24]).is_err());
+    assert!(check_image_size(TextureFormat::R8, -1, 2, &[]).is_err());
+    assert!(check_image_size(TextureFormat::Rgba8, std::i32::MAX, 2, &[]).is_err());
+}
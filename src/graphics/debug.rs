@@ -0,0 +1,308 @@
+//! Drawing lines and points for visualizing things like physics shapes, paths and grids.
+//!
+//! A `LineBatch` collects colored line segments and points and draws them at once.
+//! Since the width of GL lines is very limited in the core profile,
+//! every segment is drawn as a quad, so that lines can be arbitrarily thick.
+//!
+//! For quick debugging, the free functions in this module add to a batch owned by the context,
+//! which is drawn on top of everything else at the end of the frame.
+//!
+//! ```ignore
+//! graphics::debug::line(ctx, body.position, body.position + body.velocity, Color::RED);
+//! ```
+
+use cgmath::{InnerSpace, Matrix3, SquareMatrix, Vector2};
+
+use super::{primitives::BasicVertex2D, BlendMode, Color, Drawable, Mesh, Rect, RenderState};
+use crate::{Context, GameResult};
+
+/// The number of segments used for approximating circles.
+const CIRCLE_SEGMENTS: u32 = 32;
+
+/// Collects line segments and points, which are drawn in a single call.
+///
+/// Like a `BatchRender`, the batch is empty again after it has been drawn.
+pub struct LineBatch {
+    vertices: Vec<BasicVertex2D>,
+    indices: Vec<u32>,
+    thickness: f32,
+    point_size: f32,
+    /// Applied to the positions of lines and points, but not to their thickness.
+    transform: Matrix3<f32>,
+    /// Created when the batch is drawn for the first time, and reused afterwards.
+    mesh: Option<Mesh>,
+}
+
+impl std::fmt::Debug for LineBatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LineBatch")
+            .field("vertices", &self.vertices.len())
+            .field("indices", &self.indices.len())
+            .field("thickness", &self.thickness)
+            .field("point_size", &self.point_size)
+            .finish()
+    }
+}
+
+impl Default for LineBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineBatch {
+    /// Create an empty batch drawing lines one pixel wide and points three pixels wide.
+    pub fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            thickness: 1.0,
+            point_size: 3.0,
+            transform: Matrix3::identity(),
+            mesh: None,
+        }
+    }
+
+    /// The width of lines that are added afterwards.
+    pub fn thickness(&self) -> f32 {
+        self.thickness
+    }
+
+    pub fn set_thickness(&mut self, thickness: f32) {
+        self.thickness = thickness
+    }
+
+    /// The width of the squares drawn for points that are added afterwards.
+    pub fn point_size(&self) -> f32 {
+        self.point_size
+    }
+
+    pub fn set_point_size(&mut self, point_size: f32) {
+        self.point_size = point_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Remove everything that has been added since the batch was last drawn.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    /// Add a line segment from `a` to `b`.
+    pub fn line(&mut self, a: Vector2<f32>, b: Vector2<f32>, color: Color) {
+        let a = self.transform_point(a);
+        let b = self.transform_point(b);
+        let direction = b - a;
+        let length = direction.magnitude();
+        if length <= std::f32::EPSILON {
+            return;
+        }
+        let normal = Vector2::new(-direction.y, direction.x) / length * (self.thickness / 2.0);
+        self.push_quad([a + normal, b + normal, b - normal, a - normal], color);
+    }
+
+    /// Add line segments connecting the given points in order.
+    pub fn polyline(&mut self, points: &[Vector2<f32>], color: Color) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], color);
+        }
+    }
+
+    /// Add line segments connecting the given points in order, and the last point with the first.
+    pub fn polygon(&mut self, points: &[Vector2<f32>], color: Color) {
+        self.polyline(points, color);
+        if let (Some(first), Some(last)) = (points.first(), points.last()) {
+            self.line(*last, *first, color);
+        }
+    }
+
+    /// Add the outline of a rectangle.
+    pub fn rect(&mut self, rect: Rect<f32>, color: Color) {
+        self.polygon(&rect.corners(), color)
+    }
+
+    /// Add the outline of a circle.
+    pub fn circle(&mut self, center: Vector2<f32>, radius: f32, color: Color) {
+        let points: Vec<_> = (0..CIRCLE_SEGMENTS)
+            .map(|i| {
+                let angle = i as f32 / CIRCLE_SEGMENTS as f32 * 2.0 * std::f32::consts::PI;
+                center + Vector2::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect();
+        self.polygon(&points, color)
+    }
+
+    /// Add the lines of a grid covering the rectangle, with cells of the given size.
+    pub fn grid(&mut self, area: Rect<f32>, cell_size: Vector2<f32>, color: Color) {
+        if cell_size.x <= 0.0 || cell_size.y <= 0.0 {
+            return;
+        }
+        let mut x = area.top_left.x;
+        while x <= area.bottom_right.x {
+            self.line(
+                Vector2::new(x, area.top_left.y),
+                Vector2::new(x, area.bottom_right.y),
+                color,
+            );
+            x += cell_size.x;
+        }
+        let mut y = area.top_left.y;
+        while y <= area.bottom_right.y {
+            self.line(
+                Vector2::new(area.top_left.x, y),
+                Vector2::new(area.bottom_right.x, y),
+                color,
+            );
+            y += cell_size.y;
+        }
+    }
+
+    /// Add a point, which is drawn as a square centered on it.
+    pub fn point(&mut self, position: Vector2<f32>, color: Color) {
+        let position = self.transform_point(position);
+        let rect = Rect::from_center_size(position, Vector2::new(self.point_size, self.point_size));
+        self.push_quad(rect.corners(), color);
+    }
+
+    fn push_quad(&mut self, corners: [Vector2<f32>; 4], color: Color) {
+        let first = self.vertices.len() as u32;
+        for corner in &corners {
+            self.vertices
+                .push(BasicVertex2D::with_position_color(*corner, color));
+        }
+        self.indices
+            .extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    fn transform_point(&self, point: Vector2<f32>) -> Vector2<f32> {
+        (self.transform * point.extend(1.0)).truncate()
+    }
+}
+
+impl Drawable for LineBatch {
+    fn draw(&mut self, ctx: &mut Context, state: RenderState) -> GameResult<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let mesh = match &mut self.mesh {
+            Some(mesh) => {
+                mesh.set_vertices(&self.vertices)?;
+                mesh.set_indices(&self.indices)?;
+                mesh
+            }
+            None => {
+                let mut mesh = Mesh::new(ctx, &self.vertices, &self.indices)?;
+                mesh.set_blend_mode(Some(BlendMode::default()));
+                self.mesh.get_or_insert(mesh)
+            }
+        };
+        let result = mesh.draw(ctx, state);
+        self.clear();
+        result
+    }
+}
+
+/// Add a line segment to the debug lines of this frame.
+///
+/// The end points are given in pixels and are transformed by the current transform
+/// (see `push_transform`), but the thickness is always in pixels.
+pub fn line(ctx: &mut Context, a: Vector2<f32>, b: Vector2<f32>, color: Color) {
+    with_debug_lines(ctx, |lines| lines.line(a, b, color))
+}
+
+/// Add a point to the debug lines of this frame, see `line`.
+pub fn point(ctx: &mut Context, position: Vector2<f32>, color: Color) {
+    with_debug_lines(ctx, |lines| lines.point(position, color))
+}
+
+/// Add the outline of a rectangle to the debug lines of this frame, see `line`.
+pub fn rect(ctx: &mut Context, rect: Rect<f32>, color: Color) {
+    with_debug_lines(ctx, |lines| lines.rect(rect, color))
+}
+
+/// Add the outline of a circle to the debug lines of this frame, see `line`.
+pub fn circle(ctx: &mut Context, center: Vector2<f32>, radius: f32, color: Color) {
+    with_debug_lines(ctx, |lines| lines.circle(center, radius, color))
+}
+
+/// Set the width of debug lines and points that are added afterwards.
+pub fn set_thickness(ctx: &mut Context, thickness: f32) {
+    let lines = &mut ctx.graphics.debug_lines;
+    lines.set_thickness(thickness);
+    lines.set_point_size(thickness * 3.0);
+}
+
+fn with_debug_lines(ctx: &mut Context, add: impl FnOnce(&mut LineBatch)) {
+    // The lines are drawn without a transform at the end of the frame,
+    // so the transform that is current now is applied to their end points right away.
+    let transform = ctx.graphics.transform.get();
+    let lines = &mut ctx.graphics.debug_lines;
+    lines.transform = transform;
+    add(lines);
+    lines.transform = Matrix3::identity();
+}
+
+/// Draw the debug lines of this frame on top of everything else on the screen.
+pub(crate) fn flush(ctx: &mut Context) -> GameResult<()> {
+    if ctx.graphics.debug_lines.is_empty() {
+        return Ok(());
+    }
+    ctx.graphics.set_render_target(None)?;
+    ctx.graphics.reset_transform();
+    let mut lines = std::mem::take(&mut ctx.graphics.debug_lines);
+    let result = super::draw(ctx, &mut lines);
+    ctx.graphics.debug_lines = lines;
+    result
+}
+
+#[test]
+fn test_line_batch_thick_line() {
+    let mut batch = LineBatch::new();
+    batch.set_thickness(4.0);
+    batch.line(
+        Vector2::new(0.0, 0.0),
+        Vector2::new(10.0, 0.0),
+        Color::WHITE,
+    );
+    // A zero-length line is skipped
+    batch.line(Vector2::new(5.0, 5.0), Vector2::new(5.0, 5.0), Color::WHITE);
+
+    assert_eq!(batch.indices, vec![0, 1, 2, 0, 2, 3]);
+    let positions: Vec<_> = batch.vertices.iter().map(|v| v.position).collect();
+    assert_eq!(
+        positions,
+        vec![[0.0, 2.0], [10.0, 2.0], [10.0, -2.0], [0.0, -2.0]]
+    );
+
+    batch.clear();
+    assert!(batch.is_empty());
+}
+
+#[test]
+fn test_line_batch_scaled_transform() {
+    let mut batch = LineBatch::new();
+    batch.set_thickness(4.0);
+    batch.transform = super::transform::translate(Vector2::new(10.0, 20.0))
+        * super::transform::scale(Vector2::new(3.0, 3.0));
+    batch.line(Vector2::new(0.0, 0.0), Vector2::new(5.0, 0.0), Color::WHITE);
+    batch.point(Vector2::new(0.0, 0.0), Color::WHITE);
+
+    // The end points are transformed, but the thickness and point size are not scaled
+    let positions: Vec<_> = batch.vertices.iter().map(|v| v.position).collect();
+    assert_eq!(
+        positions,
+        vec![
+            [10.0, 22.0],
+            [25.0, 22.0],
+            [25.0, 18.0],
+            [10.0, 18.0],
+            [8.5, 18.5],
+            [11.5, 18.5],
+            [11.5, 21.5],
+            [8.5, 21.5],
+        ]
+    );
+}
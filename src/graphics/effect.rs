@@ -0,0 +1,124 @@
+//! User supplied shaders that replace the shaders of the basic 2D pipeline.
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use super::{
+    context::{BackendError, Program, UniformValue},
+    primitives::BasicPipeline2D,
+};
+use crate::Context;
+
+/// A reference counted shader program that can be used in place of the default shaders
+/// of `BatchRender`, `Sprite` and `Mesh`.
+///
+/// The shaders must be compatible with the `BasicVertex2D` layout: the vertex attributes
+/// `Position`, `TexCoord` and `Color` are bound to the locations 0, 1 and 2.
+/// The default vertex shader passes the texture coordinates and the vertex color on to the
+/// fragment shader as `Vert_Frag_TexCoord` and `Vert_Frag_Color`.
+///
+/// The drawables set the uniforms `Transform` (`mat3`), `Texture0` (`sampler2D`) and
+/// `UseTexture0` (`bool`) if they are used by the shaders. Any additional uniforms can be
+/// set through `set_uniform` and keep their value until they are changed.
+#[derive(Debug, Clone)]
+pub struct ShaderEffect {
+    inner: Rc<ShaderEffectImpl>,
+}
+
+#[derive(Debug)]
+struct ShaderEffectImpl {
+    /// Not taken from the program cache of the context, because the uniforms of the effect
+    /// are stored in the program itself and must not be shared with other effects.
+    program: Rc<Program>,
+    uniforms: RefCell<HashMap<String, EffectUniform>>,
+}
+
+struct EffectUniform {
+    value: Box<dyn UniformValue>,
+    dirty: bool,
+}
+
+impl std::fmt::Debug for EffectUniform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EffectUniform")
+            .field("dirty", &self.dirty)
+            .finish()
+    }
+}
+
+impl PartialEq for ShaderEffect {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for ShaderEffect {}
+
+impl ShaderEffect {
+    /// Create an effect from a fragment shader that is combined with the default vertex shader.
+    pub fn from_fragment(ctx: &mut Context, fragment_shader: &str) -> Result<Self, BackendError> {
+        Self::from_source(
+            ctx,
+            BasicPipeline2D::VERTEX_SHADER_330_CORE,
+            fragment_shader,
+        )
+    }
+
+    /// Create an effect from a custom vertex and fragment shader.
+    pub fn from_source(
+        _ctx: &mut Context,
+        vertex_shader: &str,
+        fragment_shader: &str,
+    ) -> Result<Self, BackendError> {
+        let program = Rc::new(Program::from_source(vertex_shader, fragment_shader)?);
+        Ok(ShaderEffect {
+            inner: Rc::new(ShaderEffectImpl {
+                program,
+                uniforms: RefCell::new(HashMap::new()),
+            }),
+        })
+    }
+
+    /// Set the value of a uniform of the effect.
+    /// The value is uploaded the next time the effect is used for drawing.
+    pub fn set_uniform<T: UniformValue + 'static>(
+        &self,
+        uniform: &str,
+        value: T,
+    ) -> Result<(), BackendError> {
+        let info = self.inner.program.uniform_info(uniform).ok_or_else(|| {
+            BackendError::NoSuchUniform {
+                uniform: uniform.to_owned(),
+            }
+        })?;
+        if !value.is_allowed_type(info.gl_type) {
+            return Err(BackendError::UniformType {
+                uniform: uniform.to_owned(),
+                uniform_type: info.gl_type,
+            });
+        }
+        self.inner.uniforms.borrow_mut().insert(
+            uniform.to_owned(),
+            EffectUniform {
+                value: Box::new(value),
+                dirty: true,
+            },
+        );
+        Ok(())
+    }
+
+    pub(crate) fn program(&self) -> &Rc<Program> {
+        &self.inner.program
+    }
+
+    /// Upload the uniforms that changed since the effect was last used.
+    /// Assumes that the program of the effect is currently bound.
+    pub(crate) fn apply_uniforms(&self) -> Result<(), BackendError> {
+        for (name, uniform) in self.inner.uniforms.borrow_mut().iter_mut() {
+            if uniform.dirty {
+                self.inner.program.set_uniform(name, &*uniform.value)?;
+                uniform.dirty = false;
+            }
+        }
+        Ok(())
+    }
+}
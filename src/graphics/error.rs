@@ -12,6 +12,34 @@ pub enum GraphicsError {
     /// Could not cache the glyphs of the text to be rendered,
     /// because the cache is too small.
     InsufficientGlyphCache,
+    /// Could not pack the images of a texture atlas into the maximum atlas size.
+    InsufficientAtlasSize,
+    /// Could not parse the description of a texture atlas.
+    InvalidAtlasDescription { line: usize, message: &'static str },
+    /// Could not parse text with style tags, where the position is the byte offset
+    /// of the offending tag.
+    InvalidMarkup {
+        position: usize,
+        message: &'static str,
+    },
+    /// Could not parse the descriptor of a bitmap font, or it didn't match the page images.
+    InvalidBitmapFont(&'static str),
+    /// Could not parse the JSON description of a sprite sheet.
+    InvalidSpriteSheetJson(Box<dyn std::error::Error + Send + Sync>),
+    /// The description of a sprite sheet was parsed, but refers to frames that don't exist.
+    InvalidSpriteSheet(&'static str),
+    /// The pixels given for a texture are in a different format than the texture.
+    TextureFormatMismatch {
+        expected: super::TextureFormat,
+        actual: super::TextureFormat,
+    },
+    /// A region of a texture was not contained in the texture.
+    RegionOutOfBounds {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
 }
 
 impl From<image::ImageError> for GraphicsError {
@@ -32,7 +60,38 @@ impl std::fmt::Display for GraphicsError {
             GraphicsError::Image(err) => write!(f, "Failed to load image: {}", err),
             GraphicsError::Backend(err) => write!(f, "Failed to create texture: {}", err),
             GraphicsError::InvalidFont => write!(f, "Could not parse font data"),
+            GraphicsError::InvalidBitmapFont(message) => {
+                write!(f, "Invalid bitmap font: {}", message)
+            }
             GraphicsError::InsufficientGlyphCache => write!(f, "Glyph cache too small"),
+            GraphicsError::InsufficientAtlasSize => write!(f, "Texture atlas too small"),
+            GraphicsError::InvalidAtlasDescription { line, message } => {
+                write!(f, "Invalid atlas description (line {}): {}", line, message)
+            }
+            GraphicsError::InvalidMarkup { position, message } => {
+                write!(f, "Invalid markup (at {}): {}", position, message)
+            }
+            GraphicsError::InvalidSpriteSheetJson(err) => {
+                write!(f, "Could not parse sprite sheet: {}", err)
+            }
+            GraphicsError::InvalidSpriteSheet(message) => {
+                write!(f, "Invalid sprite sheet: {}", message)
+            }
+            GraphicsError::TextureFormatMismatch { expected, actual } => write!(
+                f,
+                "Expected pixels in texture format {:?}, got {:?}",
+                expected, actual
+            ),
+            GraphicsError::RegionOutOfBounds {
+                x,
+                y,
+                width,
+                height,
+            } => write!(
+                f,
+                "Region of size {}x{} at ({}, {}) exceeds the texture",
+                width, height, x, y
+            ),
         }
     }
 }
@@ -1,15 +1,149 @@
 //! A generic 2D triangle mesh.
 
+use gl::types::GLenum;
+
 use super::{
-    context::{BackendError, Buffer, Program, VertexArray},
+    context::{BackendError, Buffer, Texture, VertexArray},
     primitives::BasicPipeline2D,
     primitives::BasicVertex2D,
     primitives::Pipeline,
     primitives::VertexData,
-    RenderState,
+    tessellation::Geometry,
+    BlendMode, Color, Rect, RenderState, ShaderEffect, Texture2D,
 };
 use crate::{Context, GameResult};
 
+/// How the indexed vertices of a mesh form primitives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topology {
+    /// Every three vertices form a triangle.
+    Triangles,
+    /// Every vertex forms a triangle with the two vertices before it.
+    TriangleStrip,
+    /// Every vertex forms a triangle with the vertex before it and the first vertex.
+    TriangleFan,
+    /// Every two vertices form a line.
+    Lines,
+    /// Consecutive vertices are connected by lines.
+    LineStrip,
+    /// Consecutive vertices are connected by lines, and the last vertex with the first.
+    LineLoop,
+    /// Every vertex is drawn as a single point.
+    Points,
+}
+
+impl Topology {
+    fn to_gl(self) -> GLenum {
+        match self {
+            Topology::Triangles => gl::TRIANGLES,
+            Topology::TriangleStrip => gl::TRIANGLE_STRIP,
+            Topology::TriangleFan => gl::TRIANGLE_FAN,
+            Topology::Lines => gl::LINES,
+            Topology::LineStrip => gl::LINE_STRIP,
+            Topology::LineLoop => gl::LINE_LOOP,
+            Topology::Points => gl::POINTS,
+        }
+    }
+}
+
+/// Collects vertices and indices for creating a `Mesh`.
+///
+/// The methods for adding triangles, quads and shapes are meant for the
+/// `Triangles` topology, while single vertices and indices can be used with any topology.
+#[derive(Debug, Clone)]
+pub struct MeshBuilder {
+    vertices: Vec<BasicVertex2D>,
+    indices: Vec<u32>,
+    topology: Topology,
+    texture: Option<Texture2D>,
+}
+
+impl Default for MeshBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MeshBuilder {
+    pub fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            topology: Topology::Triangles,
+            texture: None,
+        }
+    }
+
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// The texture that is sampled at the texture coordinates of the vertices.
+    pub fn with_texture(mut self, texture: Texture2D) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Add a vertex without an index, returning the index that refers to it.
+    pub fn vertex(&mut self, vertex: BasicVertex2D) -> u32 {
+        let index = self.vertices.len() as u32;
+        self.vertices.push(vertex);
+        index
+    }
+
+    /// Add indices referring to vertices that have been added before.
+    pub fn indices(&mut self, indices: &[u32]) -> &mut Self {
+        self.indices.extend_from_slice(indices);
+        self
+    }
+
+    /// Add vertices together with indices that refer to them in order.
+    pub fn vertices(&mut self, vertices: &[BasicVertex2D]) -> &mut Self {
+        for vertex in vertices {
+            let index = self.vertex(*vertex);
+            self.indices.push(index);
+        }
+        self
+    }
+
+    pub fn triangle(&mut self, corners: [BasicVertex2D; 3]) -> &mut Self {
+        self.vertices(&corners)
+    }
+
+    /// Add a rectangle showing the given part of the texture in UV coordinates.
+    pub fn quad(&mut self, rect: Rect<f32>, uv: Rect<f32>, color: Color) -> &mut Self {
+        let positions = rect.corners();
+        let tex_coords = uv.corners();
+        let first = self.vertices.len() as u32;
+        for (position, tex_coord) in positions.iter().zip(tex_coords.iter()) {
+            self.vertex(BasicVertex2D {
+                position: (*position).into(),
+                tex_coord: (*tex_coord).into(),
+                color: color.into(),
+            });
+        }
+        // The corners are given in clockwise order starting at the top-left
+        self.indices(&[first, first + 1, first + 2, first, first + 2, first + 3])
+    }
+
+    /// Add the triangles of a tessellated shape.
+    pub fn geometry(&mut self, geometry: &Geometry) -> &mut Self {
+        let first = self.vertices.len() as u32;
+        self.vertices.extend_from_slice(&geometry.vertices);
+        self.indices
+            .extend(geometry.indices.iter().map(|index| first + index));
+        self
+    }
+
+    pub fn build(&self, ctx: &mut Context) -> Result<Mesh, BackendError> {
+        let mut mesh = Mesh::new(ctx, &self.vertices, &self.indices)?;
+        mesh.set_topology(self.topology);
+        mesh.set_texture(self.texture.clone());
+        Ok(mesh)
+    }
+}
+
 pub struct Mesh {
     pipeline: BasicPipeline2D,
     #[allow(unused)]
@@ -21,21 +155,24 @@ pub struct Mesh {
     vao: VertexArray,
 
     num_elements: i32,
+    num_vertices: usize,
+    /// The largest index, which must refer to an existing vertex
+    max_index: Option<u32>,
+    topology: Topology,
+    texture: Option<Texture2D>,
 }
 
 impl Mesh {
+    pub fn build() -> MeshBuilder {
+        MeshBuilder::new()
+    }
+
+    /// Create a mesh of untextured triangles from the given vertices and indices.
     pub fn new(
         ctx: &mut Context,
         vertices: &[BasicVertex2D],
         indices: &[u32],
     ) -> Result<Mesh, BackendError> {
-        if vertices.len() > std::u32::MAX as usize {
-            return Err(BackendError::TooLarge);
-        }
-        if indices.len() > std::i32::MAX as usize {
-            return Err(BackendError::TooLarge);
-        }
-
         let pipeline = BasicPipeline2D::new(ctx)?;
         let vbo = Buffer::new()?;
         let ebo = Buffer::new()?;
@@ -48,10 +185,7 @@ impl Mesh {
         // the ELEMENT_ARRAY_BUFFER cannot be bound.
         Buffer::bind(gl::ELEMENT_ARRAY_BUFFER, &ebo)?;
         unsafe {
-            // Safe because cgmath::Vector2 is repr(C)
-            Buffer::data(gl::ARRAY_BUFFER, vertices, gl::STATIC_DRAW)?;
-            Buffer::data(gl::ELEMENT_ARRAY_BUFFER, indices, gl::STATIC_DRAW)?;
-            // Safe because it corresponds to the layout of our buffer above
+            // Safe because it corresponds to the layout of our buffer
             for attrib in BasicVertex2D::attributes() {
                 attrib.set_pointer()?;
                 attrib.enable()?;
@@ -62,26 +196,180 @@ impl Mesh {
         Buffer::unbind(gl::ARRAY_BUFFER)?;
         VertexArray::unbind()?;
 
-        Ok(Mesh {
+        let mut mesh = Mesh {
             pipeline,
             vbo,
             ebo,
             vao,
-            num_elements: indices.len() as i32,
-        })
+            num_elements: 0,
+            num_vertices: 0,
+            max_index: None,
+            topology: Topology::Triangles,
+            texture: None,
+        };
+        mesh.upload_vertices(vertices, gl::STATIC_DRAW)?;
+        mesh.upload_indices(indices, gl::STATIC_DRAW)?;
+        Ok(mesh)
+    }
+
+    /// Replace all vertices of the mesh.
+    /// Fails with `InvalidSize` if the indices refer to vertices that don't exist afterwards.
+    pub fn set_vertices(&mut self, vertices: &[BasicVertex2D]) -> Result<(), BackendError> {
+        // Buffers that are updated are likely to be updated again
+        self.upload_vertices(vertices, gl::DYNAMIC_DRAW)
+    }
+
+    /// Replace all indices of the mesh, which determine the primitives that are drawn.
+    /// Fails with `InvalidSize` if an index refers to a vertex that doesn't exist.
+    pub fn set_indices(&mut self, indices: &[u32]) -> Result<(), BackendError> {
+        self.upload_indices(indices, gl::DYNAMIC_DRAW)
+    }
+
+    fn upload_vertices(
+        &mut self,
+        vertices: &[BasicVertex2D],
+        usage: GLenum,
+    ) -> Result<(), BackendError> {
+        if vertices.len() > std::u32::MAX as usize {
+            return Err(BackendError::TooLarge);
+        }
+        check_index_range(self.max_index, vertices.len())?;
+        Buffer::bind(gl::ARRAY_BUFFER, &self.vbo)?;
+        unsafe {
+            Buffer::data(gl::ARRAY_BUFFER, vertices, usage)?;
+        }
+        Buffer::unbind(gl::ARRAY_BUFFER)?;
+        self.num_vertices = vertices.len();
+        Ok(())
+    }
+
+    fn upload_indices(&mut self, indices: &[u32], usage: GLenum) -> Result<(), BackendError> {
+        if indices.len() > std::i32::MAX as usize {
+            return Err(BackendError::TooLarge);
+        }
+        let max_index = indices.iter().copied().max();
+        check_index_range(max_index, self.num_vertices)?;
+        // The element buffer can only be bound together with the VAO
+        VertexArray::bind(&self.vao)?;
+        unsafe {
+            Buffer::data(gl::ELEMENT_ARRAY_BUFFER, indices, usage)?;
+        }
+        VertexArray::unbind()?;
+        self.num_elements = indices.len() as i32;
+        self.max_index = max_index;
+        Ok(())
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology
+    }
+
+    /// The texture sampled at the texture coordinates of the vertices,
+    /// or `None` if only vertex colors are used.
+    pub fn texture(&self) -> Option<&Texture2D> {
+        self.texture.as_ref()
+    }
+
+    pub fn set_texture(&mut self, texture: Option<Texture2D>) {
+        // Always use the first texture unit
+        self.pipeline.set_texture(texture.as_ref().map(|_| 0));
+        if let Some(texture) = &texture {
+            self.pipeline.set_texture_format(texture.format());
+        }
+        self.texture = texture;
+    }
+
+    /// The blend mode this is rendered with, or `None` for no blending, which is the default.
+    pub fn blend_mode(&self) -> Option<BlendMode> {
+        *self.pipeline.get_blend_mode()
+    }
+
+    pub fn set_blend_mode(&mut self, blend_mode: Option<BlendMode>) {
+        self.pipeline.set_blend_mode(blend_mode)
+    }
+
+    /// The shader effect this is rendered with, or `None` when using the default shaders.
+    pub fn effect(&self) -> Option<&ShaderEffect> {
+        self.pipeline.get_effect()
+    }
+
+    /// Render with the given shader effect, or the default shaders if `None`.
+    pub fn set_effect(&mut self, effect: Option<ShaderEffect>) {
+        self.pipeline.set_effect(effect)
+    }
+}
+
+/// Make sure that the largest index refers to one of the given number of vertices.
+fn check_index_range(max_index: Option<u32>, num_vertices: usize) -> Result<(), BackendError> {
+    match max_index {
+        Some(index) if index as usize >= num_vertices => Err(BackendError::InvalidSize),
+        _ => Ok(()),
     }
 }
 
 impl super::Drawable for Mesh {
     fn draw(&mut self, ctx: &mut Context, state: RenderState) -> GameResult<()> {
-        self.pipeline.set_transform(state.transform);
+        self.pipeline
+            .set_transform(state.transform * ctx.graphics.transform.get());
         self.pipeline.apply(ctx)?;
         self.vao.bind()?;
         unsafe {
-            gl::DrawElements(gl::TRIANGLES, self.num_elements, gl::UNSIGNED_INT, 0 as _);
+            if let Some(texture) = &self.texture {
+                gl::ActiveTexture(gl::TEXTURE0);
+                Texture::bind(gl::TEXTURE_2D, texture.raw())?;
+            }
+            gl::DrawElements(
+                self.topology.to_gl(),
+                self.num_elements,
+                gl::UNSIGNED_INT,
+                0 as _,
+            );
+            if self.texture.is_some() {
+                Texture::unbind(gl::TEXTURE_2D)?;
+            }
         }
         VertexArray::unbind()?;
-        Program::unbind()?;
         Ok(())
     }
 }
+
+#[test]
+fn test_mesh_builder_offsets_indices() {
+    let mut builder = MeshBuilder::new();
+    builder.quad(Rect::unit_square(), Rect::unit_square(), Color::WHITE);
+    let mut geometry = Geometry::new();
+    geometry.fill_polygon(
+        &[
+            cgmath::Vector2::new(0.0, 0.0),
+            cgmath::Vector2::new(1.0, 0.0),
+            cgmath::Vector2::new(0.0, 1.0),
+        ],
+        Color::RED,
+    );
+    builder.geometry(&geometry);
+
+    assert_eq!(builder.vertices.len(), 7);
+    assert_eq!(&builder.indices[..6], &[0, 1, 2, 0, 2, 3]);
+    let mut triangle = builder.indices[6..].to_vec();
+    triangle.sort();
+    assert_eq!(triangle, vec![4, 5, 6]);
+    assert_eq!(builder.vertices[2].tex_coord, [1.0, 1.0]);
+}
+
+#[test]
+fn test_check_index_range() {
+    assert!(check_index_range(None, 0).is_ok());
+    assert!(check_index_range(Some(2), 3).is_ok());
+    assert!(matches!(
+        check_index_range(Some(3), 3),
+        Err(BackendError::InvalidSize)
+    ));
+    assert!(matches!(
+        check_index_range(Some(0), 0),
+        Err(BackendError::InvalidSize)
+    ));
+}
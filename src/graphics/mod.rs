@@ -1,4 +1,5 @@
-use super::{Context, GameResult};
+use super::{CheckGl, Context, GameResult};
+use cgmath::Matrix3;
 use glutin::dpi::PhysicalSize;
 
 mod color;
@@ -8,15 +9,27 @@ pub use error::GraphicsError;
 mod rect;
 pub use rect::Rect;
 mod texture;
-pub use texture::{Texture2D, TextureView2D};
+pub use texture::{FilterMode, SamplerOptions, Texture2D, TextureFormat, TextureView2D, WrapMode};
 mod mesh;
-pub use mesh::Mesh;
+pub use mesh::{Mesh, MeshBuilder, Topology};
 mod sprite;
 pub use sprite::Sprite;
 mod blend;
 pub use blend::BlendMode;
 mod batch;
 pub use batch::{BatchRender, Quad, QuadBuilder};
+mod canvas;
+pub use canvas::Canvas;
+mod effect;
+pub use effect::ShaderEffect;
+mod atlas;
+pub use atlas::{AtlasLayout, TextureAtlas, TextureAtlasBuilder};
+mod camera;
+pub use camera::Camera2D;
+mod animation;
+pub use animation::{Animation, AnimationEvent, PlaybackMode};
+mod sprite_sheet;
+pub use sprite_sheet::SpriteSheet;
 
 pub mod text;
 
@@ -27,10 +40,17 @@ pub(crate) mod context;
 
 pub mod transform;
 
-pub use context::BackendError;
+pub mod tessellation;
+
+pub mod debug;
+
+pub mod testing;
+
+pub use context::{BackendError, UniformValue};
 
 /// Implemented by every "well-behaved" entity that can be drawn, meaning
-/// - it respects the current coordinate system (TODO)
+/// - it respects the current coordinate system, given by the transform of the render state
+///   and the current transform of the context (see `push_transform`)
 pub trait Drawable {
     fn draw(&mut self, ctx: &mut Context, state: RenderState) -> GameResult<()>;
 }
@@ -42,11 +62,102 @@ pub fn draw<T: Drawable>(ctx: &mut Context, drawable: &mut T) -> GameResult<()>
     drawable.draw(ctx, initial_state)
 }
 
+/// Draw in world coordinates as seen through the camera.
+pub fn draw_with_camera<T: Drawable>(
+    ctx: &mut Context,
+    camera: &Camera2D,
+    drawable: &mut T,
+) -> GameResult<()> {
+    let initial_state = camera.render_state(ctx);
+    drawable.draw(ctx, initial_state)
+}
+
+/// Multiply the current transform with the given transform from the right,
+/// so that it is applied to everything drawn afterwards before the current transform.
+/// The previous transform is restored by a matching `pop_transform`.
+///
+/// For `BatchRender`, the transform is applied when primitives are added to the batch,
+/// rather than when the batch is drawn.
+pub fn push_transform(ctx: &mut Context, transform: Matrix3<f32>) {
+    ctx.graphics.push_transform(transform)
+}
+
+/// Restore the transform that was current before the last `push_transform`.
+///
+/// # Panics
+///
+/// Panics if there is no matching `push_transform`.
+pub fn pop_transform(ctx: &mut Context) {
+    if !ctx.graphics.pop_transform() {
+        panic!("pop_transform without matching push_transform");
+    }
+}
+
+/// Apply the given transform while running `f`, restoring the previous transform afterwards.
+pub fn with_transform<R, F: FnOnce(&mut Context) -> R>(
+    ctx: &mut Context,
+    transform: Matrix3<f32>,
+    f: F,
+) -> R {
+    push_transform(ctx, transform);
+    let result = f(ctx);
+    pop_transform(ctx);
+    result
+}
+
+/// The transform that is currently applied to everything that is drawn.
+/// It is reset to the identity at the start of every frame.
+pub fn current_transform(ctx: &mut Context) -> Matrix3<f32> {
+    ctx.graphics.transform.get()
+}
+
 /// Sets the blend mode used for subsequent draw calls.
 pub fn set_blend_mode(ctx: &mut Context, blend: Option<BlendMode>) -> GameResult<()> {
     ctx.graphics.set_blend_mode(blend).map_err(|e| e.into())
 }
 
+/// Restrict subsequent drawing, including `clear`, to the given rectangle in pixel coordinates
+/// of the current render target, or remove the restriction if `None`.
+///
+/// Primitives added to a `BatchRender` are clipped to the rectangle that was set
+/// when they were added, rather than when the batch is drawn.
+pub fn set_scissor(ctx: &mut Context, scissor: Option<Rect<u32>>) -> GameResult<()> {
+    ctx.graphics.set_scissor(scissor).map_err(|e| e.into())
+}
+
+/// The rectangle that drawing is currently restricted to.
+pub fn scissor(ctx: &mut Context) -> Option<Rect<u32>> {
+    ctx.graphics.scissor.get()
+}
+
+/// Restrict subsequent drawing to the area covered by the given drawable, e.g. a `Mesh`,
+/// until `remove_mask` is called.
+///
+/// The mask itself is not visible, and `clear` is not affected by it. Unlike scissor rectangles,
+/// the mask applies to everything drawn while it is set, including batches that were filled before.
+pub fn set_mask<T: Drawable>(ctx: &mut Context, mask: &mut T) -> GameResult<()> {
+    ctx.graphics.begin_mask()?;
+    let result = draw(ctx, mask);
+    ctx.graphics.end_mask()?;
+    result
+}
+
+/// Stop restricting drawing to the mask.
+pub fn remove_mask(ctx: &mut Context) -> GameResult<()> {
+    ctx.graphics.disable_mask().map_err(|e| e.into())
+}
+
+/// Sets the canvas that subsequent draw calls render to, or the screen if `None`.
+///
+/// While a canvas is set, the viewport and the pixel coordinate system used by `draw`
+/// correspond to the size of the canvas rather than the screen.
+/// The scissor rectangle and mask are reset when changing the render target.
+pub fn set_render_target(ctx: &mut Context, target: Option<&Canvas>) -> GameResult<()> {
+    ctx.graphics
+        .set_render_target(target.cloned())
+        .map_err(|e| e.into())
+}
+
 pub fn clear(_ctx: &mut Context, color: Color) {
     unsafe {
         gl::ClearColor(color.r, color.g, color.b, color.a);
@@ -54,6 +165,39 @@ pub fn clear(_ctx: &mut Context, color: Color) {
     }
 }
 
+/// Read back the contents of the current render target into an image.
+///
+/// The returned image has its origin in the top-left corner, like the pixel coordinates
+/// used for drawing.
+pub fn screenshot(ctx: &mut Context) -> GameResult<image::RgbaImage> {
+    let size = ctx.graphics.target_size();
+    let row_len = size.width as usize * 4;
+    let mut data = vec![0u8; row_len * size.height as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        CheckGl!(gl::ReadPixels(
+            0,
+            0,
+            size.width as i32,
+            size.height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            data.as_mut_ptr() as _
+        ))?;
+    }
+    // OpenGL returns the bottom row first, but canvases are already rendered upside down
+    if ctx.graphics.render_target.is_none() {
+        let mut flipped = Vec::with_capacity(data.len());
+        for row in data.chunks_exact(row_len).rev() {
+            flipped.extend_from_slice(row);
+        }
+        data = flipped;
+    }
+    let image = image::RgbaImage::from_raw(size.width, size.height, data)
+        .expect("Image bounds should correspond to buffer size");
+    Ok(image)
+}
+
 pub fn screen_size(ctx: &mut Context) -> PhysicalSize<u32> {
     ctx.graphics.screen_size
 }
@@ -61,3 +205,8 @@ pub fn screen_size(ctx: &mut Context) -> PhysicalSize<u32> {
 pub fn scale_factor(ctx: &mut Context) -> f64 {
     ctx.graphics.scale_factor
 }
+
+/// The largest width and height of a texture supported by the graphics driver.
+pub fn max_texture_size(ctx: &mut Context) -> u32 {
+    ctx.graphics.max_texture_size
+}
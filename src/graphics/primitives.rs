@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, rc::Rc};
 
 use cgmath::Matrix3;
 
@@ -6,6 +6,7 @@ use crate::Context;
 
 use super::{
     context::Program, context::UniformValue, context::VertexAttrib, BackendError, BlendMode, Color,
+    ShaderEffect, TextureFormat,
 };
 
 // pub struct Decomposed {
@@ -29,31 +30,61 @@ pub trait VertexData: Copy {
 
 /// A basic pipeline for 2D rendering that can be used in a wide variety of drawables.
 pub struct BasicPipeline2D {
-    program: Program,
+    /// The program shared by all basic pipelines.
+    program: Rc<Program>,
+    /// Identifies this pipeline when setting uniforms of shared programs.
+    user: u64,
     transform: ShaderParameter<Matrix3<f32>>,
     texture: ShaderParameter<i32>,
     use_texture: ShaderParameter<bool>,
+    /// Whether the texture only stores coverage in its red channel.
+    coverage_texture: ShaderParameter<bool>,
     blend_mode: Option<BlendMode>,
+    effect: Option<ShaderEffect>,
 }
 
 impl BasicPipeline2D {
-    pub fn new(_ctx: &mut Context) -> Result<Self, BackendError> {
-        // TODO: we should cache the individual shaders and only relink them
-        // We probably don't want to cache the program object itself, because
-        // that would mean that uniform values are also shared across instances.
-        let program =
-            Program::from_source(Self::VERTEX_SHADER_330_CORE, Self::FRAGMENT_SHADER_330_CORE)?;
+    pub fn new(ctx: &mut Context) -> Result<Self, BackendError> {
+        // The program object is shared, the uniform values are tracked per pipeline instead
+        let program = ctx
+            .graphics
+            .cached_program(Self::VERTEX_SHADER_330_CORE, Self::FRAGMENT_SHADER_330_CORE)?;
 
         Ok(Self {
             program,
+            user: ctx.graphics.new_program_user(),
             // the identity matrix is the default matrix in the shader program.
             transform: ShaderParameter::new("Transform", cgmath::SquareMatrix::identity(), false),
             texture: ShaderParameter::new("Texture0", 0, true),
             use_texture: ShaderParameter::new("UseTexture0", false, false),
+            coverage_texture: ShaderParameter::new("CoverageTexture0", false, false),
             blend_mode: None,
+            effect: None,
         })
     }
 
+    /// The custom shaders used instead of the default ones when the pipeline is next applied.
+    pub fn get_effect(&self) -> Option<&ShaderEffect> {
+        self.effect.as_ref()
+    }
+
+    /// The custom shaders used instead of the default ones when the pipeline is next applied.
+    pub fn set_effect(&mut self, new: Option<ShaderEffect>) {
+        if self.effect != new {
+            // The uniforms of the newly used program might not be up to date
+            self.mark_dirty();
+            self.effect = new;
+        }
+    }
+
+    /// Ensure that all uniforms are uploaded when the pipeline is next applied.
+    fn mark_dirty(&mut self) {
+        self.transform.dirty = true;
+        self.texture.dirty = true;
+        self.use_texture.dirty = true;
+        self.coverage_texture.dirty = true;
+    }
+
     /// The blend mode used when the pipeline is next applied.
     pub fn get_blend_mode(&self) -> &Option<BlendMode> {
         &self.blend_mode
@@ -93,8 +124,18 @@ impl BasicPipeline2D {
         }
     }
 
+    /// The format of the texture used when the pipeline is next applied.
+    ///
+    /// Textures in the `R8` format are sampled as white with the red channel as opacity,
+    /// so that they can be tinted like any other texture.
+    /// Custom effects are responsible for handling this format themselves,
+    /// e.g. by checking the `CoverageTexture0` uniform.
+    pub fn set_texture_format(&mut self, format: TextureFormat) {
+        self.coverage_texture.set(format == TextureFormat::R8)
+    }
+
     // TODO: support multiple versions of GLSL
-    const VERTEX_SHADER_330_CORE: &'static str = r"#version 330 core
+    pub(crate) const VERTEX_SHADER_330_CORE: &'static str = r"#version 330 core
     layout (location = 0) in vec2 Position;
     layout (location = 1) in vec2 TexCoord;
     layout (location = 2) in vec4 Color;
@@ -121,11 +162,15 @@ impl BasicPipeline2D {
 
     uniform sampler2D Texture0;
     uniform bool UseTexture0 = false;
+    uniform bool CoverageTexture0 = false;
 
     void main()
     {
         if(UseTexture0) {
             vec4 tex_color = texture(Texture0, Vert_Frag_TexCoord);
+            if(CoverageTexture0) {
+                tex_color = vec4(1.0, 1.0, 1.0, tex_color.r);
+            }
             FragColor = tex_color * Vert_Frag_Color;
         } else {
             FragColor = Vert_Frag_Color;
@@ -137,11 +182,28 @@ impl Pipeline for BasicPipeline2D {
     type Vertex = BasicVertex2D;
 
     fn apply(&mut self, ctx: &mut Context) -> Result<(), BackendError> {
-        // TODO: keep track of currently used program?
-        Program::bind(&self.program)?;
-        self.transform.set_uniform(&self.program)?;
-        self.texture.set_uniform(&self.program)?;
-        self.use_texture.set_uniform(&self.program)?;
+        let effect = self.effect.clone();
+        let program = effect
+            .as_ref()
+            .map_or_else(|| self.program.clone(), |effect| effect.program().clone());
+        ctx.graphics.use_program(&program)?;
+        // Programs are shared, so another pipeline might have changed the uniforms since
+        if program.claim(self.user) {
+            self.mark_dirty();
+        }
+        if let Some(effect) = effect {
+            // Custom shaders don't need to make use of all the standard uniforms
+            self.transform.set_uniform_if_present(&program)?;
+            self.texture.set_uniform_if_present(&program)?;
+            self.use_texture.set_uniform_if_present(&program)?;
+            self.coverage_texture.set_uniform_if_present(&program)?;
+            effect.apply_uniforms()?;
+        } else {
+            self.transform.set_uniform(&program)?;
+            self.texture.set_uniform(&program)?;
+            self.use_texture.set_uniform(&program)?;
+            self.coverage_texture.set_uniform(&program)?;
+        }
         ctx.graphics.set_blend_mode(self.blend_mode)?;
         Ok(())
     }
@@ -234,4 +296,14 @@ impl<T: UniformValue + PartialEq + Debug> ShaderParameter<T> {
         }
         Ok(())
     }
+
+    /// Like `set_uniform`, but does nothing if the program doesn't have this uniform.
+    fn set_uniform_if_present(&mut self, program: &Program) -> Result<(), BackendError> {
+        if program.uniform_info(&self.name).is_some() {
+            self.set_uniform(program)
+        } else {
+            self.dirty = false;
+            Ok(())
+        }
+    }
 }
@@ -3,12 +3,12 @@
 use cgmath::{Matrix3, Rad, Vector2};
 
 use super::{
-    context::{BackendError, Buffer, Program, Texture, VertexArray},
+    context::{BackendError, Buffer, Texture, VertexArray},
     primitives::BasicPipeline2D,
     primitives::BasicVertex2D,
     primitives::Pipeline,
     primitives::VertexData,
-    BlendMode, Color, Rect, RenderState, Texture2D,
+    BlendMode, Color, Rect, RenderState, SamplerOptions, ShaderEffect, Texture2D, TextureView2D,
 };
 use crate::{Context, GameResult};
 
@@ -29,6 +29,12 @@ pub struct SpriteBuilder {
     origin: Vector2<f32>,
     /// Rotation angle around the origin.
     rotation: Rad<f32>,
+    /// Whether the texture is mirrored horizontally.
+    flip_x: bool,
+    /// Whether the texture is mirrored vertically.
+    flip_y: bool,
+    /// Sampler options that are applied to the texture when building the sprite.
+    sampler: Option<SamplerOptions>,
 }
 
 impl SpriteBuilder {
@@ -46,6 +52,9 @@ impl SpriteBuilder {
             },
             origin: Vector2 { x: 0.0, y: 0.0 },
             rotation: Rad(0.0),
+            flip_x: false,
+            flip_y: false,
+            sampler: None,
             texture,
         }
     }
@@ -75,8 +84,38 @@ impl SpriteBuilder {
         self
     }
 
+    pub fn with_rotation(mut self, rotation: Rad<f32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Mirror the texture horizontally.
+    pub fn with_flip_x(mut self, flip_x: bool) -> Self {
+        self.flip_x = flip_x;
+        self
+    }
+
+    /// Mirror the texture vertically.
+    pub fn with_flip_y(mut self, flip_y: bool) -> Self {
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// Change how the texture is sampled when building the sprite.
+    ///
+    /// Since the options are stored in the texture, this also affects everything else using it.
+    pub fn with_sampler(mut self, sampler: SamplerOptions) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
     pub fn build(self, ctx: &mut Context) -> Result<Sprite, BackendError> {
-        Sprite::new(
+        if let Some(sampler) = self.sampler {
+            if self.texture.sampler() != sampler {
+                self.texture.apply_sampler(sampler)?;
+            }
+        }
+        let mut sprite = Sprite::new(
             ctx,
             self.texture,
             self.source,
@@ -85,14 +124,16 @@ impl SpriteBuilder {
             self.origin,
             self.rotation,
             self.tint,
-        )
+        )?;
+        sprite.set_flip_x(self.flip_x);
+        sprite.set_flip_y(self.flip_y);
+        Ok(sprite)
     }
 }
 
 pub struct Sprite {
     pipeline: BasicPipeline2D,
     /// Vertex buffer object
-    #[allow(unused)]
     vbo: Buffer,
     vao: VertexArray,
 
@@ -100,6 +141,11 @@ pub struct Sprite {
     texture: Texture2D,
     source: Rect<f32>,
     tint: Color,
+    flip_x: bool,
+    flip_y: bool,
+    /// Whether the vertex buffer needs to be updated before the next draw,
+    /// because the tint, source or flipping changed.
+    vertices_dirty: bool,
 
     /// The position of the origin in global coordinates.
     position: Vector2<f32>,
@@ -110,7 +156,6 @@ pub struct Sprite {
     origin: Vector2<f32>,
     /// Rotation angle around the origin.
     rotation: Rad<f32>,
-    // TODO: sampler options
 }
 
 impl Sprite {
@@ -140,32 +185,8 @@ impl Sprite {
         VertexArray::bind(&vao)?;
         Buffer::bind(gl::ARRAY_BUFFER, &vbo)?;
         unsafe {
-            let top_left = source.top_left;
-            let bottom_right = source.bottom_right;
-
-            let vertices = &[
-                BasicVertex2D {
-                    position: [0.0, 0.0],
-                    tex_coord: [top_left.x, top_left.y],
-                    color: tint.into(),
-                },
-                BasicVertex2D {
-                    position: [1.0, 0.0],
-                    tex_coord: [bottom_right.x, top_left.y],
-                    color: tint.into(),
-                },
-                BasicVertex2D {
-                    position: [0.0, 1.0],
-                    tex_coord: [top_left.x, bottom_right.y],
-                    color: tint.into(),
-                },
-                BasicVertex2D {
-                    position: [1.0, 1.0],
-                    tex_coord: [bottom_right.x, bottom_right.y],
-                    color: tint.into(),
-                },
-            ];
-            Buffer::data(gl::ARRAY_BUFFER, vertices, gl::STATIC_DRAW)?;
+            let vertices = quad_vertices(source, tint, false, false);
+            Buffer::data(gl::ARRAY_BUFFER, &vertices, gl::DYNAMIC_DRAW)?;
 
             for attrib in BasicVertex2D::attributes() {
                 attrib.set_pointer()?;
@@ -186,6 +207,9 @@ impl Sprite {
             position,
             size,
             tint,
+            flip_x: false,
+            flip_y: false,
+            vertices_dirty: false,
             origin,
             rotation,
         })
@@ -196,25 +220,60 @@ impl Sprite {
         &self.texture
     }
 
+    /// Draw a different texture, keeping the source rectangle.
+    pub fn set_texture(&mut self, texture: Texture2D) {
+        self.texture = texture
+    }
+
+    /// Draw a part of a texture, e.g. the current frame of an `Animation`.
+    pub fn set_texture_view(&mut self, view: TextureView2D) {
+        self.set_texture(view.texture);
+        self.set_source(view.source);
+    }
+
     /// The tint color of this sprite that is multiplied with the texture color.
-    ///
-    /// # Note
-    ///
-    /// The tint is baked into the vertex buffer and cannot be changed after creating the sprite.
     pub fn tint(&self) -> Color {
         self.tint
     }
 
+    /// Change the tint color, which is uploaded the next time the sprite is drawn.
+    pub fn set_tint(&mut self, tint: Color) {
+        self.vertices_dirty |= self.tint != tint;
+        self.tint = tint
+    }
+
     /// The part of the texture that is used for rendering this sprite,
     /// given in normalized UV coordinates.
-    ///
-    /// # Note
-    ///
-    /// The source rectangle is baked into the vertex buffer and cannot be changed after creating the sprite.
     pub fn source(&self) -> Rect<f32> {
         self.source
     }
 
+    /// Change the source rectangle, which is uploaded the next time the sprite is drawn.
+    pub fn set_source(&mut self, source: Rect<f32>) {
+        self.vertices_dirty |= self.source != source;
+        self.source = source
+    }
+
+    /// Whether the texture is mirrored horizontally.
+    pub fn flip_x(&self) -> bool {
+        self.flip_x
+    }
+
+    pub fn set_flip_x(&mut self, flip_x: bool) {
+        self.vertices_dirty |= self.flip_x != flip_x;
+        self.flip_x = flip_x
+    }
+
+    /// Whether the texture is mirrored vertically.
+    pub fn flip_y(&self) -> bool {
+        self.flip_y
+    }
+
+    pub fn set_flip_y(&mut self, flip_y: bool) {
+        self.vertices_dirty |= self.flip_y != flip_y;
+        self.flip_y = flip_y
+    }
+
     pub fn position(&self) -> Vector2<f32> {
         self.position
     }
@@ -247,6 +306,16 @@ impl Sprite {
         self.origin = origin
     }
 
+    /// The shader effect this is rendered with, or `None` when using the default shaders.
+    pub fn effect(&self) -> Option<&ShaderEffect> {
+        self.pipeline.get_effect()
+    }
+
+    /// Render with the given shader effect, or the default shaders if `None`.
+    pub fn set_effect(&mut self, effect: Option<ShaderEffect>) {
+        self.pipeline.set_effect(effect)
+    }
+
     pub fn local_transform(&self) -> Matrix3<f32> {
         let origin = super::transform::translate(-self.origin);
         let rotate = super::transform::rotate(self.rotation);
@@ -254,12 +323,52 @@ impl Sprite {
         let position = super::transform::translate(self.position);
         position * scale * rotate * origin
     }
+
+    /// Upload the vertices if the tint, source or flipping changed since the last upload.
+    fn update_vertices(&mut self) -> Result<(), BackendError> {
+        if !self.vertices_dirty {
+            return Ok(());
+        }
+        let vertices = quad_vertices(self.source, self.tint, self.flip_x, self.flip_y);
+        Buffer::bind(gl::ARRAY_BUFFER, &self.vbo)?;
+        unsafe {
+            Buffer::sub_data(gl::ARRAY_BUFFER, 0, &vertices)?;
+        }
+        Buffer::unbind(gl::ARRAY_BUFFER)?;
+        self.vertices_dirty = false;
+        Ok(())
+    }
+}
+
+/// The vertices of the unit square drawn as triangle strip.
+fn quad_vertices(source: Rect<f32>, tint: Color, flip_x: bool, flip_y: bool) -> [BasicVertex2D; 4] {
+    let (mut left, mut right) = (source.top_left.x, source.bottom_right.x);
+    let (mut top, mut bottom) = (source.top_left.y, source.bottom_right.y);
+    if flip_x {
+        std::mem::swap(&mut left, &mut right);
+    }
+    if flip_y {
+        std::mem::swap(&mut top, &mut bottom);
+    }
+    let vertex = |position: [f32; 2], tex_coord: [f32; 2]| BasicVertex2D {
+        position,
+        tex_coord,
+        color: tint.into(),
+    };
+    [
+        vertex([0.0, 0.0], [left, top]),
+        vertex([1.0, 0.0], [right, top]),
+        vertex([0.0, 1.0], [left, bottom]),
+        vertex([1.0, 1.0], [right, bottom]),
+    ]
 }
 
 impl super::Drawable for Sprite {
     fn draw(&mut self, ctx: &mut Context, mut state: RenderState) -> GameResult<()> {
-        state.transform = state.transform * self.local_transform();
+        self.update_vertices()?;
+        state.transform = state.transform * ctx.graphics.transform.get() * self.local_transform();
         self.pipeline.set_transform(state.transform);
+        self.pipeline.set_texture_format(self.texture.format());
         self.pipeline.apply(ctx)?;
         self.vao.bind()?;
         unsafe {
@@ -269,7 +378,32 @@ impl super::Drawable for Sprite {
             Texture::unbind(gl::TEXTURE_2D)?;
         }
         VertexArray::unbind()?;
-        Program::unbind()?;
         Ok(())
     }
 }
+
+#[test]
+fn test_quad_vertices_flip() {
+    let source = Rect {
+        top_left: Vector2::new(0.25, 0.0),
+        bottom_right: Vector2::new(0.5, 1.0),
+    };
+    let tex_coords = |vertices: [BasicVertex2D; 4]| {
+        vertices
+            .iter()
+            .map(|vertex| vertex.tex_coord)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        tex_coords(quad_vertices(source, Color::WHITE, false, false)),
+        vec![[0.25, 0.0], [0.5, 0.0], [0.25, 1.0], [0.5, 1.0]]
+    );
+    assert_eq!(
+        tex_coords(quad_vertices(source, Color::WHITE, true, false)),
+        vec![[0.5, 0.0], [0.25, 0.0], [0.5, 1.0], [0.25, 1.0]]
+    );
+    assert_eq!(
+        tex_coords(quad_vertices(source, Color::WHITE, false, true)),
+        vec![[0.25, 1.0], [0.5, 1.0], [0.25, 0.0], [0.5, 0.0]]
+    );
+}
@@ -0,0 +1,431 @@
+//! Slicing a texture into the frames of animations.
+
+use std::{fmt, path::Path, time::Duration};
+
+use cgmath::Vector2;
+use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use super::{Animation, GraphicsError, PlaybackMode, Rect, Texture2D, TextureView2D};
+use crate::{Context, GameResult};
+
+/// How long frames are shown when the sprite sheet doesn't specify a duration.
+const DEFAULT_FRAME_DURATION: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq)]
+struct SheetFrame {
+    name: Option<String>,
+    /// The part of the texture in pixel coordinates
+    region: Rect<u32>,
+    duration: Option<Duration>,
+}
+
+/// A named range of frames forming an animation.
+#[derive(Debug, Clone, PartialEq)]
+struct FrameTag {
+    name: String,
+    /// Index of the first frame
+    from: usize,
+    /// Index of the last frame, inclusive
+    to: usize,
+    mode: PlaybackMode,
+    /// Whether the frames are played from last to first
+    reverse: bool,
+}
+
+/// A texture divided into frames, which are usually played as animations.
+#[derive(Debug, Clone)]
+pub struct SpriteSheet {
+    texture: Texture2D,
+    frames: Vec<SheetFrame>,
+    tags: Vec<FrameTag>,
+}
+
+impl SpriteSheet {
+    /// Divide the texture into a grid of equally sized frames,
+    /// which are numbered row by row starting at the top-left.
+    pub fn from_grid(texture: Texture2D, frame_size: Vector2<u32>) -> Self {
+        Self::from_grid_with_spacing(texture, frame_size, 0, 0)
+    }
+
+    /// Like `from_grid`, but with `margin` pixels around the whole grid
+    /// and `spacing` pixels between neighboring frames.
+    pub fn from_grid_with_spacing(
+        texture: Texture2D,
+        frame_size: Vector2<u32>,
+        margin: u32,
+        spacing: u32,
+    ) -> Self {
+        let size = Vector2::new(texture.width(), texture.height());
+        let frames = grid_regions(size, frame_size, margin, spacing)
+            .into_iter()
+            .map(|region| SheetFrame {
+                name: None,
+                region,
+                duration: None,
+            })
+            .collect();
+        Self {
+            texture,
+            frames,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Read the frames from a JSON description as exported by Aseprite or TexturePacker,
+    /// where `frames` is either an array or an object, in which case the keys are
+    /// the names of the frames.
+    ///
+    /// Frame durations and the animations in `meta.frameTags` are taken from Aseprite exports.
+    /// Trimmed and rotated frames are not supported.
+    pub fn from_json(texture: Texture2D, json: &str) -> Result<Self, GraphicsError> {
+        let (frames, tags) = parse_json(json)?;
+        for frame in &frames {
+            let size = frame.region.size();
+            if frame.region.bottom_right.x > texture.width()
+                || frame.region.bottom_right.y > texture.height()
+            {
+                return Err(GraphicsError::RegionOutOfBounds {
+                    x: frame.region.top_left.x,
+                    y: frame.region.top_left.y,
+                    width: size.x,
+                    height: size.y,
+                });
+            }
+        }
+        Ok(Self {
+            texture,
+            frames,
+            tags,
+        })
+    }
+
+    /// Load a sprite sheet from its image and JSON description, see `from_json`.
+    pub fn from_files<P: AsRef<Path>, Q: AsRef<Path>>(
+        ctx: &mut Context,
+        image_filename: P,
+        json_filename: Q,
+    ) -> GameResult<Self> {
+        let json = std::fs::read_to_string(json_filename)?;
+        let texture = Texture2D::from_file(ctx, image_filename)?;
+        Ok(Self::from_json(texture, &json)?)
+    }
+
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    /// The number of frames.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The part of the texture showing the frame with the given index.
+    pub fn frame(&self, index: usize) -> Option<TextureView2D> {
+        let frame = self.frames.get(index)?;
+        Some(TextureView2D::from_pixels(
+            self.texture.clone(),
+            frame.region,
+        ))
+    }
+
+    /// The part of the texture showing the frame with the given name.
+    pub fn frame_by_name(&self, name: &str) -> Option<TextureView2D> {
+        let index = self
+            .frames
+            .iter()
+            .position(|frame| frame.name.as_deref() == Some(name))?;
+        self.frame(index)
+    }
+
+    /// The duration of the frame given in the sprite sheet, if any.
+    pub fn frame_duration(&self, index: usize) -> Option<Duration> {
+        self.frames.get(index)?.duration
+    }
+
+    /// The names of the animations defined in the sprite sheet.
+    pub fn tag_names(&self) -> impl Iterator<Item = &str> {
+        self.tags.iter().map(|tag| tag.name.as_str())
+    }
+
+    /// Create an animation showing the frames with the given indices for the same duration each,
+    /// or `None` if one of the frames doesn't exist.
+    pub fn animation(
+        &self,
+        frames: &[usize],
+        frame_duration: Duration,
+        mode: PlaybackMode,
+    ) -> Option<Animation> {
+        let views = frames
+            .iter()
+            .map(|index| self.frame(*index))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Animation::from_frames(views, frame_duration, mode))
+    }
+
+    /// Create the animation with the given name that is defined in the sprite sheet,
+    /// using the durations of the frames in the sprite sheet.
+    pub fn tagged_animation(&self, name: &str) -> Option<Animation> {
+        let tag = self.tags.iter().find(|tag| tag.name == name)?;
+        let mut indices: Vec<usize> = (tag.from..=tag.to).collect();
+        if tag.reverse {
+            indices.reverse();
+        }
+        let animation = indices
+            .into_iter()
+            .fold(Animation::new(tag.mode), |animation, index| {
+                let view =
+                    TextureView2D::from_pixels(self.texture.clone(), self.frames[index].region);
+                let duration = self.frames[index]
+                    .duration
+                    .unwrap_or(DEFAULT_FRAME_DURATION);
+                animation.with_frame(view, duration)
+            });
+        Some(animation)
+    }
+}
+
+/// The frames of a grid covering an area of the given size.
+fn grid_regions(
+    size: Vector2<u32>,
+    frame_size: Vector2<u32>,
+    margin: u32,
+    spacing: u32,
+) -> Vec<Rect<u32>> {
+    let mut regions = Vec::new();
+    if frame_size.x == 0 || frame_size.y == 0 {
+        return regions;
+    }
+    // Whether a frame starting at `start` ends inside of the margin, without overflowing
+    let fits = |start: u32, frame: u32, limit: u32| {
+        let end = start
+            .checked_add(frame)
+            .and_then(|end| end.checked_add(margin));
+        matches!(end, Some(end) if end <= limit)
+    };
+    let next = |start: u32, frame: u32| start.checked_add(frame)?.checked_add(spacing);
+    let mut y = margin;
+    while fits(y, frame_size.y, size.y) {
+        let mut x = margin;
+        while fits(x, frame_size.x, size.x) {
+            let top_left = Vector2::new(x, y);
+            regions.push(Rect {
+                top_left,
+                bottom_right: top_left + frame_size,
+            });
+            x = match next(x, frame_size.x) {
+                Some(x) => x,
+                None => break,
+            };
+        }
+        y = match next(y, frame_size.y) {
+            Some(y) => y,
+            None => break,
+        };
+    }
+    regions
+}
+
+#[derive(Deserialize)]
+struct JsonSheet {
+    #[serde(deserialize_with = "deserialize_frames")]
+    frames: Vec<JsonFrame>,
+    #[serde(default)]
+    meta: JsonMeta,
+}
+
+#[derive(Deserialize)]
+struct JsonFrame {
+    #[serde(default)]
+    filename: Option<String>,
+    frame: JsonRect,
+    /// In milliseconds
+    #[serde(default)]
+    duration: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct JsonRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize, Default)]
+struct JsonMeta {
+    #[serde(default, rename = "frameTags")]
+    frame_tags: Vec<JsonTag>,
+}
+
+#[derive(Deserialize)]
+struct JsonTag {
+    name: String,
+    from: usize,
+    to: usize,
+    #[serde(default)]
+    direction: Option<String>,
+}
+
+/// Accept frames both as an array and as an object, keeping the order in which they appear.
+fn deserialize_frames<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<JsonFrame>, D::Error> {
+    struct FramesVisitor;
+
+    impl<'de> Visitor<'de> for FramesVisitor {
+        type Value = Vec<JsonFrame>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "an array or object of frames")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut frames = Vec::new();
+            while let Some(frame) = seq.next_element()? {
+                frames.push(frame);
+            }
+            Ok(frames)
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut frames = Vec::new();
+            while let Some((name, frame)) = map.next_entry::<String, JsonFrame>()? {
+                frames.push(JsonFrame {
+                    filename: Some(name),
+                    ..frame
+                });
+            }
+            Ok(frames)
+        }
+    }
+
+    deserializer.deserialize_any(FramesVisitor)
+}
+
+fn parse_json(json: &str) -> Result<(Vec<SheetFrame>, Vec<FrameTag>), GraphicsError> {
+    let sheet: JsonSheet = serde_json::from_str(json)
+        .map_err(|err| GraphicsError::InvalidSpriteSheetJson(Box::new(err)))?;
+    let mut frames = Vec::with_capacity(sheet.frames.len());
+    for frame in sheet.frames {
+        let JsonRect { x, y, w, h } = frame.frame;
+        let bottom_right = match (x.checked_add(w), y.checked_add(h)) {
+            (Some(right), Some(bottom)) => Vector2::new(right, bottom),
+            _ => {
+                return Err(GraphicsError::RegionOutOfBounds {
+                    x,
+                    y,
+                    width: w,
+                    height: h,
+                })
+            }
+        };
+        frames.push(SheetFrame {
+            name: frame.filename,
+            region: Rect {
+                top_left: Vector2::new(x, y),
+                bottom_right,
+            },
+            duration: frame.duration.map(Duration::from_millis),
+        });
+    }
+
+    let mut tags = Vec::new();
+    for tag in sheet.meta.frame_tags {
+        if tag.from > tag.to || tag.to >= frames.len() {
+            return Err(GraphicsError::InvalidSpriteSheet(
+                "frame tag refers to missing frames",
+            ));
+        }
+        let (mode, reverse) = match tag.direction.as_deref() {
+            None | Some("forward") => (PlaybackMode::Loop, false),
+            Some("reverse") => (PlaybackMode::Loop, true),
+            Some("pingpong") => (PlaybackMode::PingPong, false),
+            Some("pingpong_reverse") => (PlaybackMode::PingPong, true),
+            Some(_) => {
+                return Err(GraphicsError::InvalidSpriteSheet(
+                    "unknown direction of frame tag",
+                ))
+            }
+        };
+        tags.push(FrameTag {
+            name: tag.name,
+            from: tag.from,
+            to: tag.to,
+            mode,
+            reverse,
+        });
+    }
+    Ok((frames, tags))
+}
+
+#[test]
+fn test_grid_regions() {
+    let regions = grid_regions(Vector2::new(36, 20), Vector2::new(16, 8), 1, 2);
+    let top_lefts: Vec<_> = regions
+        .iter()
+        .map(|r| (r.top_left.x, r.top_left.y))
+        .collect();
+    assert_eq!(top_lefts, vec![(1, 1), (19, 1), (1, 11), (19, 11)]);
+    assert_eq!(regions[3].bottom_right, Vector2::new(35, 19));
+
+    assert!(grid_regions(Vector2::new(8, 8), Vector2::new(16, 16), 0, 0).is_empty());
+    // Huge margins and spacings don't overflow
+    assert!(grid_regions(Vector2::new(8, 8), Vector2::new(4, 4), std::u32::MAX, 0).is_empty());
+    assert_eq!(
+        grid_regions(Vector2::new(8, 8), Vector2::new(4, 4), 0, std::u32::MAX).len(),
+        1
+    );
+}
+
+#[test]
+fn test_parse_json() {
+    // Frames as object, in the order of an Aseprite export
+    let json = r#"{
+        "frames": {
+            "walk 10": { "frame": { "x": 32, "y": 0, "w": 16, "h": 16 }, "duration": 50 },
+            "walk 2": { "frame": { "x": 16, "y": 0, "w": 16, "h": 16 }, "duration": 100 }
+        },
+        "meta": {
+            "frameTags": [ { "name": "walk", "from": 0, "to": 1, "direction": "pingpong" } ]
+        }
+    }"#;
+    let (frames, tags) = parse_json(json).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].name.as_deref(), Some("walk 10"));
+    assert_eq!(frames[0].duration, Some(Duration::from_millis(50)));
+    assert_eq!(frames[1].region.top_left, Vector2::new(16, 0));
+    assert_eq!(tags[0].mode, PlaybackMode::PingPong);
+
+    // Frames as array, as exported by TexturePacker
+    let json =
+        r#"{ "frames": [ { "filename": "a.png", "frame": { "x": 0, "y": 0, "w": 4, "h": 2 } } ] }"#;
+    let (frames, tags) = parse_json(json).unwrap();
+    assert_eq!(frames[0].region.bottom_right, Vector2::new(4, 2));
+    assert_eq!(frames[0].duration, None);
+    assert!(tags.is_empty());
+
+    let json =
+        r#"{ "frames": [], "meta": { "frameTags": [ { "name": "x", "from": 0, "to": 0 } ] } }"#;
+    assert!(matches!(
+        parse_json(json),
+        Err(GraphicsError::InvalidSpriteSheet(_))
+    ));
+    assert!(matches!(
+        parse_json("{ \"frames\": 1 }"),
+        Err(GraphicsError::InvalidSpriteSheetJson(_))
+    ));
+
+    // Frames reaching beyond the range of coordinates are rejected instead of wrapping around
+    let json = r#"{ "frames": [ { "frame": { "x": 4294967295, "y": 0, "w": 1, "h": 1 } } ] }"#;
+    assert!(matches!(
+        parse_json(json),
+        Err(GraphicsError::RegionOutOfBounds { .. })
+    ));
+}
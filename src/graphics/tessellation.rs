@@ -0,0 +1,657 @@
+//! Turning shapes into triangles that can be drawn with a `BatchRender` or a `Mesh`.
+//!
+//! All shapes are added to a `Geometry`, which collects indexed triangles:
+//!
+//! ```ignore
+//! let mut geometry = Geometry::new();
+//! geometry.fill_circle(Vector2::new(100.0, 100.0), 50.0, Color::RED);
+//! geometry.stroke_polyline(&points, &StrokeStyle::new(4.0), Color::WHITE);
+//! batch.draw_geometry(&geometry);
+//! ```
+
+use std::f32::consts::PI;
+
+use cgmath::{InnerSpace, Rad, Vector2};
+
+use super::{context::BackendError, primitives::BasicVertex2D, Color, Mesh, Rect};
+use crate::Context;
+
+/// The default maximum distance in pixels between a curve and the polygon approximating it.
+const DEFAULT_TOLERANCE: f32 = 0.25;
+
+/// The largest number of segments an arc is approximated with, however small the tolerance.
+const MAX_ARC_SEGMENTS: usize = 4096;
+
+/// Miter joins that stick out by more than this multiple of half the line width
+/// are beveled instead.
+const MITER_LIMIT: f32 = 4.0;
+
+/// How two segments of a stroked line are connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineJoin {
+    /// Extend the outer edges until they meet, which falls back to a bevel for sharp angles.
+    Miter,
+    /// Connect the outer corners with a straight edge.
+    Bevel,
+    /// Connect the outer corners with a circular arc.
+    Round,
+}
+
+/// How the ends of an open stroked line look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineCap {
+    /// End exactly at the end points.
+    Butt,
+    /// Extend beyond the end points by half the line width.
+    Square,
+    /// Add a half circle around the end points.
+    Round,
+}
+
+/// Determines how the outline of a shape is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StrokeStyle {
+    /// The width of the line, centered on the outline.
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+}
+
+impl StrokeStyle {
+    /// A line of the given width with miter joins and butt caps.
+    pub fn new(width: f32) -> Self {
+        Self {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+        }
+    }
+
+    pub fn with_join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        self
+    }
+
+    pub fn with_cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        self
+    }
+}
+
+/// A list of untextured triangles, given by vertices and indices into them.
+#[derive(Debug, Clone)]
+pub struct Geometry {
+    pub vertices: Vec<BasicVertex2D>,
+    /// Every three indices form a triangle.
+    pub indices: Vec<u32>,
+    /// Maximum distance between curves and the polygons approximating them.
+    tolerance: f32,
+}
+
+impl Default for Geometry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Geometry {
+    pub fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            tolerance: DEFAULT_TOLERANCE,
+        }
+    }
+
+    /// Set the maximum distance between curves and the polygons approximating them.
+    /// Smaller values produce smoother curves with more triangles.
+    /// The tolerance is kept positive, and arcs never have more than 4096 segments.
+    pub fn with_tolerance(mut self, tolerance: f32) -> Self {
+        // Also replaces NaN
+        self.tolerance = tolerance.max(std::f32::EPSILON);
+        self
+    }
+
+    /// Remove all triangles.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+    }
+
+    /// Upload the triangles into a new mesh.
+    pub fn build_mesh(&self, ctx: &mut Context) -> Result<Mesh, BackendError> {
+        Mesh::new(ctx, &self.vertices, &self.indices)
+    }
+
+    pub fn fill_circle(&mut self, center: Vector2<f32>, radius: f32, color: Color) {
+        self.fill_ellipse(center, Vector2::new(radius, radius), color)
+    }
+
+    pub fn stroke_circle(
+        &mut self,
+        center: Vector2<f32>,
+        radius: f32,
+        style: &StrokeStyle,
+        color: Color,
+    ) {
+        self.stroke_ellipse(center, Vector2::new(radius, radius), style, color)
+    }
+
+    pub fn fill_ellipse(&mut self, center: Vector2<f32>, radii: Vector2<f32>, color: Color) {
+        let mut points = self.arc_points(center, radii, 0.0, 2.0 * PI);
+        // The last point is the same as the first
+        points.pop();
+        self.fill_fan(center, &points, true, color);
+    }
+
+    pub fn stroke_ellipse(
+        &mut self,
+        center: Vector2<f32>,
+        radii: Vector2<f32>,
+        style: &StrokeStyle,
+        color: Color,
+    ) {
+        let mut points = self.arc_points(center, radii, 0.0, 2.0 * PI);
+        points.pop();
+        self.stroke_path(&points, true, style, color);
+    }
+
+    /// Fill the circular sector between the given angles, measured clockwise
+    /// from the positive X axis in pixel coordinates.
+    pub fn fill_arc<A: Into<Rad<f32>>>(
+        &mut self,
+        center: Vector2<f32>,
+        radius: f32,
+        start: A,
+        end: A,
+        color: Color,
+    ) {
+        let start = start.into().0;
+        let sweep = end.into().0 - start;
+        let points = self.arc_points(center, Vector2::new(radius, radius), start, sweep);
+        self.fill_fan(center, &points, false, color);
+    }
+
+    /// Stroke the circular arc between the given angles, measured clockwise
+    /// from the positive X axis in pixel coordinates.
+    pub fn stroke_arc<A: Into<Rad<f32>>>(
+        &mut self,
+        center: Vector2<f32>,
+        radius: f32,
+        start: A,
+        end: A,
+        style: &StrokeStyle,
+        color: Color,
+    ) {
+        let start = start.into().0;
+        let sweep = end.into().0 - start;
+        let points = self.arc_points(center, Vector2::new(radius, radius), start, sweep);
+        self.stroke_path(&points, false, style, color);
+    }
+
+    /// Fill a rectangle with corners rounded by the given radius,
+    /// which is limited to half the shorter side of the rectangle.
+    pub fn fill_rounded_rect(&mut self, rect: Rect<f32>, radius: f32, color: Color) {
+        let points = self.rounded_rect_points(rect, radius);
+        self.fill_fan(rect.center(), &points, true, color);
+    }
+
+    pub fn stroke_rounded_rect(
+        &mut self,
+        rect: Rect<f32>,
+        radius: f32,
+        style: &StrokeStyle,
+        color: Color,
+    ) {
+        let points = self.rounded_rect_points(rect, radius);
+        self.stroke_path(&points, true, style, color);
+    }
+
+    /// Fill a simple polygon, which may be concave, with the corners given in clockwise
+    /// or counter-clockwise order. Self-intersecting polygons are not filled correctly.
+    pub fn fill_polygon(&mut self, corners: &[Vector2<f32>], color: Color) {
+        let base = self.vertices.len() as u32;
+        for corner in corners {
+            self.push_vertex(*corner, color);
+        }
+        for triangle in triangulate(corners) {
+            self.indices
+                .extend(triangle.iter().map(|i| base + *i as u32));
+        }
+    }
+
+    /// Stroke the outline of a polygon, connecting the last corner with the first.
+    pub fn stroke_polygon(&mut self, corners: &[Vector2<f32>], style: &StrokeStyle, color: Color) {
+        self.stroke_path(corners, true, style, color);
+    }
+
+    /// Stroke a line through the given points.
+    pub fn stroke_polyline(&mut self, points: &[Vector2<f32>], style: &StrokeStyle, color: Color) {
+        self.stroke_path(points, false, style, color);
+    }
+
+    fn push_vertex(&mut self, position: Vector2<f32>, color: Color) -> u32 {
+        let index = self.vertices.len() as u32;
+        self.vertices
+            .push(BasicVertex2D::with_position_color(position, color));
+        index
+    }
+
+    fn push_triangle(&mut self, a: Vector2<f32>, b: Vector2<f32>, c: Vector2<f32>, color: Color) {
+        let a = self.push_vertex(a, color);
+        let b = self.push_vertex(b, color);
+        let c = self.push_vertex(c, color);
+        self.indices.extend_from_slice(&[a, b, c]);
+    }
+
+    /// Connect the center with each pair of consecutive points.
+    fn fill_fan(
+        &mut self,
+        center: Vector2<f32>,
+        points: &[Vector2<f32>],
+        closed: bool,
+        color: Color,
+    ) {
+        if points.len() < 2 {
+            return;
+        }
+        let center = self.push_vertex(center, color);
+        let first = self.vertices.len() as u32;
+        for point in points {
+            self.push_vertex(*point, color);
+        }
+        let count = points.len() as u32;
+        let segments = if closed { count } else { count - 1 };
+        for i in 0..segments {
+            self.indices
+                .extend_from_slice(&[center, first + i, first + (i + 1) % count]);
+        }
+    }
+
+    /// Add a circular fan around `center`, starting at `center + offset` and rotating by `sweep`.
+    fn fill_round(&mut self, center: Vector2<f32>, offset: Vector2<f32>, sweep: f32, color: Color) {
+        let segments = self.segments(offset.magnitude(), sweep);
+        let points: Vec<_> = (0..=segments)
+            .map(|i| center + rotate(offset, sweep * i as f32 / segments as f32))
+            .collect();
+        self.fill_fan(center, &points, false, color);
+    }
+
+    /// The number of segments needed for approximating an arc within the tolerance.
+    fn segments(&self, radius: f32, sweep: f32) -> usize {
+        let sweep = sweep.abs();
+        if radius <= self.tolerance {
+            return 1.max((sweep / (PI / 2.0)).ceil() as usize);
+        }
+        // The distance between the arc and the chord of an angle `step` is `r * (1 - cos(step / 2))`
+        let step = 2.0 * (1.0 - self.tolerance / radius).acos();
+        // The step rounds to zero for tolerances that are tiny compared to the radius
+        if step.is_nan() || step <= 0.0 {
+            return MAX_ARC_SEGMENTS;
+        }
+        ((sweep / step).ceil() as usize)
+            .max(1)
+            .min(MAX_ARC_SEGMENTS)
+    }
+
+    /// Points on an elliptic arc, including both end points.
+    fn arc_points(
+        &self,
+        center: Vector2<f32>,
+        radii: Vector2<f32>,
+        start: f32,
+        sweep: f32,
+    ) -> Vec<Vector2<f32>> {
+        let segments = self.segments(radii.x.max(radii.y), sweep);
+        (0..=segments)
+            .map(|i| {
+                let angle = start + sweep * i as f32 / segments as f32;
+                center + Vector2::new(angle.cos() * radii.x, angle.sin() * radii.y)
+            })
+            .collect()
+    }
+
+    fn rounded_rect_points(&self, rect: Rect<f32>, radius: f32) -> Vec<Vector2<f32>> {
+        let size = rect.size();
+        let radius = radius.max(0.0).min(size.x.min(size.y) / 2.0);
+        let (min, max) = (rect.top_left, rect.bottom_right);
+        let corners = [
+            (Vector2::new(max.x - radius, min.y + radius), -PI / 2.0),
+            (Vector2::new(max.x - radius, max.y - radius), 0.0),
+            (Vector2::new(min.x + radius, max.y - radius), PI / 2.0),
+            (Vector2::new(min.x + radius, min.y + radius), PI),
+        ];
+        let mut points = Vec::new();
+        for (center, start) in corners.iter() {
+            if radius > 0.0 {
+                let radii = Vector2::new(radius, radius);
+                points.extend(self.arc_points(*center, radii, *start, PI / 2.0));
+            } else {
+                points.push(*center);
+            }
+        }
+        points
+    }
+
+    fn stroke_path(
+        &mut self,
+        points: &[Vector2<f32>],
+        closed: bool,
+        style: &StrokeStyle,
+        color: Color,
+    ) {
+        let mut points: Vec<Vector2<f32>> = points.to_vec();
+        points.dedup();
+        if closed && points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() < 2 || style.width <= 0.0 {
+            return;
+        }
+        let half_width = style.width / 2.0;
+        let count = points.len();
+        let num_segments = if closed { count } else { count - 1 };
+        let directions: Vec<Vector2<f32>> = (0..num_segments)
+            .map(|i| (points[(i + 1) % count] - points[i]).normalize())
+            .collect();
+
+        // The left and right corners where the segment ending at a point ends,
+        // and where the segment starting at a point starts.
+        let mut ends = vec![(Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0)); count];
+        let mut starts = ends.clone();
+        for i in 0..count {
+            let point = points[i];
+            let incoming = if i > 0 || closed {
+                Some(directions[(i + num_segments - 1) % num_segments])
+            } else {
+                None
+            };
+            let outgoing = directions.get(i).copied();
+            match (incoming, outgoing) {
+                (Some(incoming), Some(outgoing)) => {
+                    let (end, start) =
+                        self.join(point, incoming, outgoing, half_width, style.join, color);
+                    ends[i] = end;
+                    starts[i] = start;
+                }
+                (None, Some(direction)) => {
+                    starts[i] = self.cap(point, -direction, half_width, style.cap, color);
+                    // The cap is built looking backwards, so left and right are swapped
+                    starts[i] = (starts[i].1, starts[i].0);
+                }
+                (Some(direction), None) => {
+                    ends[i] = self.cap(point, direction, half_width, style.cap, color);
+                }
+                (None, None) => unreachable!("paths have at least two points"),
+            }
+        }
+
+        for i in 0..num_segments {
+            let (start_left, start_right) = starts[i];
+            let (end_left, end_right) = ends[(i + 1) % count];
+            let a = self.push_vertex(start_left, color);
+            let b = self.push_vertex(start_right, color);
+            let c = self.push_vertex(end_right, color);
+            let d = self.push_vertex(end_left, color);
+            self.indices.extend_from_slice(&[a, b, c, a, c, d]);
+        }
+    }
+
+    /// Add the geometry joining two segments at a point, and return the left and right corners
+    /// of the end of the incoming segment, and of the start of the outgoing segment.
+    fn join(
+        &mut self,
+        point: Vector2<f32>,
+        incoming: Vector2<f32>,
+        outgoing: Vector2<f32>,
+        half_width: f32,
+        join: LineJoin,
+        color: Color,
+    ) -> (Corners, Corners) {
+        let normal_in = left_normal(incoming);
+        let normal_out = left_normal(outgoing);
+        let turn = cross(incoming, outgoing);
+        let sum = normal_in + normal_out;
+
+        if sum.magnitude2() < 1e-6 {
+            // The line reverses its direction
+            let end = (
+                point + normal_in * half_width,
+                point - normal_in * half_width,
+            );
+            let start = (
+                point + normal_out * half_width,
+                point - normal_out * half_width,
+            );
+            if join == LineJoin::Round {
+                self.fill_round(point, normal_in * half_width, PI, color);
+            }
+            return (end, start);
+        }
+
+        let miter = sum.normalize();
+        // How much longer the miter is than half the width
+        let miter_scale = 1.0 / miter.dot(normal_in);
+        if turn.abs() < 1e-6 || (join == LineJoin::Miter && miter_scale <= MITER_LIMIT) {
+            let offset = miter * half_width * miter_scale;
+            let corners = (point + offset, point - offset);
+            return (corners, corners);
+        }
+
+        // Turning clockwise in pixel coordinates puts the outer corner on the left side
+        let side = if turn > 0.0 { 1.0 } else { -1.0 };
+        let inner = point - miter * side * half_width * miter_scale.min(MITER_LIMIT);
+        let outer_in = point + normal_in * side * half_width;
+        let outer_out = point + normal_out * side * half_width;
+        match join {
+            LineJoin::Round => {
+                let sweep = turn.atan2(normal_in.dot(normal_out));
+                self.fill_round(point, outer_in - point, sweep, color);
+                self.push_triangle(inner, outer_in, point, color);
+                self.push_triangle(inner, point, outer_out, color);
+            }
+            LineJoin::Miter | LineJoin::Bevel => {
+                self.push_triangle(inner, outer_in, outer_out, color);
+            }
+        }
+        if side > 0.0 {
+            ((outer_in, inner), (outer_out, inner))
+        } else {
+            ((inner, outer_in), (inner, outer_out))
+        }
+    }
+
+    /// Add the geometry of a cap at the end of a line going into `direction`,
+    /// and return the left and right corners of the end of the line.
+    fn cap(
+        &mut self,
+        point: Vector2<f32>,
+        direction: Vector2<f32>,
+        half_width: f32,
+        cap: LineCap,
+        color: Color,
+    ) -> Corners {
+        let normal = left_normal(direction) * half_width;
+        match cap {
+            LineCap::Butt => (point + normal, point - normal),
+            LineCap::Square => {
+                let end = point + direction * half_width;
+                (end + normal, end - normal)
+            }
+            LineCap::Round => {
+                // Rotate from the left corner through the direction to the right corner
+                self.fill_round(point, normal, PI, color);
+                (point + normal, point - normal)
+            }
+        }
+    }
+}
+
+/// The left and right corners of the end of a line segment.
+type Corners = (Vector2<f32>, Vector2<f32>);
+
+/// The normal pointing to the left of the direction, i.e. rotated by 90 degrees
+/// counter-clockwise in pixel coordinates where Y points down.
+fn left_normal(direction: Vector2<f32>) -> Vector2<f32> {
+    Vector2::new(direction.y, -direction.x)
+}
+
+fn cross(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn rotate(v: Vector2<f32>, angle: f32) -> Vector2<f32> {
+    let (sin, cos) = angle.sin_cos();
+    Vector2::new(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+}
+
+/// Split a simple polygon into triangles by repeatedly clipping ears,
+/// returning the indices of the corners of each triangle.
+pub fn triangulate(corners: &[Vector2<f32>]) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..corners.len()).collect();
+    let mut triangles = Vec::new();
+    if remaining.len() < 3 {
+        return triangles;
+    }
+    // Twice the signed area determines whether the corners are in clockwise order
+    let area: f32 = (0..corners.len())
+        .map(|i| cross(corners[i], corners[(i + 1) % corners.len()]))
+        .sum();
+    let orientation = if area < 0.0 { -1.0 } else { 1.0 };
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (
+                corners[remaining[(i + n - 1) % n]],
+                corners[remaining[i]],
+                corners[remaining[(i + 1) % n]],
+            );
+            if cross(b - a, c - b) * orientation <= 0.0 {
+                // A reflex or degenerate corner
+                return false;
+            }
+            remaining.iter().all(|&other| {
+                let p = corners[other];
+                p == a || p == b || p == c || !in_triangle(p, a, b, c)
+            })
+        });
+        match ear {
+            Some(i) => {
+                triangles.push([
+                    remaining[(i + n - 1) % n],
+                    remaining[i],
+                    remaining[(i + 1) % n],
+                ]);
+                remaining.remove(i);
+            }
+            None => {
+                // Only happens for self-intersecting or degenerate polygons
+                break;
+            }
+        }
+    }
+    for i in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    triangles
+}
+
+/// Whether `p` lies inside or on the boundary of the triangle `abc`.
+fn in_triangle(p: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>, c: Vector2<f32>) -> bool {
+    let d1 = cross(b - a, p - a);
+    let d2 = cross(c - b, p - b);
+    let d3 = cross(a - c, p - c);
+    let has_negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(has_negative && has_positive)
+}
+
+/// The sum of the areas of all triangles of the geometry.
+#[cfg(test)]
+fn total_area(geometry: &Geometry) -> f32 {
+    let corner = |i: u32| Vector2::from(geometry.vertices[i as usize].position);
+    geometry
+        .indices
+        .chunks(3)
+        .map(|t| cross(corner(t[1]) - corner(t[0]), corner(t[2]) - corner(t[0])).abs() / 2.0)
+        .sum()
+}
+
+#[test]
+fn test_triangulate_concave() {
+    // An L-shape with an area of 3
+    let corners = [
+        Vector2::new(0.0, 0.0),
+        Vector2::new(2.0, 0.0),
+        Vector2::new(2.0, 1.0),
+        Vector2::new(1.0, 1.0),
+        Vector2::new(1.0, 2.0),
+        Vector2::new(0.0, 2.0),
+    ];
+    let mut geometry = Geometry::new();
+    geometry.fill_polygon(&corners, Color::WHITE);
+    assert_eq!(geometry.indices.len(), 3 * 4);
+    let area = total_area(&geometry);
+    assert!((area - 3.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_stroke_polyline_butt() {
+    let mut geometry = Geometry::new();
+    let points = [Vector2::new(0.0, 0.0), Vector2::new(10.0, 0.0)];
+    geometry.stroke_polyline(&points, &StrokeStyle::new(2.0), Color::WHITE);
+    assert_eq!(geometry.indices.len(), 6);
+    for vertex in &geometry.vertices {
+        assert!(vertex.position[0] == 0.0 || vertex.position[0] == 10.0);
+        assert!(vertex.position[1].abs() == 1.0);
+    }
+}
+
+#[test]
+fn test_stroke_polyline_joins() {
+    // A right angle stroked 2 wide. With a miter join, the outline is an L-shape of
+    // 2 * (10 * 2) = 40, since the 1x1 square where the legs overlap on the inside is
+    // made up for by the 1x1 square of the outer corner. A bevel cuts that corner square
+    // in half along its diagonal, leaving 40 - 1 * 1 / 2 = 39.5.
+    let points = [
+        Vector2::new(0.0, 0.0),
+        Vector2::new(10.0, 0.0),
+        Vector2::new(10.0, 10.0),
+    ];
+    let area = |join| {
+        let mut geometry = Geometry::new();
+        geometry.stroke_polyline(
+            &points,
+            &StrokeStyle::new(2.0).with_join(join),
+            Color::WHITE,
+        );
+        total_area(&geometry)
+    };
+    assert!((area(LineJoin::Miter) - 40.0).abs() < 1e-4);
+    assert!((area(LineJoin::Bevel) - 39.5).abs() < 1e-4);
+    let round = area(LineJoin::Round);
+    assert!(round > 39.5 && round < 40.0);
+}
+
+#[test]
+fn test_circle_tolerance() {
+    let mut coarse = Geometry::new().with_tolerance(1.0);
+    coarse.fill_circle(Vector2::new(0.0, 0.0), 100.0, Color::WHITE);
+    let mut fine = Geometry::new().with_tolerance(0.1);
+    fine.fill_circle(Vector2::new(0.0, 0.0), 100.0, Color::WHITE);
+    assert!(coarse.indices.len() < fine.indices.len());
+    // All points of the polygon lie on the circle
+    for vertex in &fine.vertices[1..] {
+        let distance = Vector2::from(vertex.position).magnitude();
+        assert!((distance - 100.0).abs() < 1e-3);
+    }
+
+    // Degenerate tolerances fall back to the finest approximation instead of
+    // trying to allocate an unbounded number of points
+    for &tolerance in &[0.0, -1.0, std::f32::NAN] {
+        let mut geometry = Geometry::new().with_tolerance(tolerance);
+        geometry.fill_circle(Vector2::new(0.0, 0.0), 100.0, Color::WHITE);
+        assert_eq!(geometry.indices.len(), 3 * MAX_ARC_SEGMENTS);
+    }
+}
@@ -0,0 +1,183 @@
+//! Helpers for writing regression tests against reference images,
+//! e.g. of frames rendered by a headless context and captured with `graphics::screenshot`.
+
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+
+/// When this environment variable is set, `assert_matches_reference` overwrites
+/// the reference images with the actual images instead of comparing them.
+pub const UPDATE_REFERENCE_VAR: &str = "DS2D_UPDATE_REFERENCE";
+
+/// Describes how an image differs from the expected image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageMismatch {
+    /// The images don't have the same dimensions.
+    Size {
+        actual: (u32, u32),
+        expected: (u32, u32),
+    },
+    /// Some pixels differ by more than the allowed tolerance.
+    Pixels {
+        /// Number of pixels where at least one channel exceeded the tolerance.
+        count: usize,
+        /// The first mismatching pixel in row-major order.
+        first: (u32, u32),
+        /// The largest difference observed in any channel.
+        max_difference: u8,
+    },
+}
+
+impl std::fmt::Display for ImageMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageMismatch::Size { actual, expected } => write!(
+                f,
+                "image is {}x{}, but expected {}x{}",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+            ImageMismatch::Pixels {
+                count,
+                first,
+                max_difference,
+            } => write!(
+                f,
+                "{} pixels differ (first at {:?}, maximum channel difference {})",
+                count, first, max_difference
+            ),
+        }
+    }
+}
+
+/// Compare two images, allowing each channel of each pixel to differ by at most `tolerance`.
+pub fn compare_images(
+    actual: &RgbaImage,
+    expected: &RgbaImage,
+    tolerance: u8,
+) -> Result<(), ImageMismatch> {
+    if actual.dimensions() != expected.dimensions() {
+        return Err(ImageMismatch::Size {
+            actual: actual.dimensions(),
+            expected: expected.dimensions(),
+        });
+    }
+    let mut count = 0;
+    let mut first = None;
+    let mut max_difference = 0;
+    for ((x, y, a), b) in actual.enumerate_pixels().zip(expected.pixels()) {
+        let difference =
+            a.0.iter()
+                .zip(b.0.iter())
+                .map(|(ca, cb)| ca.max(cb) - ca.min(cb))
+                .max()
+                .unwrap_or(0);
+        if difference > tolerance {
+            count += 1;
+            first = first.or(Some((x, y)));
+            max_difference = max_difference.max(difference);
+        }
+    }
+    match first {
+        None => Ok(()),
+        Some(first) => Err(ImageMismatch::Pixels {
+            count,
+            first,
+            max_difference,
+        }),
+    }
+}
+
+/// Assert that an image matches the reference PNG at the given path, within a per-channel tolerance.
+///
+/// On a mismatch, the actual image is written next to the reference with an `.actual.png`
+/// extension for inspection. If the `DS2D_UPDATE_REFERENCE` environment variable is set,
+/// the reference is (re)created from the actual image instead.
+///
+/// # Panics
+///
+/// Panics if the images don't match, if the reference does not exist (unless it is being
+/// updated), or if the images cannot be read or written.
+pub fn assert_matches_reference<P: AsRef<Path>>(actual: &RgbaImage, reference: P, tolerance: u8) {
+    let reference = reference.as_ref();
+    if std::env::var_os(UPDATE_REFERENCE_VAR).is_some() {
+        log::info!("Writing reference image {}", reference.display());
+        actual
+            .save(reference)
+            .unwrap_or_else(|err| panic!("Could not write {}: {}", reference.display(), err));
+        return;
+    }
+    if !reference.exists() {
+        let actual_path = actual_path(reference);
+        if let Err(err) = actual.save(&actual_path) {
+            log::error!("Could not write {}: {}", actual_path.display(), err);
+        }
+        panic!(
+            "Reference image {} does not exist, set {} to create it (actual image written to {})",
+            reference.display(),
+            UPDATE_REFERENCE_VAR,
+            actual_path.display()
+        );
+    }
+
+    let expected = image::open(reference)
+        .unwrap_or_else(|err| panic!("Could not read {}: {}", reference.display(), err))
+        .into_rgba();
+    if let Err(mismatch) = compare_images(actual, &expected, tolerance) {
+        let actual_path = actual_path(reference);
+        if let Err(err) = actual.save(&actual_path) {
+            log::error!("Could not write {}: {}", actual_path.display(), err);
+        }
+        panic!(
+            "Image does not match reference {}: {} (actual image written to {})",
+            reference.display(),
+            mismatch,
+            actual_path.display()
+        );
+    }
+}
+
+fn actual_path(reference: &Path) -> PathBuf {
+    let stem = reference
+        .file_stem()
+        .map_or_else(|| "reference".into(), |stem| stem.to_string_lossy());
+    reference.with_file_name(format!("{}.actual.png", stem))
+}
+
+#[test]
+fn test_compare_images_tolerance() {
+    let expected = RgbaImage::from_pixel(4, 3, image::Rgba([100, 100, 100, 255]));
+    let mut actual = expected.clone();
+    actual.put_pixel(2, 1, image::Rgba([103, 98, 100, 255]));
+    assert_eq!(compare_images(&actual, &expected, 3), Ok(()));
+    assert_eq!(
+        compare_images(&actual, &expected, 2),
+        Err(ImageMismatch::Pixels {
+            count: 1,
+            first: (2, 1),
+            max_difference: 3,
+        })
+    );
+}
+
+#[test]
+fn test_compare_images_size() {
+    let expected = RgbaImage::new(4, 3);
+    let actual = RgbaImage::new(3, 4);
+    assert_eq!(
+        compare_images(&actual, &expected, 255),
+        Err(ImageMismatch::Size {
+            actual: (3, 4),
+            expected: (4, 3),
+        })
+    );
+}
+
+#[test]
+#[should_panic(expected = "does not exist")]
+fn test_missing_reference_fails() {
+    let dir = std::env::temp_dir().join("ds2d-test-missing-reference");
+    std::fs::create_dir_all(&dir).unwrap();
+    let reference = dir.join("missing.png");
+    let _ = std::fs::remove_file(&reference);
+    assert_matches_reference(&RgbaImage::new(2, 2), &reference, 0);
+}
@@ -0,0 +1,655 @@
+//! Loading prebuilt bitmap fonts in the AngelCode BMFont format.
+
+use std::{collections::HashMap, path::Path};
+
+use cgmath::Vector2;
+
+use crate::{
+    graphics::{GraphicsError, Rect, Texture2D, TextureView2D},
+    GameResult,
+};
+
+/// The contents of a BMFont descriptor (`.fnt`) in its text, XML or binary format.
+///
+/// Only the information needed for laying out text is kept. Glyphs are expected
+/// to be stored in all channels of the page images, i.e. not packed into separate channels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitmapFontDescription {
+    /// The name of the font the bitmap font was generated from.
+    pub face: String,
+    /// The size in pixels the font was generated at.
+    pub size: u32,
+    /// The distance in pixels between the tops of two lines.
+    pub line_height: u32,
+    /// The distance in pixels from the top of a line to the baseline.
+    pub base: u32,
+    /// The file names of the page images, relative to the descriptor.
+    pub pages: Vec<String>,
+    pub chars: Vec<BitmapChar>,
+    pub kernings: Vec<BitmapKerning>,
+}
+
+/// Where the image of a character is placed on a page, and how it is positioned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitmapChar {
+    /// The unicode code point of the character,
+    /// or `u32::MAX` for the glyph used in place of missing characters.
+    pub id: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Offset of the image from the current position at the top of the line.
+    pub x_offset: i32,
+    pub y_offset: i32,
+    /// How far the current position moves after drawing the character.
+    pub x_advance: i32,
+    pub page: u32,
+}
+
+/// An adjustment of the distance between two characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitmapKerning {
+    pub first: u32,
+    pub second: u32,
+    pub amount: i32,
+}
+
+/// A tag of the text or XML format, with its name and attributes.
+type Tag = (String, Vec<(String, String)>);
+
+impl BitmapFontDescription {
+    /// Parse a descriptor, detecting its format from the contents.
+    pub fn parse(data: &[u8]) -> Result<Self, GraphicsError> {
+        if data.starts_with(b"BMF") {
+            return parse_binary(data);
+        }
+        let text = std::str::from_utf8(data).map_err(|_| invalid("descriptor is not UTF-8"))?;
+        let text = text.trim_start_matches('\u{feff}').trim_start();
+        if text.starts_with('<') {
+            Self::from_tags(xml_tags(text)?)
+        } else {
+            Self::from_tags(text_tags(text))
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(filename: P) -> GameResult<Self> {
+        let data = std::fs::read(filename)?;
+        Ok(Self::parse(&data)?)
+    }
+
+    fn from_tags(tags: Vec<Tag>) -> Result<Self, GraphicsError> {
+        let mut description = empty_description();
+        let mut has_common = false;
+        for (name, attributes) in &tags {
+            let attributes = attributes.as_slice();
+            match name.as_str() {
+                "info" => {
+                    description.face = attribute(attributes, "face").unwrap_or("").to_owned();
+                    description.size = number::<i32>(attributes, "size")?.wrapping_abs() as u32;
+                }
+                "common" => {
+                    if attribute(attributes, "lineHeight").is_none() {
+                        return Err(invalid("common tag without line height"));
+                    }
+                    description.line_height = number(attributes, "lineHeight")?;
+                    description.base = number(attributes, "base")?;
+                    let pages: u16 = number(attributes, "pages")?;
+                    description.pages.resize(pages as usize, String::new());
+                    has_common = true;
+                }
+                "page" => {
+                    if attribute(attributes, "id").is_none() {
+                        return Err(invalid("page tag without id"));
+                    }
+                    let id = number::<u16>(attributes, "id")? as usize;
+                    if id >= description.pages.len() {
+                        description.pages.resize(id + 1, String::new());
+                    }
+                    description.pages[id] = attribute(attributes, "file").unwrap_or("").to_owned();
+                }
+                "char" => {
+                    if attribute(attributes, "id").is_none() {
+                        return Err(invalid("char tag without id"));
+                    }
+                    description.chars.push(BitmapChar {
+                        // The glyph for missing characters has the id -1
+                        id: number::<i64>(attributes, "id")? as u32,
+                        x: number(attributes, "x")?,
+                        y: number(attributes, "y")?,
+                        width: number(attributes, "width")?,
+                        height: number(attributes, "height")?,
+                        x_offset: number(attributes, "xoffset")?,
+                        y_offset: number(attributes, "yoffset")?,
+                        x_advance: number(attributes, "xadvance")?,
+                        page: number(attributes, "page")?,
+                    });
+                }
+                "kerning" => description.kernings.push(BitmapKerning {
+                    first: number(attributes, "first")?,
+                    second: number(attributes, "second")?,
+                    amount: number(attributes, "amount")?,
+                }),
+                _ => {}
+            }
+        }
+        if !has_common {
+            return Err(invalid("missing common tag"));
+        }
+        description.validate()
+    }
+
+    fn validate(self) -> Result<Self, GraphicsError> {
+        if self.pages.iter().any(|page| page.is_empty()) {
+            return Err(invalid("missing page file"));
+        }
+        if self
+            .chars
+            .iter()
+            .any(|ch| ch.page as usize >= self.pages.len())
+        {
+            return Err(invalid("character on a missing page"));
+        }
+        Ok(self)
+    }
+}
+
+fn empty_description() -> BitmapFontDescription {
+    BitmapFontDescription {
+        face: String::new(),
+        size: 0,
+        line_height: 0,
+        base: 0,
+        pages: Vec::new(),
+        chars: Vec::new(),
+        kernings: Vec::new(),
+    }
+}
+
+fn invalid(message: &'static str) -> GraphicsError {
+    GraphicsError::InvalidBitmapFont(message)
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Parse a numeric attribute, which is zero if it is missing.
+fn number<T: std::str::FromStr + Default>(
+    attributes: &[(String, String)],
+    key: &str,
+) -> Result<T, GraphicsError> {
+    match attribute(attributes, key) {
+        Some(value) => value.trim().parse().map_err(|_| invalid("invalid number")),
+        None => Ok(T::default()),
+    }
+}
+
+/// Split the text format into tags, one per line.
+fn text_tags(text: &str) -> Vec<Tag> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let end = line.find(char::is_whitespace).unwrap_or(line.len());
+            (
+                line[..end].to_owned(),
+                parse_attributes(&line[end..], false),
+            )
+        })
+        .collect()
+}
+
+/// Extract the tags from the XML format, ignoring its nesting.
+fn xml_tags(text: &str) -> Result<Vec<Tag>, GraphicsError> {
+    let mut tags = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if rest.starts_with("!--") {
+            let end = rest
+                .find("-->")
+                .ok_or_else(|| invalid("unterminated comment"))?;
+            rest = &rest[end + 3..];
+            continue;
+        }
+        let end = rest.find('>').ok_or_else(|| invalid("unterminated tag"))?;
+        let content = &rest[..end];
+        rest = &rest[end + 1..];
+        // Declarations and closing tags carry no information
+        if content.starts_with('?') || content.starts_with('!') || content.starts_with('/') {
+            continue;
+        }
+        let content = content.trim_end_matches('/').trim();
+        let name_end = content.find(char::is_whitespace).unwrap_or(content.len());
+        tags.push((
+            content[..name_end].to_owned(),
+            parse_attributes(&content[name_end..], true),
+        ));
+    }
+    Ok(tags)
+}
+
+/// Parse `key=value` pairs separated by whitespace, where values may be quoted.
+fn parse_attributes(text: &str, unescape: bool) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = rest[key_end..].trim_start();
+        let mut value = "";
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            match after.chars().next() {
+                Some(quote) if quote == '"' || quote == '\'' => {
+                    let quoted = &after[1..];
+                    let end = quoted.find(quote).unwrap_or(quoted.len());
+                    value = &quoted[..end];
+                    rest = quoted.get(end + 1..).unwrap_or("");
+                }
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    value = &after[..end];
+                    rest = &after[end..];
+                }
+            }
+        }
+        let value = if unescape {
+            unescape_xml(value)
+        } else {
+            value.to_owned()
+        };
+        attributes.push((key.to_owned(), value));
+        rest = rest.trim_start();
+    }
+    attributes
+}
+
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Parse version 3 of the binary format, which consists of blocks of little endian values.
+fn parse_binary(data: &[u8]) -> Result<BitmapFontDescription, GraphicsError> {
+    if data.get(3) != Some(&3) {
+        return Err(invalid("unsupported binary version"));
+    }
+    let u16_at =
+        |block: &[u8], offset: usize| u16::from_le_bytes([block[offset], block[offset + 1]]);
+    let i16_at =
+        |block: &[u8], offset: usize| i16::from_le_bytes([block[offset], block[offset + 1]]);
+    let u32_at = |block: &[u8], offset: usize| {
+        u32::from_le_bytes([
+            block[offset],
+            block[offset + 1],
+            block[offset + 2],
+            block[offset + 3],
+        ])
+    };
+
+    let mut description = empty_description();
+    let mut has_common = false;
+    let mut rest = &data[4..];
+    while !rest.is_empty() {
+        if rest.len() < 5 {
+            return Err(invalid("truncated block"));
+        }
+        let kind = rest[0];
+        let size = u32_at(rest, 1) as usize;
+        let block = rest
+            .get(5..5 + size)
+            .ok_or_else(|| invalid("truncated block"))?;
+        rest = &rest[5 + size..];
+        match kind {
+            1 => {
+                if block.len() < 14 {
+                    return Err(invalid("truncated info block"));
+                }
+                description.size = (i16_at(block, 0) as i32).wrapping_abs() as u32;
+                let name = block[14..].split(|&byte| byte == 0).next().unwrap_or(&[]);
+                description.face = String::from_utf8_lossy(name).into_owned();
+            }
+            2 => {
+                if block.len() < 15 {
+                    return Err(invalid("truncated common block"));
+                }
+                description.line_height = u16_at(block, 0) as u32;
+                description.base = u16_at(block, 2) as u32;
+                has_common = true;
+            }
+            3 => {
+                description.pages = block
+                    .split(|&byte| byte == 0)
+                    .filter(|name| !name.is_empty())
+                    .map(|name| String::from_utf8_lossy(name).into_owned())
+                    .collect();
+            }
+            4 => {
+                for ch in block.chunks_exact(20) {
+                    description.chars.push(BitmapChar {
+                        id: u32_at(ch, 0),
+                        x: u16_at(ch, 4) as u32,
+                        y: u16_at(ch, 6) as u32,
+                        width: u16_at(ch, 8) as u32,
+                        height: u16_at(ch, 10) as u32,
+                        x_offset: i16_at(ch, 12) as i32,
+                        y_offset: i16_at(ch, 14) as i32,
+                        x_advance: i16_at(ch, 16) as i32,
+                        page: ch[18] as u32,
+                    });
+                }
+            }
+            5 => {
+                for pair in block.chunks_exact(10) {
+                    description.kernings.push(BitmapKerning {
+                        first: u32_at(pair, 0),
+                        second: u32_at(pair, 4),
+                        amount: i16_at(pair, 8) as i32,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    if !has_common {
+        return Err(invalid("missing common block"));
+    }
+    description.validate()
+}
+
+/// A bitmap font whose pages have been loaded, laid out like an outline font.
+#[derive(Debug)]
+pub(super) struct BitmapFont {
+    /// The size the font was generated at, which is drawn unscaled
+    size: f32,
+    line_height: f32,
+    base: f32,
+    /// Glyphs are addressed by their index, where the first one is empty
+    glyphs: Vec<BitmapGlyph>,
+    indices: HashMap<char, u16>,
+    /// The glyph drawn for characters that are not part of the font
+    fallback: u16,
+    kerning: HashMap<(u16, u16), f32>,
+}
+
+#[derive(Debug, Clone)]
+struct BitmapGlyph {
+    /// `None` for glyphs without an image, like spaces
+    view: Option<TextureView2D>,
+    /// Position of the top-left corner relative to the origin on the baseline
+    offset: Vector2<f32>,
+    size: Vector2<f32>,
+    advance: f32,
+}
+
+impl BitmapFont {
+    pub(super) fn new(
+        description: &BitmapFontDescription,
+        pages: &[Texture2D],
+    ) -> Result<Self, GraphicsError> {
+        let size = match description.size {
+            0 => description.line_height,
+            size => size,
+        };
+        let mut glyphs = vec![BitmapGlyph {
+            view: None,
+            offset: Vector2::new(0.0, 0.0),
+            size: Vector2::new(0.0, 0.0),
+            advance: 0.0,
+        }];
+        let mut indices = HashMap::new();
+        let mut fallback = None;
+        for ch in &description.chars {
+            let view = if ch.width > 0 && ch.height > 0 {
+                let page = pages
+                    .get(ch.page as usize)
+                    .ok_or_else(|| invalid("character on a missing page"))?;
+                let bottom_right = match (ch.x.checked_add(ch.width), ch.y.checked_add(ch.height)) {
+                    (Some(right), Some(bottom))
+                        if right <= page.width() && bottom <= page.height() =>
+                    {
+                        Vector2::new(right, bottom)
+                    }
+                    _ => return Err(invalid("character outside of its page")),
+                };
+                let region = Rect {
+                    top_left: Vector2::new(ch.x, ch.y),
+                    bottom_right,
+                };
+                Some(TextureView2D::from_pixels(page.clone(), region))
+            } else {
+                None
+            };
+            if glyphs.len() > std::u16::MAX as usize {
+                return Err(invalid("too many characters"));
+            }
+            let index = glyphs.len() as u16;
+            glyphs.push(BitmapGlyph {
+                view,
+                offset: Vector2::new(
+                    ch.x_offset as f32,
+                    ch.y_offset as f32 - description.base as f32,
+                ),
+                size: Vector2::new(ch.width as f32, ch.height as f32),
+                advance: ch.x_advance as f32,
+            });
+            if ch.id == std::u32::MAX {
+                fallback = Some(index);
+            } else if let Some(c) = std::char::from_u32(ch.id) {
+                indices.insert(c, index);
+            }
+        }
+        let fallback = fallback.or_else(|| indices.get(&'?').copied()).unwrap_or(0);
+
+        let index_of = |id: u32| std::char::from_u32(id).and_then(|c| indices.get(&c).copied());
+        let kerning = description
+            .kernings
+            .iter()
+            .filter_map(|pair| {
+                Some((
+                    (index_of(pair.first)?, index_of(pair.second)?),
+                    pair.amount as f32,
+                ))
+            })
+            .collect();
+
+        Ok(Self {
+            size: size.max(1) as f32,
+            line_height: description.line_height as f32,
+            base: description.base as f32,
+            glyphs,
+            indices,
+            fallback,
+            kerning,
+        })
+    }
+
+    /// The index of the glyph drawn for a character.
+    pub(super) fn index(&self, c: char) -> u16 {
+        self.indices.get(&c).copied().unwrap_or(self.fallback)
+    }
+
+    pub(super) fn contains(&self, c: char) -> bool {
+        self.indices.contains_key(&c)
+    }
+
+    fn scale(&self, size: f32) -> f32 {
+        size / self.size
+    }
+
+    pub(super) fn advance(&self, index: u16, size: f32) -> f32 {
+        self.glyphs[index as usize].advance * self.scale(size)
+    }
+
+    pub(super) fn pair_kerning(&self, size: f32, first: u16, second: u16) -> f32 {
+        self.kerning
+            .get(&(first, second))
+            .map_or(0.0, |amount| amount * self.scale(size))
+    }
+
+    pub(super) fn v_metrics(&self, size: f32) -> rusttype::VMetrics {
+        let scale = self.scale(size);
+        rusttype::VMetrics {
+            ascent: self.base * scale,
+            descent: (self.base - self.line_height) * scale,
+            line_gap: 0.0,
+        }
+    }
+
+    /// The image of a glyph and the rectangle it covers when its origin is at the given position.
+    pub(super) fn place(
+        &self,
+        index: u16,
+        size: f32,
+        origin: Vector2<f32>,
+    ) -> Option<(TextureView2D, Rect<f32>)> {
+        let glyph = &self.glyphs[index as usize];
+        let scale = self.scale(size);
+        let top_left = origin + glyph.offset * scale;
+        glyph.view.clone().map(|view| {
+            (
+                view,
+                Rect {
+                    top_left,
+                    bottom_right: top_left + glyph.size * scale,
+                },
+            )
+        })
+    }
+}
+
+#[test]
+fn test_bitmap_font_text_and_xml() {
+    let text = r#"info face="Pixel Font" size=-8 bold=0 padding=0,0,0,0 spacing=1,1
+common lineHeight=10 base=8 scaleW=64 scaleH=64 pages=2 packed=0
+page id=0 file="pixel_0.png"
+page id=1 file="pixel_1.png"
+chars count=3
+char id=32   x=0  y=0  width=0 height=0 xoffset=0 yoffset=0 xadvance=4 page=0 chnl=15
+char id=65   x=1  y=1  width=5 height=7 xoffset=0 yoffset=1 xadvance=6 page=0 chnl=15
+char id=-1   x=10 y=1  width=5 height=7 xoffset=-1 yoffset=1 xadvance=6 page=1 chnl=15
+kernings count=1
+kerning first=65 second=65 amount=-1
+"#;
+    let xml = r#"<?xml version="1.0"?>
+<font>
+  <info face="Pixel Font" size="-8" bold="0" padding="0,0,0,0" spacing="1,1"/>
+  <common lineHeight="10" base="8" scaleW="64" scaleH="64" pages="2" packed="0"/>
+  <pages>
+    <page id="0" file="pixel_0.png" />
+    <page id="1" file="pixel_1.png" />
+  </pages>
+  <!-- Comments are <ignored> -->
+  <chars count="3">
+    <char id="32" x="0" y="0" width="0" height="0" xoffset="0" yoffset="0" xadvance="4" page="0" chnl="15" />
+    <char id="65" x="1" y="1" width="5" height="7" xoffset="0" yoffset="1" xadvance="6" page="0" chnl="15" />
+    <char id="-1" x="10" y="1" width="5" height="7" xoffset="-1" yoffset="1" xadvance="6" page="1" chnl="15" />
+  </chars>
+  <kernings count="1">
+    <kerning first="65" second="65" amount="-1" />
+  </kernings>
+</font>
+"#;
+    let parsed = BitmapFontDescription::parse(text.as_bytes()).unwrap();
+    assert_eq!(parsed.face, "Pixel Font");
+    assert_eq!(parsed.size, 8);
+    assert_eq!((parsed.line_height, parsed.base), (10, 8));
+    assert_eq!(parsed.pages, vec!["pixel_0.png", "pixel_1.png"]);
+    assert_eq!(parsed.chars.len(), 3);
+    assert_eq!(parsed.chars[2].id, std::u32::MAX);
+    assert_eq!(parsed.chars[2].x_offset, -1);
+    assert_eq!(
+        parsed.kernings,
+        vec![BitmapKerning {
+            first: 65,
+            second: 65,
+            amount: -1
+        }]
+    );
+    assert_eq!(
+        BitmapFontDescription::parse(xml.as_bytes()).unwrap(),
+        parsed
+    );
+
+    let error = |text: &str| match BitmapFontDescription::parse(text.as_bytes()) {
+        Err(GraphicsError::InvalidBitmapFont(message)) => message,
+        _ => panic!("expected an error for {}", text),
+    };
+    assert_eq!(error("info size=8"), "missing common tag");
+    assert_eq!(error("common lineHeight=x"), "invalid number");
+    assert_eq!(error("common lineHeight=10 pages=1"), "missing page file");
+    assert_eq!(
+        error("common lineHeight=10\nchar id=65 page=0"),
+        "character on a missing page"
+    );
+}
+
+#[test]
+fn test_bitmap_font_binary() {
+    let mut data = b"BMF\x03".to_vec();
+    let mut block = |kind: u8, contents: &[u8]| {
+        data.push(kind);
+        data.extend(&(contents.len() as u32).to_le_bytes());
+        data.extend(contents);
+    };
+    let mut info = vec![0; 14];
+    info[..2].copy_from_slice(&(-8i16).to_le_bytes());
+    info.extend(b"Pixel Font\0");
+    block(1, &info);
+    let mut common = vec![0; 15];
+    common[..2].copy_from_slice(&10u16.to_le_bytes());
+    common[2..4].copy_from_slice(&8u16.to_le_bytes());
+    common[8..10].copy_from_slice(&1u16.to_le_bytes());
+    block(2, &common);
+    block(3, b"pixel_0.png\0");
+    let mut ch = Vec::new();
+    ch.extend(&65u32.to_le_bytes());
+    for value in &[1u16, 1, 5, 7] {
+        ch.extend(&value.to_le_bytes());
+    }
+    for value in &[0i16, 1, 6] {
+        ch.extend(&value.to_le_bytes());
+    }
+    ch.extend(&[0, 15]);
+    block(4, &ch);
+    let mut kerning = Vec::new();
+    kerning.extend(&65u32.to_le_bytes());
+    kerning.extend(&65u32.to_le_bytes());
+    kerning.extend(&(-1i16).to_le_bytes());
+    block(5, &kerning);
+
+    let parsed = BitmapFontDescription::parse(&data).unwrap();
+    assert_eq!(parsed.face, "Pixel Font");
+    assert_eq!(parsed.size, 8);
+    assert_eq!((parsed.line_height, parsed.base), (10, 8));
+    assert_eq!(parsed.pages, vec!["pixel_0.png"]);
+    assert_eq!(
+        parsed.chars,
+        vec![BitmapChar {
+            id: 65,
+            x: 1,
+            y: 1,
+            width: 5,
+            height: 7,
+            x_offset: 0,
+            y_offset: 1,
+            x_advance: 6,
+            page: 0,
+        }]
+    );
+    assert_eq!(parsed.kernings[0].amount, -1);
+
+    assert!(BitmapFontDescription::parse(&data[..data.len() - 1]).is_err());
+    assert!(BitmapFontDescription::parse(b"BMF\x02").is_err());
+}
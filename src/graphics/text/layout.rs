@@ -0,0 +1,741 @@
+//! Breaking styled text into lines and positioning its glyphs.
+//!
+//! With the `shaping` feature, rustybuzz chooses the glyphs of outline fonts, applying
+//! ligatures, combining marks and the contextual forms of scripts like Arabic.
+//! Right-to-left text is then reversed within each line, though mixed directions are
+//! not reordered according to the full Unicode bidirectional algorithm.
+
+use cgmath::Vector2;
+
+use super::{Style, StyledGlyph};
+use crate::graphics::{Color, Rect};
+
+/// How lines of text are aligned horizontally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+    /// Stretch the spaces of wrapped lines to fill the maximum width,
+    /// while the last line of each paragraph is aligned to the left.
+    Justify,
+}
+
+/// Determines how text is broken into lines and how those lines are arranged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    /// Lines are wrapped at spaces so that they are not wider than this.
+    /// Words that don't fit into a line on their own are broken anywhere.
+    pub max_width: Option<f32>,
+    /// Lines are aligned within the maximum width, or within the widest line without one.
+    pub alignment: Alignment,
+    /// The distance between baselines as a multiple of the line height given by the font.
+    pub line_spacing: f32,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layout {
+    /// Left-aligned lines without wrapping.
+    pub fn new() -> Self {
+        Self {
+            max_width: None,
+            alignment: Alignment::Left,
+            line_spacing: 1.0,
+        }
+    }
+
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn with_alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    pub fn with_line_spacing(mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    /// The rectangle covered by the text if it was added at the origin with this layout.
+    pub fn measure<S: Into<String>>(&self, style: &Style, text: S) -> Rect<f32> {
+        self.measure_runs(&[StyledRun::new(style, text)])
+    }
+
+    /// The rectangle covered by the runs if they were added at the origin with this layout.
+    pub fn measure_runs(&self, runs: &[StyledRun]) -> Rect<f32> {
+        self.arrange(runs, Vector2::new(0.0, 0.0)).bounds
+    }
+
+    /// Position the glyphs of the runs with the top-left corner of the text at the given position.
+    pub(super) fn arrange(&self, runs: &[StyledRun], position: Vector2<f32>) -> Arrangement {
+        let lines = break_lines(runs, self.max_width);
+        let widest = lines.iter().map(|line| line.width).fold(0.0, f32::max);
+        let available = self.max_width.unwrap_or(widest);
+
+        let mut arrangement = Arrangement {
+            glyphs: Vec::new(),
+            decorations: Vec::new(),
+            bounds: Rect {
+                top_left: position,
+                bottom_right: position,
+            },
+        };
+        let mut left = std::f32::INFINITY;
+        let mut right = std::f32::NEG_INFINITY;
+        let mut baseline = position.y;
+        for (index, line) in lines.iter().enumerate() {
+            baseline += match index.checked_sub(1) {
+                Some(previous) => {
+                    let previous = &lines[previous].metrics;
+                    (previous.line_gap - previous.descent + line.metrics.ascent) * self.line_spacing
+                }
+                None => line.metrics.ascent,
+            };
+
+            let free = (available - line.width).max(0.0);
+            let spaces = line.inner_spaces();
+            let (offset, space_stretch) = match self.alignment {
+                Alignment::Left => (0.0, 0.0),
+                Alignment::Center => (free / 2.0, 0.0),
+                Alignment::Right => (free, 0.0),
+                Alignment::Justify => {
+                    if line.ends_paragraph || self.max_width.is_none() || spaces == 0 {
+                        (0.0, 0.0)
+                    } else {
+                        (0.0, free / spaces as f32)
+                    }
+                }
+            };
+            let start = position.x + offset;
+            left = left.min(start);
+            right = right.max(start + line.width + space_stretch * spaces as f32);
+
+            // Underlines and strikethroughs are drawn from the first to the last
+            // visible glyph of each run in a line
+            let mut decoration: Option<(usize, f32, f32)> = None;
+            let mut stretch = 0.0;
+            for glyph in &line.glyphs {
+                let run = &runs[glyph.run];
+                let x = start + glyph.x + stretch;
+                let font = run.style.font_at(glyph.font);
+                let origin = Vector2::new(x, baseline) + glyph.offset;
+                arrangement.glyphs.push((
+                    glyph.run,
+                    StyledGlyph {
+                        font_id: font.font_id,
+                        color: run.style.color,
+                        glyph: font.position(glyph.id, run.style.size, origin),
+                    },
+                ));
+                if glyph.is_space {
+                    stretch += space_stretch;
+                } else if run.underline || run.strikethrough {
+                    decoration = match decoration {
+                        Some((current, from, _)) if current == glyph.run => {
+                            Some((current, from, x + glyph.advance))
+                        }
+                        other => {
+                            if let Some(finished) = other {
+                                arrangement.decorate(runs, finished, baseline);
+                            }
+                            Some((glyph.run, x, x + glyph.advance))
+                        }
+                    };
+                }
+            }
+            if let Some(finished) = decoration {
+                arrangement.decorate(runs, finished, baseline);
+            }
+        }
+
+        let last = &lines[lines.len() - 1].metrics;
+        arrangement.bounds = Rect {
+            top_left: Vector2::new(left, position.y),
+            bottom_right: Vector2::new(right, baseline - last.descent),
+        };
+        arrangement
+    }
+}
+
+/// A piece of text in a single style.
+#[derive(Debug, Clone)]
+pub struct StyledRun {
+    pub text: String,
+    pub style: Style,
+    /// Draw a line below the text.
+    pub underline: bool,
+    /// Draw a line through the text.
+    pub strikethrough: bool,
+}
+
+impl StyledRun {
+    pub fn new<S: Into<String>>(style: &Style, text: S) -> Self {
+        Self {
+            text: text.into(),
+            style: style.clone(),
+            underline: false,
+            strikethrough: false,
+        }
+    }
+}
+
+/// The glyphs of laid out runs.
+pub(super) struct Arrangement {
+    /// Positioned glyphs together with the index of the run they belong to
+    pub glyphs: Vec<(usize, StyledGlyph)>,
+    /// Rectangles drawn for underlines and strikethroughs
+    pub decorations: Vec<(Rect<f32>, Color)>,
+    /// The rectangle covered by the lines of text
+    pub bounds: Rect<f32>,
+}
+
+impl Arrangement {
+    /// Add the lines decorating the part of a run between two horizontal positions.
+    fn decorate(&mut self, runs: &[StyledRun], (run, from, to): (usize, f32, f32), baseline: f32) {
+        let run = &runs[run];
+        let size = run.style.size;
+        let thickness = (size / 16.0).max(1.0);
+        let mut add = |center: f32| {
+            self.decorations.push((
+                Rect {
+                    top_left: Vector2::new(from, center - thickness / 2.0),
+                    bottom_right: Vector2::new(to, center + thickness / 2.0),
+                },
+                run.style.color,
+            ))
+        };
+        if run.underline {
+            add(baseline + size * 0.1);
+        }
+        if run.strikethrough {
+            add(baseline - size * 0.3);
+        }
+    }
+}
+
+/// A glyph within a line of text.
+#[derive(Debug, Clone, Copy)]
+struct LineGlyph {
+    /// Index of the run the glyph belongs to
+    run: usize,
+    /// Index of the font within the fonts of the run's style
+    font: usize,
+    id: rusttype::GlyphId,
+    /// Horizontal position relative to the start of the line, including kerning
+    x: f32,
+    advance: f32,
+    /// Where the glyph is drawn relative to its position, as placed by the shaper
+    offset: Vector2<f32>,
+    /// The kerning towards the previous glyph, if both end up in the same line
+    kerning: f32,
+    is_space: bool,
+    /// Part of right-to-left text, which is reversed after breaking the lines
+    rtl: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    glyphs: Vec<LineGlyph>,
+    /// The width without trailing spaces
+    width: f32,
+    /// Whether the line was ended by a newline or the end of the text, rather than wrapping.
+    ends_paragraph: bool,
+    /// The largest vertical metrics of the fonts in the line
+    metrics: rusttype::VMetrics,
+}
+
+impl Line {
+    /// Create a line from glyphs in logical order, using the given metrics if it is empty.
+    fn new(
+        mut glyphs: Vec<LineGlyph>,
+        ends_paragraph: bool,
+        font_metrics: &[Vec<rusttype::VMetrics>],
+        empty_metrics: rusttype::VMetrics,
+    ) -> Self {
+        let visible = glyphs
+            .iter()
+            .rposition(|glyph| !glyph.is_space)
+            .map_or(0, |last| last + 1);
+        let width = glyphs[..visible]
+            .last()
+            .map_or(0.0, |glyph| glyph.x + glyph.advance);
+
+        // Right-to-left text is shown in reverse, while trailing spaces stay at the end
+        let mut start = 0;
+        while start < visible {
+            if !glyphs[start].rtl {
+                start += 1;
+                continue;
+            }
+            let end = glyphs[start..visible]
+                .iter()
+                .position(|glyph| !glyph.rtl)
+                .map_or(visible, |length| start + length);
+            let mut x = glyphs[start].x;
+            glyphs[start..end].reverse();
+            for glyph in &mut glyphs[start..end] {
+                glyph.x = x;
+                x += glyph.advance;
+            }
+            start = end;
+        }
+
+        let metrics = glyphs
+            .iter()
+            .map(|glyph| font_metrics[glyph.run][glyph.font])
+            .fold(None, |combined: Option<rusttype::VMetrics>, metrics| {
+                Some(match combined {
+                    Some(combined) => rusttype::VMetrics {
+                        ascent: combined.ascent.max(metrics.ascent),
+                        descent: combined.descent.min(metrics.descent),
+                        line_gap: combined.line_gap.max(metrics.line_gap),
+                    },
+                    None => metrics,
+                })
+            })
+            .unwrap_or(empty_metrics);
+        Self {
+            glyphs,
+            width,
+            ends_paragraph,
+            metrics,
+        }
+    }
+
+    /// The number of spaces that are followed by other glyphs in the line.
+    fn inner_spaces(&self) -> usize {
+        match self.glyphs.iter().rposition(|glyph| !glyph.is_space) {
+            Some(last) => self.glyphs[..last]
+                .iter()
+                .filter(|glyph| glyph.is_space)
+                .count(),
+            None => 0,
+        }
+    }
+}
+
+/// Break the runs into lines at newlines, and at spaces where lines would get too wide.
+fn break_lines(runs: &[StyledRun], max_width: Option<f32>) -> Vec<Line> {
+    let font_metrics: Vec<Vec<_>> = runs
+        .iter()
+        .map(|run| {
+            run.style
+                .fonts()
+                .map(|font| font.v_metrics(run.style.size))
+                .collect()
+        })
+        .collect();
+    let no_metrics = rusttype::VMetrics {
+        ascent: 0.0,
+        descent: 0.0,
+        line_gap: 0.0,
+    };
+
+    let mut lines = Vec::new();
+    for paragraph in paragraphs(runs) {
+        let empty_metrics = paragraph.run.map_or(no_metrics, |run| font_metrics[run][0]);
+        let mut glyphs: Vec<LineGlyph> = Vec::new();
+        let mut caret = 0.0;
+        // Index of the glyph starting the word after the last space
+        let mut word_start = None;
+
+        for mut glyph in paragraph.glyphs {
+            let kerning = |glyphs: &[LineGlyph]| {
+                if glyphs.is_empty() {
+                    0.0
+                } else {
+                    glyph.kerning
+                }
+            };
+            let mut x = caret + kerning(&glyphs);
+
+            let too_wide = match max_width {
+                Some(max_width) => x + glyph.advance > max_width,
+                None => false,
+            };
+            if too_wide && !glyph.is_space && !glyphs.is_empty() {
+                // Move the current word to the next line if possible, otherwise break it here
+                let rest = match word_start.take() {
+                    Some(start) => glyphs.split_off(start),
+                    None => Vec::new(),
+                };
+                let line = std::mem::replace(&mut glyphs, rest);
+                lines.push(Line::new(line, false, &font_metrics, empty_metrics));
+                let shift = glyphs.first().map_or(0.0, |glyph| glyph.x);
+                for glyph in &mut glyphs {
+                    glyph.x -= shift;
+                }
+                caret = glyphs.last().map_or(0.0, |glyph| glyph.x + glyph.advance);
+                x = caret + kerning(&glyphs);
+            }
+
+            glyph.x = x;
+            caret = x + glyph.advance;
+            glyphs.push(glyph);
+            if glyph.is_space {
+                word_start = Some(glyphs.len());
+            }
+        }
+        lines.push(Line::new(glyphs, true, &font_metrics, empty_metrics));
+    }
+    lines
+}
+
+/// The glyphs of the text between two newlines, in logical order and not yet positioned.
+struct Paragraph {
+    glyphs: Vec<LineGlyph>,
+    /// The run containing the end of the paragraph, determining the height of an empty line
+    run: Option<usize>,
+}
+
+/// Split the runs into paragraphs and choose the glyphs of their characters.
+fn paragraphs(runs: &[StyledRun]) -> Vec<Paragraph> {
+    let mut paragraphs = Vec::new();
+    let mut glyphs = Vec::new();
+    let mut segment = Segment::default();
+    let mut last_run = None;
+    // Whether the last character with a direction of its own was written from right to left
+    let mut rtl = false;
+    // Characters without a direction after right-to-left text, which are only written
+    // from right to left as well if the text continues from right to left after them
+    let mut neutral = Vec::new();
+
+    for (run_index, run) in runs.iter().enumerate() {
+        last_run = Some(run_index);
+        for c in run.text.chars() {
+            if c == '\r' {
+                continue;
+            }
+            if c == '\n' {
+                for (run, font, c) in neutral.drain(..) {
+                    segment.add(runs, &mut glyphs, run, font, c, false);
+                }
+                segment.finish(runs, &mut glyphs);
+                paragraphs.push(Paragraph {
+                    glyphs: std::mem::take(&mut glyphs),
+                    run: Some(run_index),
+                });
+                rtl = false;
+                continue;
+            }
+            let font = run.style.font_index(c);
+            match direction(c) {
+                None if rtl => neutral.push((run_index, font, c)),
+                None => segment.add(runs, &mut glyphs, run_index, font, c, false),
+                Some(direction) => {
+                    for (run, font, c) in neutral.drain(..) {
+                        segment.add(runs, &mut glyphs, run, font, c, direction);
+                    }
+                    segment.add(runs, &mut glyphs, run_index, font, c, direction);
+                    rtl = direction;
+                }
+            }
+        }
+    }
+    for (run, font, c) in neutral.drain(..) {
+        segment.add(runs, &mut glyphs, run, font, c, false);
+    }
+    segment.finish(runs, &mut glyphs);
+    paragraphs.push(Paragraph {
+        glyphs,
+        run: last_run,
+    });
+    paragraphs
+}
+
+/// Whether a character is written from right to left, `None` for characters without a
+/// direction of their own, like spaces, digits and punctuation.
+/// Those are written from left to right, unless they are surrounded by right-to-left text.
+///
+/// Without shaping, all text is written from left to right.
+fn direction(c: char) -> Option<bool> {
+    if !cfg!(feature = "shaping") || !c.is_alphabetic() {
+        return None;
+    }
+    // Hebrew, Arabic, Syriac, Thaana, N'Ko and their presentation forms
+    Some(matches!(
+        c as u32,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF
+    ))
+}
+
+/// Consecutive characters, possibly from different runs, that are drawn in the same font
+/// and size, and in the same direction.
+#[derive(Debug, Default)]
+struct Segment {
+    text: String,
+    /// The byte offset in `text`, run and font index of each character
+    chars: Vec<(usize, usize, usize)>,
+    rtl: bool,
+}
+
+impl Segment {
+    /// Add a character, first finishing the segment if the character can't be part of it.
+    fn add(
+        &mut self,
+        runs: &[StyledRun],
+        glyphs: &mut Vec<LineGlyph>,
+        run: usize,
+        font: usize,
+        c: char,
+        rtl: bool,
+    ) {
+        if !self.accepts(runs, run, font, rtl) {
+            self.finish(runs, glyphs);
+        }
+        self.chars.push((self.text.len(), run, font));
+        self.text.push(c);
+        self.rtl = rtl;
+    }
+
+    fn accepts(&self, runs: &[StyledRun], run: usize, font: usize, rtl: bool) -> bool {
+        let (first_run, first_font) = match self.chars.first() {
+            Some(&(_, run, font)) => (run, font),
+            None => return true,
+        };
+        let first = &runs[first_run].style;
+        let style = &runs[run].style;
+        first.font_at(first_font).font_id == style.font_at(font).font_id
+            && first.size == style.size
+            && rtl == self.rtl
+    }
+
+    /// Add the glyphs of the characters to the paragraph and start a new segment.
+    fn finish(&mut self, runs: &[StyledRun], glyphs: &mut Vec<LineGlyph>) {
+        let (first_run, first_font) = match self.chars.first() {
+            Some(&(_, run, font)) => (run, font),
+            None => return,
+        };
+        let style = &runs[first_run].style;
+        let font = style.font_at(first_font);
+        let size = style.size;
+        let rtl = self.rtl;
+
+        #[cfg(feature = "shaping")]
+        {
+            if let super::FontData::Outline { shaper, .. } = &font.inner {
+                for shaped in shaper.shape(size, &self.text, rtl) {
+                    let index = match self.chars.binary_search_by_key(&shaped.cluster, |c| c.0) {
+                        Ok(index) => index,
+                        Err(index) => index.saturating_sub(1),
+                    };
+                    let (offset, run, font) = self.chars[index];
+                    glyphs.push(LineGlyph {
+                        run,
+                        font,
+                        id: shaped.id,
+                        x: 0.0,
+                        advance: shaped.advance,
+                        offset: shaped.offset,
+                        // Kerning is part of the advances chosen by the shaper
+                        kerning: 0.0,
+                        is_space: self.text[offset..].starts_with(char::is_whitespace),
+                        rtl,
+                    });
+                }
+                *self = Segment::default();
+                return;
+            }
+        }
+
+        let mut previous = None;
+        for (&(_, run, font_index), c) in self.chars.iter().zip(self.text.chars()) {
+            let id = font.glyph_id(c);
+            glyphs.push(LineGlyph {
+                run,
+                font: font_index,
+                id,
+                x: 0.0,
+                advance: font.advance(id, size),
+                offset: Vector2::new(0.0, 0.0),
+                // Kerning also applies between runs, as long as they use the same font and size
+                kerning: previous.map_or(0.0, |previous| font.pair_kerning(size, previous, id)),
+                is_space: c.is_whitespace(),
+                rtl,
+            });
+            previous = Some(id);
+        }
+        *self = Segment::default();
+    }
+}
+
+#[test]
+fn test_layout_wrapping() {
+    let style = super::test_style(20.0);
+    let lines = |max_width: Option<f32>, text: &str| {
+        break_lines(&[StyledRun::new(&style, text)], max_width)
+    };
+
+    assert_eq!(lines(None, "hello world").len(), 1);
+    let word = lines(None, "hello")[0].width;
+
+    // Wrapping between the words, ignoring the trailing space
+    let wrapped = lines(Some(word * 1.5), "hello hello");
+    assert_eq!(wrapped.len(), 2);
+    assert!(!wrapped[0].ends_paragraph);
+    assert!((wrapped[0].width - word).abs() < 0.001);
+    assert_eq!(wrapped[1].glyphs[0].x, 0.0);
+
+    // Breaking words that don't fit on their own
+    let broken = lines(Some(word / 2.0), "hello");
+    assert!(broken.len() > 1);
+    assert!(broken.iter().all(|line| line.width <= word / 2.0));
+
+    // Newlines
+    let paragraphs = lines(None, "a\n\nb");
+    assert_eq!(paragraphs.len(), 3);
+    assert!(paragraphs[1].glyphs.is_empty());
+    assert_eq!(paragraphs[1].metrics, style.v_metrics());
+}
+
+#[test]
+fn test_layout_measure() {
+    let style = super::test_style(20.0);
+    let v_metrics = style.v_metrics();
+    let line_height = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
+
+    let one = Layout::new().measure(&style, "hello");
+    let two = Layout::new().measure(&style, "hello\nhello");
+    assert_eq!(one.top_left, Vector2::new(0.0, 0.0));
+    assert!((two.size().y - one.size().y - line_height).abs() < 0.001);
+    assert_eq!(one.size().x, two.size().x);
+
+    let spaced = Layout::new()
+        .with_line_spacing(2.0)
+        .measure(&style, "hello\nhello");
+    assert!((spaced.size().y - one.size().y - 2.0 * line_height).abs() < 0.001);
+
+    // Centered and right-aligned lines within the maximum width
+    let centered = Layout::new()
+        .with_max_width(200.0)
+        .with_alignment(Alignment::Center)
+        .measure(&style, "hello");
+    assert!((centered.center().x - 100.0).abs() < 0.001);
+    let right = Layout::new()
+        .with_max_width(200.0)
+        .with_alignment(Alignment::Right)
+        .measure(&style, "hello");
+    assert!((right.bottom_right.x - 200.0).abs() < 0.001);
+
+    // Justified lines are stretched to the maximum width, except for the last one
+    let justified = Layout::new()
+        .with_max_width(one.size().x * 1.5)
+        .with_alignment(Alignment::Justify)
+        .arrange(
+            &[StyledRun::new(&style, "hello a b")],
+            Vector2::new(0.0, 0.0),
+        );
+    assert!((justified.bounds.size().x - one.size().x * 1.5).abs() < 0.001);
+    assert_eq!(justified.glyphs.len(), 9);
+}
+
+#[test]
+fn test_layout_runs() {
+    let style = super::test_style(20.0);
+    let whole = Layout::new().measure(&style, "AVA");
+    // Kerning still applies when the text is split into runs of the same font and size
+    let mut underlined = StyledRun::new(&style, "V");
+    underlined.underline = true;
+    let runs = [
+        StyledRun::new(&style, "A"),
+        underlined,
+        StyledRun::new(&style, "A"),
+    ];
+    let arrangement = Layout::new().arrange(&runs, Vector2::new(0.0, 0.0));
+    assert_eq!(arrangement.bounds, whole);
+    assert_eq!(arrangement.decorations.len(), 1);
+    assert_eq!(arrangement.glyphs[1].0, 1);
+
+    // Larger runs make the line taller
+    let large = Style {
+        size: 40.0,
+        ..style.clone()
+    };
+    let mixed =
+        Layout::new().measure_runs(&[StyledRun::new(&style, "a"), StyledRun::new(&large, "b")]);
+    assert!((mixed.size().y - Layout::new().measure(&large, "b").size().y).abs() < 0.001);
+}
+
+#[test]
+fn test_layout_fallback() {
+    let outline = super::test_style(20.0).font;
+    // A bitmap font with nothing but an `a`, which needs no pages as it has no image
+    let description = super::BitmapFontDescription {
+        face: "a".to_owned(),
+        size: 20,
+        line_height: 20,
+        base: 10,
+        pages: Vec::new(),
+        chars: vec![super::BitmapChar {
+            id: 'a' as u32,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            x_offset: 0,
+            y_offset: 0,
+            x_advance: 10,
+            page: 0,
+        }],
+        kernings: Vec::new(),
+    };
+    let bitmap = super::Font {
+        inner: super::FontData::Bitmap(std::rc::Rc::new(
+            super::bmfont::BitmapFont::new(&description, &[]).unwrap(),
+        )),
+        font_id: 1,
+    };
+    let style = Style::new(bitmap, 20.0, Color::WHITE).with_fallback(vec![outline.clone()]);
+    let line = |text: &str| break_lines(&[StyledRun::new(&style, text)], None).remove(0);
+
+    let mixed = line("aé?");
+    let fonts: Vec<_> = mixed.glyphs.iter().map(|glyph| glyph.font).collect();
+    assert_eq!(fonts, [0, 1, 1]);
+    assert_eq!(mixed.glyphs[1].x, 10.0);
+    // The line is as tall as the tallest font used in it
+    assert_eq!(mixed.metrics.ascent, outline.v_metrics(20.0).ascent);
+    assert_eq!(line("a").metrics, style.v_metrics());
+
+    // Characters missing from all fonts are drawn with the replacement of the first one
+    assert_eq!(line("\u{10FFFD}").glyphs[0].font, 0);
+}
+
+#[cfg(feature = "shaping")]
+#[test]
+fn test_layout_shaping() {
+    let style = super::test_style(20.0);
+
+    // Ligatures replace multiple characters with a single glyph
+    let ligature = break_lines(&[StyledRun::new(&style, "fi")], None);
+    assert_eq!(ligature[0].glyphs.len(), 1);
+
+    // Right-to-left text is reversed, even across runs, but the spaces around it are not
+    let runs = [
+        StyledRun::new(&style, "a \u{5d0}"),
+        StyledRun::new(&style, "\u{5d1} "),
+    ];
+    let line = &break_lines(&runs, None)[0];
+    let order: Vec<_> = line.glyphs.iter().map(|glyph| glyph.run).collect();
+    assert_eq!(order, [0, 0, 1, 0, 1]);
+    assert!(line.glyphs.windows(2).all(|pair| pair[0].x <= pair[1].x));
+    assert!(line.glyphs[4].is_space);
+
+    // Spaces between right-to-left words are reversed with them, but the space
+    // before left-to-right text keeps its place between the two
+    let text = "\u{5e9}\u{5dc}\u{5d5}\u{5dd} \u{5e2} world";
+    let line = &break_lines(&[StyledRun::new(&style, text)], None)[0];
+    let rtl: Vec<_> = line.glyphs.iter().map(|glyph| glyph.rtl).collect();
+    assert_eq!(rtl[..8], [true, true, true, true, true, true, false, false]);
+    assert!(line.glyphs[1].is_space);
+    assert!(line.glyphs[6].is_space);
+    assert!(line.glyphs[7].x > line.glyphs[6].x);
+    assert_eq!(line.glyphs[7].x, line.glyphs[6].x + line.glyphs[6].advance);
+}
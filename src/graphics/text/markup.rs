@@ -0,0 +1,171 @@
+//! Parsing text with tags that change its style into styled runs.
+
+use std::collections::HashMap;
+
+use super::{Font, StyledRun};
+use crate::graphics::{Color, GraphicsError};
+
+/// Turns text with style tags into runs that can be added to a `TextBuffer`.
+///
+/// The following tags are supported, and each of them is closed by `[/tag]`:
+///
+/// - `[color=#rrggbb]` changes the color, given in any notation understood by `Color::from_hex`
+/// - `[size=24]` changes the font size in pixels
+/// - `[font=name]` switches to a font registered with `with_font`
+/// - `[b]` and `[i]` switch to the fonts registered as `bold` and `italic`,
+///   or keep the current font if there are none
+/// - `[u]` underlines and `[s]` strikes through the text
+///
+/// A literal `[` is written as `[[`.
+///
+/// ```ignore
+/// let runs = markup.parse(&style, "Press [color=#ff0][b]Space[/b][/color] to [u]jump[/u]")?;
+/// text.add_runs(&runs, &Layout::new(), Vector2::new(20.0, 20.0));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Markup {
+    fonts: HashMap<String, Font>,
+}
+
+impl Markup {
+    pub fn new() -> Self {
+        Self {
+            fonts: HashMap::new(),
+        }
+    }
+
+    /// Register a font that can be selected with `[font=name]`.
+    pub fn with_font<S: Into<String>>(mut self, name: S, font: Font) -> Self {
+        self.fonts.insert(name.into(), font);
+        self
+    }
+
+    /// Split the text into runs, starting out in the given style.
+    pub fn parse(&self, base: &super::Style, text: &str) -> Result<Vec<StyledRun>, GraphicsError> {
+        let mut runs = Vec::new();
+        let mut current = StyledRun::new(base, "");
+        // The open tags, with their position and the style before them
+        let mut open: Vec<(&str, usize, StyledRun)> = Vec::new();
+
+        let mut rest = text;
+        while let Some(start) = rest.find('[') {
+            let position = text.len() - rest.len() + start;
+            current.text.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+            if rest.starts_with('[') {
+                current.text.push('[');
+                rest = &rest[1..];
+                continue;
+            }
+            let end = rest
+                .find(']')
+                .ok_or_else(|| invalid(position, "unterminated tag"))?;
+            let tag = &rest[..end];
+            rest = &rest[end + 1..];
+
+            // Every change of style starts a new run
+            if !current.text.is_empty() {
+                let mut next = current.clone();
+                next.text.clear();
+                runs.push(std::mem::replace(&mut current, next));
+            }
+
+            if let Some(name) = tag.strip_prefix('/') {
+                match open.pop() {
+                    Some((open_name, _, previous)) if open_name == name => current = previous,
+                    Some(_) => return Err(invalid(position, "closing tag does not match")),
+                    None => return Err(invalid(position, "closing tag without opening tag")),
+                }
+                continue;
+            }
+
+            let (name, value) = match tag.find('=') {
+                Some(index) => (&tag[..index], Some(&tag[index + 1..])),
+                None => (tag, None),
+            };
+            open.push((name, position, current.clone()));
+            match (name, value) {
+                ("color", Some(value)) => {
+                    current.style.color =
+                        Color::from_hex(value).ok_or_else(|| invalid(position, "invalid color"))?
+                }
+                ("size", Some(value)) => {
+                    current.style.size = value
+                        .parse()
+                        .ok()
+                        .filter(|size: &f32| *size > 0.0)
+                        .ok_or_else(|| invalid(position, "invalid size"))?
+                }
+                ("font", Some(value)) => {
+                    current.style.font = self
+                        .fonts
+                        .get(value)
+                        .cloned()
+                        .ok_or_else(|| invalid(position, "unknown font"))?
+                }
+                ("b", None) => {
+                    if let Some(font) = self.fonts.get("bold") {
+                        current.style.font = font.clone();
+                    }
+                }
+                ("i", None) => {
+                    if let Some(font) = self.fonts.get("italic") {
+                        current.style.font = font.clone();
+                    }
+                }
+                ("u", None) => current.underline = true,
+                ("s", None) => current.strikethrough = true,
+                _ => return Err(invalid(position, "unknown tag")),
+            }
+        }
+        current.text.push_str(rest);
+
+        if let Some((_, position, _)) = open.pop() {
+            return Err(invalid(position, "tag is never closed"));
+        }
+        if !current.text.is_empty() || runs.is_empty() {
+            runs.push(current);
+        }
+        Ok(runs)
+    }
+}
+
+fn invalid(position: usize, message: &'static str) -> GraphicsError {
+    GraphicsError::InvalidMarkup { position, message }
+}
+
+#[test]
+fn test_markup_parse() {
+    let style = super::test_style(20.0);
+    let markup = Markup::new();
+
+    let runs = markup
+        .parse(
+            &style,
+            "a [[b] [color=#f00]c [size=30][u]d[/u][/size][/color] e",
+        )
+        .unwrap();
+    let texts: Vec<_> = runs.iter().map(|run| run.text.as_str()).collect();
+    assert_eq!(texts, vec!["a [b] ", "c ", "d", " e"]);
+    assert_eq!(runs[1].style.color, Color::RED);
+    assert_eq!(runs[2].style.size, 30.0);
+    assert!(runs[2].underline);
+    assert_eq!(runs[3].style.color, Color::WHITE);
+    assert!(!runs[3].underline);
+
+    // Bold falls back to the current font
+    assert_eq!(markup.parse(&style, "[b]x[/b]").unwrap().len(), 1);
+    assert_eq!(markup.parse(&style, "").unwrap().len(), 1);
+
+    let error = |text: &str| match markup.parse(&style, text) {
+        Err(GraphicsError::InvalidMarkup { position, message }) => (position, message),
+        _ => panic!("expected a markup error for {}", text),
+    };
+    assert_eq!(error("ab[u]c").0, 2);
+    assert_eq!(error("[u]c[/s]").1, "closing tag does not match");
+    assert_eq!(error("[/u]").1, "closing tag without opening tag");
+    assert_eq!(error("[color=red]").1, "invalid color");
+    assert_eq!(error("[font=x]").1, "unknown font");
+    assert_eq!(error("[blink]").1, "unknown tag");
+    assert_eq!(error("a[u").1, "unterminated tag");
+}
@@ -0,0 +1,649 @@
+use std::{path::Path, rc::Rc};
+
+use cgmath::Vector2;
+
+use crate::{Context, GameResult};
+
+use super::{
+    context::Texture, Color, Drawable, GraphicsError, Quad, Rect, SamplerOptions, Texture2D,
+    TextureFormat, TextureView2D,
+};
+
+mod bmfont;
+pub use bmfont::{BitmapChar, BitmapFontDescription, BitmapKerning};
+mod layout;
+pub use layout::{Alignment, Layout, StyledRun};
+mod markup;
+pub use markup::Markup;
+mod sdf;
+pub use sdf::{Glow, Outline, SdfAtlas, SdfFont, SdfFontBuilder, SdfGlyph, SdfText, Shadow};
+#[cfg(feature = "shaping")]
+mod shaping;
+
+/// Options for creating a `Rasterizer`.
+#[derive(Debug, Clone)]
+pub struct RasterizerBuilder {
+    cache_size: u32,
+    max_cache_size: u32,
+    scale_tolerance: f32,
+    position_tolerance: f32,
+    multithread: bool,
+    sampler: SamplerOptions,
+}
+
+impl Default for RasterizerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RasterizerBuilder {
+    pub fn new() -> Self {
+        Self {
+            cache_size: 1024,
+            max_cache_size: 4096,
+            scale_tolerance: 0.1,
+            position_tolerance: 0.1,
+            multithread: true,
+            // Glyphs are uploaded to the base level only, so mipmaps would get out of date
+            sampler: SamplerOptions::linear(),
+        }
+    }
+
+    /// The initial width and height of the glyph cache texture in pixels.
+    pub fn with_cache_size(mut self, cache_size: u32) -> Self {
+        self.cache_size = cache_size;
+        self
+    }
+
+    /// The size up to which the glyph cache texture grows when the glyphs of a text don't fit,
+    /// limited by the maximum texture size supported by the driver.
+    /// Text that doesn't fit into the largest cache is drawn in multiple passes.
+    ///
+    /// Defaults to 4096. The cache texture uses one byte per pixel, so it takes up to 16 MiB
+    /// of GPU memory at that size, and four times as much for every doubling of the size.
+    pub fn with_max_cache_size(mut self, max_cache_size: u32) -> Self {
+        self.max_cache_size = max_cache_size;
+        self
+    }
+
+    /// How much the scale of a glyph may differ from a cached glyph, in pixels,
+    /// for the cached glyph to be drawn in its place.
+    pub fn with_scale_tolerance(mut self, scale_tolerance: f32) -> Self {
+        self.scale_tolerance = scale_tolerance;
+        self
+    }
+
+    /// How much the subpixel position of a glyph may differ from a cached glyph,
+    /// as a fraction of a pixel, for the cached glyph to be drawn in its place.
+    pub fn with_position_tolerance(mut self, position_tolerance: f32) -> Self {
+        self.position_tolerance = position_tolerance;
+        self
+    }
+
+    /// Whether glyphs are rasterized on multiple threads when many are added at once.
+    pub fn with_multithread(mut self, multithread: bool) -> Self {
+        self.multithread = multithread;
+        self
+    }
+
+    /// How the glyph cache texture is sampled.
+    pub fn with_sampler(mut self, sampler: SamplerOptions) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn build(&self, ctx: &mut Context) -> Result<Rasterizer, GraphicsError> {
+        let max_texture_size = super::max_texture_size(ctx);
+        let max_cache_size = self.max_cache_size.min(max_texture_size).max(1);
+        let size = self.cache_size.max(1).min(max_cache_size);
+
+        // Glyphs only consist of coverage, which is drawn as the opacity of white pixels
+        let cache_texture = Texture2D::new_empty(ctx, size, size, TextureFormat::R8)?;
+        cache_texture.set_sampler(ctx, self.sampler)?;
+        let renderer = super::BatchRender::new(ctx)?;
+
+        Ok(Rasterizer {
+            renderer,
+            cache_texture,
+            cache: rusttype::gpu_cache::Cache::builder()
+                .dimensions(size, size)
+                .scale_tolerance(self.scale_tolerance)
+                .position_tolerance(self.position_tolerance)
+                .multithread(self.multithread)
+                .build(),
+            max_cache_size,
+            font_id_next: 0,
+        })
+    }
+}
+
+pub struct Rasterizer {
+    renderer: super::BatchRender,
+    cache_texture: Texture2D,
+    cache: rusttype::gpu_cache::Cache<'static>,
+    /// The size up to which the cache texture may grow
+    max_cache_size: u32,
+    font_id_next: usize,
+}
+
+impl Rasterizer {
+    pub fn new(ctx: &mut Context) -> Result<Self, GraphicsError> {
+        Self::build().build(ctx)
+    }
+
+    pub fn build() -> RasterizerBuilder {
+        RasterizerBuilder::new()
+    }
+
+    pub fn create_font(&mut self, data: Vec<u8>) -> Result<Font, GraphicsError> {
+        let inner = FontData::outline(data)?;
+        Ok(self.next_font(inner))
+    }
+
+    /// Create a font from a BMFont descriptor and its page images, in the order of its pages.
+    ///
+    /// Bitmap fonts are drawn unscaled when used at the size they were generated at.
+    pub fn create_bitmap_font(
+        &mut self,
+        description: &BitmapFontDescription,
+        pages: &[Texture2D],
+    ) -> Result<Font, GraphicsError> {
+        let font = bmfont::BitmapFont::new(description, pages)?;
+        Ok(self.next_font(FontData::Bitmap(Rc::new(font))))
+    }
+
+    /// Load a BMFont descriptor file and the page images next to it.
+    ///
+    /// The pages are sampled with nearest filtering, which keeps pixel fonts crisp.
+    /// Use `create_bitmap_font` to load them differently.
+    pub fn load_bitmap_font<P: AsRef<Path>>(
+        &mut self,
+        ctx: &mut Context,
+        filename: P,
+    ) -> GameResult<Font> {
+        let filename = filename.as_ref();
+        let description = BitmapFontDescription::load(filename)?;
+        let directory = filename.parent().unwrap_or_else(|| Path::new(""));
+        let mut pages = Vec::new();
+        for page in &description.pages {
+            let texture = Texture2D::from_file(ctx, directory.join(page))?;
+            texture.set_sampler(ctx, SamplerOptions::nearest())?;
+            pages.push(texture);
+        }
+        Ok(self.create_bitmap_font(&description, &pages)?)
+    }
+
+    fn next_font(&mut self, inner: FontData) -> Font {
+        let font_id = self.font_id_next;
+        self.font_id_next += 1;
+        Font { inner, font_id }
+    }
+
+    /// The options determining how the glyph cache texture is sampled.
+    pub fn sampler(&self) -> SamplerOptions {
+        self.cache_texture.sampler()
+    }
+
+    /// Change how the glyph cache texture is sampled, e.g. to nearest filtering for pixel fonts.
+    ///
+    /// With mipmaps enabled, they are recomputed whenever new glyphs are added to the cache.
+    pub fn set_sampler(
+        &mut self,
+        ctx: &mut Context,
+        sampler: SamplerOptions,
+    ) -> Result<(), GraphicsError> {
+        self.cache_texture.set_sampler(ctx, sampler)
+    }
+
+    /// The current width and height of the glyph cache texture.
+    pub fn cache_size(&self) -> u32 {
+        self.cache.dimensions().0
+    }
+
+    /// Make sure that a prefix of the glyphs is in the cache, growing the cache if necessary,
+    /// and return the number of glyphs in that prefix.
+    /// Only if not even a single glyph fits, this fails.
+    fn cache_glyphs(
+        &mut self,
+        ctx: &mut Context,
+        glyphs: &[StyledGlyph],
+    ) -> Result<usize, GraphicsError> {
+        cache_prefix(
+            &mut (self, ctx),
+            glyphs.len(),
+            |(rasterizer, ctx), count| rasterizer.try_cache(ctx, &glyphs[..count]),
+            |(rasterizer, ctx)| rasterizer.grow_cache(ctx),
+        )
+    }
+
+    /// Add the glyphs to the cache, returning `false` if they don't fit at once.
+    fn try_cache(
+        &mut self,
+        ctx: &mut Context,
+        glyphs: &[StyledGlyph],
+    ) -> Result<bool, GraphicsError> {
+        for glyph in glyphs {
+            // Bitmap glyphs are drawn from the pages of their font instead
+            if let Glyph::Outline(outline) = &glyph.glyph {
+                self.cache.queue_glyph(glyph.font_id, outline.clone());
+            }
+        }
+
+        unsafe {
+            Texture::bind(gl::TEXTURE_2D, &self.cache_texture.raw())?;
+        }
+        let mut uploaded = false;
+        let result = self.cache.cache_queued(|region, data| {
+            uploaded = true;
+            // Only we have access to the texture, so it is safe to modify
+            unsafe {
+                let size = region.max - region.min;
+                if let Err(err) = Texture::subimage2d(
+                    gl::TEXTURE_2D,
+                    0,
+                    TextureFormat::R8,
+                    region.min.x as i32,
+                    region.min.y as i32,
+                    size.x as i32,
+                    size.y as i32,
+                    data,
+                ) {
+                    log::error!("Failed to upload glyph: {}", err);
+                }
+            }
+        });
+        unsafe {
+            Texture::unbind(gl::TEXTURE_2D)?;
+        }
+        if uploaded {
+            self.cache_texture.generate_mipmaps(ctx)?;
+        }
+
+        if result.is_err() {
+            // The queue is kept when caching fails
+            self.cache.clear_queue();
+        }
+        Ok(result.is_ok())
+    }
+
+    /// Double the size of the cache texture, unless it is already at the maximum size.
+    /// All glyphs need to be cached again afterwards.
+    fn grow_cache(&mut self, ctx: &mut Context) -> Result<bool, GraphicsError> {
+        let size = self.cache_size();
+        if size >= self.max_cache_size {
+            return Ok(false);
+        }
+        let size = size.saturating_mul(2).min(self.max_cache_size);
+        let sampler = self.cache_texture.sampler();
+        self.cache_texture = Texture2D::new_empty(ctx, size, size, TextureFormat::R8)?;
+        self.cache_texture.set_sampler(ctx, sampler)?;
+        self.cache
+            .to_builder()
+            .dimensions(size, size)
+            .rebuild(&mut self.cache);
+        log::info!("Growing glyph cache to {}x{}", size, size);
+        Ok(true)
+    }
+
+    pub fn rasterize<'r, 't>(&'r mut self, text: &'t TextBuffer) -> RasterizedText<'r, 't> {
+        RasterizedText {
+            rasterizer: self,
+            text,
+        }
+    }
+}
+
+/// Find the length of a prefix of `len` glyphs that fits into a cache at once.
+/// While the prefix doesn't fit, the cache is grown, and once it can't grow anymore,
+/// the prefix is halved. Only if not even a single glyph fits, this fails.
+///
+/// `try_cache` adds the given number of glyphs to the cache, returning `false` if they
+/// don't fit, and `grow` enlarges the cache, returning `false` if it is already at its limit.
+fn cache_prefix<C>(
+    cache: &mut C,
+    len: usize,
+    mut try_cache: impl FnMut(&mut C, usize) -> Result<bool, GraphicsError>,
+    mut grow: impl FnMut(&mut C) -> Result<bool, GraphicsError>,
+) -> Result<usize, GraphicsError> {
+    let mut count = len;
+    loop {
+        if try_cache(cache, count)? {
+            return Ok(count);
+        }
+        if !grow(cache)? {
+            if count <= 1 {
+                return Err(GraphicsError::InsufficientGlyphCache);
+            }
+            count /= 2;
+        }
+    }
+}
+
+/// A font that text can be laid out in, either created from the outlines of a TrueType
+/// or OpenType font, or from the prebuilt images of a bitmap font.
+#[derive(Debug, Clone)]
+pub struct Font {
+    inner: FontData,
+    font_id: usize,
+}
+
+#[derive(Debug, Clone)]
+enum FontData {
+    Outline {
+        font: rusttype::Font<'static>,
+        #[cfg(feature = "shaping")]
+        shaper: Rc<shaping::Shaper>,
+    },
+    Bitmap(Rc<bmfont::BitmapFont>),
+}
+
+impl FontData {
+    fn outline(data: Vec<u8>) -> Result<Self, GraphicsError> {
+        #[cfg(feature = "shaping")]
+        {
+            let (font, shaper) = shaping::Shaper::new(data)?;
+            Ok(FontData::Outline {
+                font,
+                shaper: Rc::new(shaper),
+            })
+        }
+        #[cfg(not(feature = "shaping"))]
+        {
+            let font = rusttype::Font::try_from_vec(data).ok_or(GraphicsError::InvalidFont)?;
+            Ok(FontData::Outline { font })
+        }
+    }
+}
+
+impl Font {
+    /// The glyph drawn for a character.
+    fn glyph_id(&self, c: char) -> rusttype::GlyphId {
+        match &self.inner {
+            FontData::Outline { font, .. } => font.glyph(c).id(),
+            FontData::Bitmap(font) => rusttype::GlyphId(font.index(c)),
+        }
+    }
+
+    /// Whether the font has a glyph for the character, rather than drawing a replacement.
+    fn has_glyph(&self, c: char) -> bool {
+        match &self.inner {
+            FontData::Outline { font, .. } => font.glyph(c).id().0 != 0,
+            FontData::Bitmap(font) => font.contains(c),
+        }
+    }
+
+    fn advance(&self, id: rusttype::GlyphId, size: f32) -> f32 {
+        match &self.inner {
+            FontData::Outline { font, .. } => {
+                let scale = rusttype::Scale::uniform(size);
+                font.glyph(id).scaled(scale).h_metrics().advance_width
+            }
+            FontData::Bitmap(font) => font.advance(id.0, size),
+        }
+    }
+
+    fn pair_kerning(&self, size: f32, first: rusttype::GlyphId, second: rusttype::GlyphId) -> f32 {
+        match &self.inner {
+            FontData::Outline { font, .. } => {
+                font.pair_kerning(rusttype::Scale::uniform(size), first, second)
+            }
+            FontData::Bitmap(font) => font.pair_kerning(size, first.0, second.0),
+        }
+    }
+
+    fn v_metrics(&self, size: f32) -> rusttype::VMetrics {
+        match &self.inner {
+            FontData::Outline { font, .. } => font.v_metrics(rusttype::Scale::uniform(size)),
+            FontData::Bitmap(font) => font.v_metrics(size),
+        }
+    }
+
+    /// Position a glyph with its origin on the baseline at the given position.
+    fn position(&self, id: rusttype::GlyphId, size: f32, origin: Vector2<f32>) -> Glyph {
+        match &self.inner {
+            FontData::Outline { font, .. } => Glyph::Outline(
+                font.glyph(id)
+                    .scaled(rusttype::Scale::uniform(size))
+                    .positioned(rusttype::point(origin.x, origin.y)),
+            ),
+            FontData::Bitmap(font) => Glyph::Bitmap(font.place(id.0, size, origin)),
+        }
+    }
+}
+
+/// A glyph positioned in a text.
+#[derive(Debug, Clone)]
+enum Glyph {
+    /// Rasterized from its outline when drawn
+    Outline(rusttype::PositionedGlyph<'static>),
+    /// The image of a bitmap glyph and where it is drawn, `None` for glyphs without an image
+    Bitmap(Option<(TextureView2D, Rect<f32>)>),
+}
+
+pub struct TextBuffer {
+    glyphs: Vec<StyledGlyph>,
+    /// Underlines and strikethroughs
+    decorations: Vec<(Rect<f32>, Color)>,
+}
+
+struct StyledGlyph {
+    font_id: usize,
+    color: Color,
+    glyph: Glyph,
+}
+
+impl TextBuffer {
+    pub fn new() -> Self {
+        Self {
+            glyphs: Vec::new(),
+            decorations: Vec::new(),
+        }
+    }
+
+    pub fn singleton<S: AsRef<str>>(style: &Style, position: Vector2<f32>, text: S) -> Self {
+        let mut buf = Self::new();
+        buf.add(style, position, text);
+        buf
+    }
+
+    /// Add text starting with its baseline at the given position.
+    /// Lines are only broken at newlines.
+    pub fn add<S: AsRef<str>>(&mut self, style: &Style, position: Vector2<f32>, text: S) {
+        let ascent = style.v_metrics().ascent;
+        self.add_layout(
+            style,
+            &Layout::default(),
+            position - Vector2::new(0.0, ascent),
+            text,
+        );
+    }
+
+    /// Add text arranged according to the layout with the top-left corner at the given position,
+    /// returning the rectangle covered by the lines of text.
+    pub fn add_layout<S: AsRef<str>>(
+        &mut self,
+        style: &Style,
+        layout: &Layout,
+        position: Vector2<f32>,
+        text: S,
+    ) -> Rect<f32> {
+        self.add_runs(&[StyledRun::new(style, text.as_ref())], layout, position)
+    }
+
+    /// Add runs of differently styled text, e.g. parsed from `Markup`, which are laid out
+    /// as one continuous text with the top-left corner at the given position.
+    /// Returns the rectangle covered by the lines of text.
+    pub fn add_runs(
+        &mut self,
+        runs: &[StyledRun],
+        layout: &Layout,
+        position: Vector2<f32>,
+    ) -> Rect<f32> {
+        let arrangement = layout.arrange(runs, position);
+        self.glyphs
+            .extend(arrangement.glyphs.into_iter().map(|(_, glyph)| glyph));
+        self.decorations.extend(arrangement.decorations);
+        arrangement.bounds
+    }
+
+    /// The rectangle covered by the text if it was added at the origin without wrapping.
+    pub fn measure<S: AsRef<str>>(style: &Style, text: S) -> Rect<f32> {
+        Layout::default().measure(style, text.as_ref())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Style {
+    pub font: Font,
+    pub size: f32,
+    pub color: Color,
+    /// Fonts that are tried in order for characters that `font` has no glyph for.
+    /// Characters missing from all of them are drawn with the replacement glyph of `font`.
+    pub fallback: Vec<Font>,
+}
+
+impl Style {
+    pub fn new(font: Font, size: f32, color: Color) -> Self {
+        Self {
+            font,
+            size,
+            color,
+            fallback: Vec::new(),
+        }
+    }
+
+    /// Use the given fonts for characters that `font` has no glyph for.
+    pub fn with_fallback(mut self, fallback: Vec<Font>) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    fn v_metrics(&self) -> rusttype::VMetrics {
+        self.font.v_metrics(self.size)
+    }
+
+    /// The font followed by its fallbacks.
+    fn fonts(&self) -> impl Iterator<Item = &Font> {
+        std::iter::once(&self.font).chain(&self.fallback)
+    }
+
+    /// The position in `fonts` of the font that a character is drawn in.
+    fn font_index(&self, c: char) -> usize {
+        if self.fallback.is_empty() {
+            return 0;
+        }
+        self.fonts().position(|font| font.has_glyph(c)).unwrap_or(0)
+    }
+
+    fn font_at(&self, index: usize) -> &Font {
+        match index.checked_sub(1) {
+            Some(fallback) => &self.fallback[fallback],
+            None => &self.font,
+        }
+    }
+}
+
+/// The style used in tests, drawing white text in Roboto Slab at the given size.
+#[cfg(test)]
+fn test_style(size: f32) -> Style {
+    let data = include_bytes!("../../../examples/RobotoSlab-Regular.ttf").to_vec();
+    let font = Font {
+        inner: FontData::outline(data).unwrap(),
+        font_id: 0,
+    };
+    Style::new(font, size, Color::WHITE)
+}
+
+pub struct RasterizedText<'r, 't> {
+    rasterizer: &'r mut Rasterizer,
+    text: &'t TextBuffer,
+}
+
+impl<'r, 't> Drawable for RasterizedText<'r, 't> {
+    fn draw(&mut self, ctx: &mut Context, state: super::RenderState) -> crate::GameResult<()> {
+        let mut glyphs = &self.text.glyphs[..];
+        while !glyphs.is_empty() {
+            let count = self.rasterizer.cache_glyphs(ctx, glyphs)?;
+            for glyph in &glyphs[..count] {
+                match &glyph.glyph {
+                    Glyph::Outline(outline) => {
+                        if let Ok(Some((tex_coords, screen_coords))) =
+                            self.rasterizer.cache.rect_for(glyph.font_id, outline)
+                        {
+                            let tex = TextureView2D {
+                                texture: self.rasterizer.cache_texture.clone(),
+                                source: tex_coords.into(),
+                            };
+                            let pos_size: Rect<i32> = screen_coords.into();
+                            self.rasterizer.renderer.draw_quad(
+                                Quad::textured(tex)
+                                    .with_position(pos_size.top_left.map(|x| x as f32))
+                                    .with_size(pos_size.size().map(|x| x as f32))
+                                    .with_tint(glyph.color),
+                            )
+                        }
+                    }
+                    Glyph::Bitmap(Some((view, rect))) => self.rasterizer.renderer.draw_quad(
+                        Quad::textured(view.clone())
+                            .with_position(rect.top_left)
+                            .with_size(rect.size())
+                            .with_tint(glyph.color),
+                    ),
+                    Glyph::Bitmap(None) => {}
+                }
+            }
+            glyphs = &glyphs[count..];
+            if !glyphs.is_empty() {
+                // The remaining glyphs replace the ones in the cache, so draw these first
+                self.rasterizer.renderer.draw(ctx, state)?;
+            }
+        }
+
+        for (rect, color) in &self.text.decorations {
+            self.rasterizer.renderer.draw_quad(
+                Quad::untextured(rect.size())
+                    .with_position(rect.top_left)
+                    .with_tint(*color),
+            )
+        }
+
+        self.rasterizer.renderer.draw(ctx, state)
+    }
+}
+
+#[test]
+fn test_cache_prefix() {
+    // A cache fitting as many glyphs as its size, which doubles when growing up to a limit
+    fn draw_passes(len: usize, size: usize, max_size: usize) -> Result<Vec<usize>, GraphicsError> {
+        let mut cache = (size, max_size);
+        let mut remaining = len;
+        let mut passes = Vec::new();
+        while remaining > 0 {
+            let count = cache_prefix(
+                &mut cache,
+                remaining,
+                |(size, _), count| Ok(count <= *size),
+                |(size, max_size)| {
+                    let grows = *size < *max_size;
+                    *size = (*size * 2).min(*max_size);
+                    Ok(grows)
+                },
+            )?;
+            passes.push(count);
+            remaining -= count;
+        }
+        Ok(passes)
+    }
+
+    // The cache grows until all glyphs fit
+    assert_eq!(draw_passes(10, 1, 16).unwrap(), vec![10]);
+    // Otherwise, the glyphs are drawn in multiple passes of halved prefixes
+    assert_eq!(draw_passes(10, 1, 4).unwrap(), vec![2, 4, 4]);
+    assert_eq!(draw_passes(3, 1, 1).unwrap(), vec![1, 1, 1]);
+    // Fails if not even a single glyph fits
+    assert!(matches!(
+        draw_passes(3, 0, 0),
+        Err(GraphicsError::InsufficientGlyphCache)
+    ));
+}
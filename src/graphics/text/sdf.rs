@@ -0,0 +1,650 @@
+//! Rendering text from signed distance fields, which stay crisp at any scale.
+
+use std::collections::HashMap;
+
+use cgmath::{Vector2, Zero};
+use image::GrayImage;
+
+use super::{Font, FontData, Glyph, StyledGlyph, TextBuffer};
+use crate::{
+    graphics::{
+        atlas::pack_growing, BatchRender, Color, Drawable, GraphicsError, Quad, Rect, RenderState,
+        ShaderEffect, Texture2D, TextureFormat, TextureView2D,
+    },
+    Context, GameResult,
+};
+
+/// Options for generating the distance fields of a font.
+#[derive(Debug, Clone)]
+pub struct SdfFontBuilder {
+    size: f32,
+    spread: f32,
+    chars: Vec<char>,
+    padding: u32,
+    max_size: u32,
+}
+
+impl Default for SdfFontBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SdfFontBuilder {
+    pub fn new() -> Self {
+        Self {
+            size: 32.0,
+            spread: 4.0,
+            // Printable ASCII
+            chars: (32u8..127).map(char::from).collect(),
+            padding: 1,
+            max_size: 4096,
+        }
+    }
+
+    /// The size in pixels the glyphs are rasterized at.
+    /// Larger sizes preserve more detail, e.g. sharp corners, at the cost of a larger atlas.
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// How far in pixels at the rasterized size the distance fields extend on each side
+    /// of the outline, which limits how wide outlines, glows and shadows can be.
+    pub fn with_spread(mut self, spread: f32) -> Self {
+        self.spread = spread;
+        self
+    }
+
+    /// The characters whose glyphs are added to the atlas, replacing the default of printable ASCII.
+    pub fn with_chars<I: IntoIterator<Item = char>>(mut self, chars: I) -> Self {
+        self.chars = chars.into_iter().collect();
+        self
+    }
+
+    /// Empty space between neighboring glyphs in the atlas.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// The maximum width and height of the atlas.
+    pub fn with_max_size(mut self, max_size: u32) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Compute the distance fields of the glyphs and pack them into an atlas image.
+    /// Only works for fonts with outlines, not for bitmap fonts.
+    pub fn generate(&self, font: &Font) -> Result<SdfAtlas, GraphicsError> {
+        let outlines = match &font.inner {
+            FontData::Outline { font, .. } => font,
+            FontData::Bitmap(_) => return Err(GraphicsError::InvalidFont),
+        };
+        let size = self.size.max(1.0);
+        let spread = self.spread.max(1.0);
+        // The field fades out completely within the border around each glyph
+        let border = spread.ceil() as i32 + 1;
+
+        let mut ids: Vec<rusttype::GlyphId> =
+            self.chars.iter().map(|&ch| font.glyph_id(ch)).collect();
+        ids.sort_by_key(|id| id.0);
+        ids.dedup();
+
+        let mut glyphs = HashMap::new();
+        let mut fields = Vec::new();
+        for id in ids {
+            let glyph = outlines
+                .glyph(id)
+                .scaled(rusttype::Scale::uniform(size))
+                .positioned(rusttype::point(0.0, 0.0));
+            let bounds = match glyph.pixel_bounding_box() {
+                Some(bounds) => bounds,
+                None => {
+                    // Glyphs without an outline, like spaces, take no space in the atlas
+                    let empty = Rect {
+                        top_left: Vector2::zero(),
+                        bottom_right: Vector2::zero(),
+                    };
+                    glyphs.insert(
+                        id,
+                        SdfGlyph {
+                            region: empty,
+                            offset: Vector2::zero(),
+                        },
+                    );
+                    continue;
+                }
+            };
+            let width = (bounds.width() + 2 * border) as usize;
+            let height = (bounds.height() + 2 * border) as usize;
+            let mut coverage = vec![0.0; width * height];
+            glyph.draw(|x, y, value| {
+                let index = (y as usize + border as usize) * width + x as usize + border as usize;
+                coverage[index] = value;
+            });
+            let field = signed_distance_field(&coverage, width, height, spread);
+            let offset = Vector2::new(
+                (bounds.min.x - border) as f32,
+                (bounds.min.y - border) as f32,
+            );
+            fields.push((id, offset, width as u32, height as u32, field));
+        }
+
+        // Placing tall glyphs first leaves a flatter skyline
+        fields.sort_by_key(|&(_, _, width, height, _)| std::cmp::Reverse((height, width)));
+        let sizes: Vec<Vector2<u32>> = fields
+            .iter()
+            .map(|&(_, _, width, height, _)| {
+                Vector2::new(width + self.padding, height + self.padding)
+            })
+            .collect();
+        let (atlas_size, positions) =
+            pack_growing(&sizes, self.max_size).ok_or(GraphicsError::InsufficientAtlasSize)?;
+
+        let mut image = GrayImage::new(atlas_size.x, atlas_size.y);
+        for ((id, offset, width, height, field), position) in fields.into_iter().zip(positions) {
+            for (index, &value) in field.iter().enumerate() {
+                let x = position.x + index as u32 % width;
+                let y = position.y + index as u32 / width;
+                image.put_pixel(x, y, image::Luma([value]));
+            }
+            let region = Rect {
+                top_left: position,
+                bottom_right: position + Vector2::new(width, height),
+            };
+            glyphs.insert(id, SdfGlyph { region, offset });
+        }
+
+        Ok(SdfAtlas {
+            font: font.clone(),
+            image,
+            size,
+            spread,
+            glyphs,
+        })
+    }
+
+    /// Generate the atlas and upload it into a texture.
+    pub fn build(&self, ctx: &mut Context, font: &Font) -> Result<SdfFont, GraphicsError> {
+        SdfFont::new(ctx, self.generate(font)?)
+    }
+}
+
+/// Where the distance field of a glyph is placed in an atlas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SdfGlyph {
+    /// The pixel coordinates of the distance field, empty for glyphs without an outline.
+    pub region: Rect<u32>,
+    /// The position of the top-left corner of the region relative to the origin of the glyph
+    /// on the baseline, in pixels at the rasterized size.
+    pub offset: Vector2<f32>,
+}
+
+/// The distance fields of the glyphs of a font, packed into a single image.
+///
+/// Each pixel stores the distance to the outline of the glyph as `0.5 + distance / (2 * spread)`
+/// scaled to `0..=255`, where distances inside the glyph are positive.
+#[derive(Debug, Clone)]
+pub struct SdfAtlas {
+    font: Font,
+    image: GrayImage,
+    size: f32,
+    spread: f32,
+    glyphs: HashMap<rusttype::GlyphId, SdfGlyph>,
+}
+
+impl SdfAtlas {
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    pub fn image(&self) -> &GrayImage {
+        &self.image
+    }
+
+    /// The size in pixels the glyphs were rasterized at.
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// How far in pixels at the rasterized size the distance fields extend around the outlines.
+    pub fn spread(&self) -> f32 {
+        self.spread
+    }
+
+    /// Where the glyph of a character is placed, or `None` if it wasn't added to the atlas.
+    pub fn glyph(&self, ch: char) -> Option<&SdfGlyph> {
+        self.glyphs.get(&self.font.glyph_id(ch))
+    }
+}
+
+/// A line of the given color around the outside of the glyphs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outline {
+    pub color: Color,
+    pub width: f32,
+}
+
+/// A halo around the glyphs that fades out over the radius.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glow {
+    pub color: Color,
+    pub radius: f32,
+}
+
+/// A copy of the glyphs drawn behind them, displaced by the offset and blurred by the softness.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shadow {
+    pub color: Color,
+    pub offset: Vector2<f32>,
+    pub softness: f32,
+}
+
+/// A font whose glyphs are drawn from distance fields that are generated once,
+/// instead of being rasterized for every size like the glyphs of a `Rasterizer`.
+///
+/// Text is added to a `TextBuffer` with styles using the font returned by `font`,
+/// and drawn with `render`. Glyphs that are not part of the atlas are skipped,
+/// as are glyphs of other fonts.
+///
+/// The widths of outlines, glows and shadows are given in pixels at the size
+/// the atlas was generated at, so they scale with the text.
+/// Effects cannot extend further from the glyphs than the spread of the atlas,
+/// and underlines and strikethroughs are drawn without them.
+///
+/// ```ignore
+/// let mut sdf = SdfFont::build().with_size(48.0).build(ctx, &font)?;
+/// sdf.set_outline(Some(Outline { color: Color::BLACK, width: 2.0 }));
+/// let style = Style::new(sdf.font().clone(), 96.0, Color::WHITE);
+/// graphics::draw(ctx, &mut sdf.render(&TextBuffer::singleton(&style, position, "Crisp")))?;
+/// ```
+pub struct SdfFont {
+    font: Font,
+    texture: Texture2D,
+    glyphs: HashMap<rusttype::GlyphId, SdfGlyph>,
+    size: f32,
+    spread: f32,
+    renderer: BatchRender,
+    effect: ShaderEffect,
+    outline: Option<Outline>,
+    glow: Option<Glow>,
+    shadow: Option<Shadow>,
+}
+
+impl SdfFont {
+    pub fn build() -> SdfFontBuilder {
+        SdfFontBuilder::new()
+    }
+
+    /// Upload a generated atlas, e.g. one that was generated at build time.
+    pub fn new(ctx: &mut Context, atlas: SdfAtlas) -> Result<Self, GraphicsError> {
+        let texture = Texture2D::new_empty(
+            ctx,
+            atlas.image.width(),
+            atlas.image.height(),
+            TextureFormat::R8,
+        )?;
+        texture.update_region_gray(ctx, 0, 0, &atlas.image)?;
+        let effect = ShaderEffect::from_fragment(ctx, FRAGMENT_SHADER_330_CORE)?;
+        let mut renderer = BatchRender::new(ctx)?;
+        renderer.set_effect(Some(effect.clone()));
+
+        Ok(Self {
+            font: atlas.font,
+            texture,
+            glyphs: atlas.glyphs,
+            size: atlas.size,
+            spread: atlas.spread,
+            renderer,
+            effect,
+            outline: None,
+            glow: None,
+            shadow: None,
+        })
+    }
+
+    /// The font to use in the styles of text drawn with this.
+    pub fn font(&self) -> &Font {
+        &self.font
+    }
+
+    /// The texture containing the distance fields.
+    pub fn texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    pub fn outline(&self) -> Option<Outline> {
+        self.outline
+    }
+
+    pub fn set_outline(&mut self, outline: Option<Outline>) {
+        self.outline = outline
+    }
+
+    pub fn glow(&self) -> Option<Glow> {
+        self.glow
+    }
+
+    pub fn set_glow(&mut self, glow: Option<Glow>) {
+        self.glow = glow
+    }
+
+    pub fn shadow(&self) -> Option<Shadow> {
+        self.shadow
+    }
+
+    pub fn set_shadow(&mut self, shadow: Option<Shadow>) {
+        self.shadow = shadow
+    }
+
+    pub fn render<'f, 't>(&'f mut self, text: &'t TextBuffer) -> SdfText<'f, 't> {
+        SdfText { font: self, text }
+    }
+
+    /// Upload the parameters of the effects, which are uploaded when the effect is next used.
+    fn set_uniforms(&self) -> Result<(), GraphicsError> {
+        let outline = self.outline.unwrap_or(Outline {
+            color: Color::TRANSPARENT,
+            width: 0.0,
+        });
+        let glow = self.glow.unwrap_or(Glow {
+            color: Color::TRANSPARENT,
+            radius: 0.0,
+        });
+        let shadow = self.shadow.unwrap_or(Shadow {
+            color: Color::TRANSPARENT,
+            offset: Vector2::zero(),
+            softness: 0.0,
+        });
+        self.effect.set_uniform("Spread", self.spread)?;
+        self.effect.set_uniform("OutlineWidth", outline.width)?;
+        self.effect.set_uniform("OutlineColor", outline.color)?;
+        self.effect.set_uniform("GlowRadius", glow.radius)?;
+        self.effect.set_uniform("GlowColor", glow.color)?;
+        self.effect.set_uniform("ShadowSoftness", shadow.softness)?;
+        self.effect.set_uniform("ShadowColor", shadow.color)?;
+        Ok(())
+    }
+
+    /// Add a quad for each glyph of this font, displaced by the offset at the rasterized size.
+    fn add_glyphs(&mut self, glyphs: &[StyledGlyph], offset: Vector2<f32>) {
+        for glyph in glyphs {
+            let outline = match &glyph.glyph {
+                Glyph::Outline(outline) if glyph.font_id == self.font.font_id => outline,
+                _ => continue,
+            };
+            let sdf_glyph = match self.glyphs.get(&outline.id()) {
+                Some(sdf_glyph) if sdf_glyph.region.size() != Vector2::zero() => sdf_glyph,
+                _ => continue,
+            };
+            let factor = outline.scale().y / self.size;
+            let origin = outline.position();
+            let position = Vector2::new(origin.x, origin.y) + (sdf_glyph.offset + offset) * factor;
+            self.renderer.draw_quad(
+                Quad::textured(TextureView2D::from_pixels(
+                    self.texture.clone(),
+                    sdf_glyph.region,
+                ))
+                .with_position(position)
+                .with_size(sdf_glyph.region.size().map(|x| x as f32) * factor)
+                .with_tint(glyph.color),
+            );
+        }
+    }
+}
+
+pub struct SdfText<'f, 't> {
+    font: &'f mut SdfFont,
+    text: &'t TextBuffer,
+}
+
+impl<'f, 't> Drawable for SdfText<'f, 't> {
+    fn draw(&mut self, ctx: &mut Context, state: RenderState) -> GameResult<()> {
+        self.font.set_uniforms()?;
+        if let Some(shadow) = self.font.shadow {
+            self.font.effect.set_uniform("ShadowPass", true)?;
+            self.font.add_glyphs(&self.text.glyphs, shadow.offset);
+            self.font.renderer.draw(ctx, state)?;
+            self.font.effect.set_uniform("ShadowPass", false)?;
+        }
+
+        self.font.add_glyphs(&self.text.glyphs, Vector2::zero());
+        for (rect, color) in &self.text.decorations {
+            self.font.renderer.draw_quad(
+                Quad::untextured(rect.size())
+                    .with_position(rect.top_left)
+                    .with_tint(*color),
+            )
+        }
+        self.font.renderer.draw(ctx, state)
+    }
+}
+
+const FRAGMENT_SHADER_330_CORE: &str = r"#version 330 core
+in vec2 Vert_Frag_TexCoord;
+in vec4 Vert_Frag_Color;
+
+out vec4 FragColor;
+
+uniform sampler2D Texture0;
+uniform bool UseTexture0 = false;
+// How far the field extends on each side of the outline, in pixels of the atlas
+uniform float Spread;
+uniform float OutlineWidth;
+uniform vec4 OutlineColor;
+uniform float GlowRadius;
+uniform vec4 GlowColor;
+uniform bool ShadowPass = false;
+uniform float ShadowSoftness;
+uniform vec4 ShadowColor;
+
+// Blend colors with straight alpha
+vec4 over(vec4 top, vec4 bottom) {
+    float alpha = top.a + bottom.a * (1.0 - top.a);
+    if(alpha <= 0.0) {
+        return vec4(0.0);
+    }
+    return vec4((top.rgb * top.a + bottom.rgb * bottom.a * (1.0 - top.a)) / alpha, alpha);
+}
+
+void main()
+{
+    // Underlines and strikethroughs
+    if(!UseTexture0) {
+        FragColor = ShadowPass ? vec4(0.0) : Vert_Frag_Color;
+        return;
+    }
+
+    // Distance to the outline of the glyph in pixels of the atlas, positive inside
+    float dist = (texture(Texture0, Vert_Frag_TexCoord).r - 0.5) * 2.0 * Spread;
+    // Half a screen pixel in pixels of the atlas, so that edges are smooth at any scale
+    float smoothing = max(fwidth(dist) * 0.5, 0.001);
+    float outer = dist + OutlineWidth;
+
+    if(ShadowPass) {
+        float shadow = smoothstep(-smoothing - ShadowSoftness, smoothing, outer);
+        FragColor = vec4(ShadowColor.rgb, ShadowColor.a * shadow * Vert_Frag_Color.a);
+        return;
+    }
+
+    vec4 color = vec4(0.0);
+    if(GlowRadius > 0.0) {
+        color = vec4(GlowColor.rgb, GlowColor.a * (1.0 - smoothstep(0.0, GlowRadius, -outer)));
+    }
+    color = over(vec4(OutlineColor.rgb, OutlineColor.a * smoothstep(-smoothing, smoothing, outer)), color);
+    color = over(vec4(Vert_Frag_Color.rgb, smoothstep(-smoothing, smoothing, dist)), color);
+    FragColor = vec4(color.rgb, color.a * Vert_Frag_Color.a);
+}";
+
+/// Compute the signed distance field of a coverage bitmap,
+/// encoded as described for `SdfAtlas`.
+fn signed_distance_field(coverage: &[f32], width: usize, height: usize, spread: f32) -> Vec<u8> {
+    const INF: f64 = 1e20;
+    // Squared distances to the inside and to the outside of the glyph,
+    // where partially covered pixels are treated as lying partially on the edge
+    let mut outer: Vec<f64> = coverage
+        .iter()
+        .map(|&value| match value {
+            v if v >= 1.0 => 0.0,
+            v if v <= 0.0 => INF,
+            v => (0.5 - v as f64).max(0.0).powi(2),
+        })
+        .collect();
+    let mut inner: Vec<f64> = coverage
+        .iter()
+        .map(|&value| match value {
+            v if v >= 1.0 => INF,
+            v if v <= 0.0 => 0.0,
+            v => (v as f64 - 0.5).max(0.0).powi(2),
+        })
+        .collect();
+    distance_transform(&mut outer, width, height);
+    distance_transform(&mut inner, width, height);
+
+    outer
+        .iter()
+        .zip(&inner)
+        .map(|(outer, inner)| {
+            let distance = inner.sqrt() - outer.sqrt();
+            let value = 0.5 + distance / (2.0 * spread as f64);
+            (value.max(0.0).min(1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+/// Turn a grid of squared distances to features into squared distances to the nearest feature,
+/// using the algorithm by Felzenszwalb and Huttenlocher along the columns and then the rows.
+fn distance_transform(grid: &mut [f64], width: usize, height: usize) {
+    let len = width.max(height);
+    let mut input = vec![0.0; len];
+    let mut output = vec![0.0; len];
+    let mut parabolas = vec![0; len];
+    let mut boundaries = vec![0.0; len + 1];
+    for x in 0..width {
+        for y in 0..height {
+            input[y] = grid[y * width + x];
+        }
+        distance_transform_1d(
+            &input[..height],
+            &mut output,
+            &mut parabolas,
+            &mut boundaries,
+        );
+        for y in 0..height {
+            grid[y * width + x] = output[y];
+        }
+    }
+    for y in 0..height {
+        input[..width].copy_from_slice(&grid[y * width..(y + 1) * width]);
+        distance_transform_1d(
+            &input[..width],
+            &mut output,
+            &mut parabolas,
+            &mut boundaries,
+        );
+        grid[y * width..(y + 1) * width].copy_from_slice(&output[..width]);
+    }
+}
+
+/// The lower envelope of the parabolas rooted at each input value.
+fn distance_transform_1d(
+    input: &[f64],
+    output: &mut [f64],
+    parabolas: &mut [usize],
+    boundaries: &mut [f64],
+) {
+    let square = |q: usize| (q * q) as f64;
+    let mut k = 0;
+    parabolas[0] = 0;
+    boundaries[0] = -std::f64::INFINITY;
+    boundaries[1] = std::f64::INFINITY;
+    for q in 1..input.len() {
+        let mut s;
+        loop {
+            let r = parabolas[k];
+            s = ((input[q] + square(q)) - (input[r] + square(r))) / (2 * (q - r)) as f64;
+            if s <= boundaries[k] && k > 0 {
+                k -= 1;
+            } else {
+                break;
+            }
+        }
+        k += 1;
+        parabolas[k] = q;
+        boundaries[k] = s;
+        boundaries[k + 1] = std::f64::INFINITY;
+    }
+    k = 0;
+    for (q, value) in output.iter_mut().enumerate().take(input.len()) {
+        while boundaries[k + 1] < q as f64 {
+            k += 1;
+        }
+        let r = parabolas[k];
+        *value = (q as f64 - r as f64).powi(2) + input[r];
+    }
+}
+
+#[test]
+fn test_signed_distance_field() {
+    // A filled square in the middle of an empty bitmap
+    let size = 21;
+    let mut coverage = vec![0.0; size * size];
+    for y in 5..16 {
+        for x in 5..16 {
+            coverage[y * size + x] = 1.0;
+        }
+    }
+    let field = signed_distance_field(&coverage, size, size, 3.0);
+    let at = |x: usize, y: usize| field[y * size + x];
+    assert_eq!(at(10, 10), 255);
+    assert_eq!(at(0, 0), 0);
+    // One pixel on either side of the edge
+    assert!(at(5, 10) > 128);
+    assert!(at(4, 10) < 128);
+    assert_eq!(at(5, 10) as u32 + at(4, 10) as u32, 255);
+    // Distances grow with the distance from the edge
+    assert!(at(6, 10) > at(5, 10));
+    assert!(at(3, 10) < at(4, 10));
+}
+
+#[test]
+fn test_sdf_atlas_generate() {
+    let font = super::test_style(24.0).font;
+    let atlas = SdfFontBuilder::new()
+        .with_size(24.0)
+        .with_chars("AB A".chars())
+        .generate(&font)
+        .unwrap();
+    assert_eq!(atlas.glyphs.len(), 3);
+
+    let space = atlas.glyph(' ').unwrap();
+    assert_eq!(space.region.size(), Vector2::new(0, 0));
+    assert!(atlas.glyph('C').is_none());
+
+    let a = atlas.glyph('A').unwrap();
+    let b = atlas.glyph('B').unwrap();
+    for glyph in &[a, b] {
+        assert!(glyph.region.bottom_right.x <= atlas.image().width());
+        assert!(glyph.region.bottom_right.y <= atlas.image().height());
+        // The glyphs rise above the baseline, surrounded by the border of the field
+        assert!(glyph.offset.y < -atlas.size() / 2.0);
+        assert_eq!(
+            atlas
+                .image()
+                .get_pixel(glyph.region.top_left.x, glyph.region.top_left.y)
+                .0,
+            [0]
+        );
+    }
+    let overlap = a.region.top_left.x < b.region.bottom_right.x
+        && b.region.top_left.x < a.region.bottom_right.x
+        && a.region.top_left.y < b.region.bottom_right.y
+        && b.region.top_left.y < a.region.bottom_right.y;
+    assert!(!overlap);
+
+    assert!(SdfFontBuilder::new()
+        .with_max_size(16)
+        .generate(&font)
+        .is_err());
+}
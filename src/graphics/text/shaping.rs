@@ -0,0 +1,90 @@
+//! Shaping text with rustybuzz, which is enabled by the `shaping` feature.
+
+use std::rc::Rc;
+
+use cgmath::Vector2;
+
+use crate::graphics::GraphicsError;
+
+/// Shapes text in an outline font, parsing the font file only once.
+pub(super) struct Shaper {
+    /// Borrows `data`, and thus needs to be declared (and dropped) before it
+    face: rustybuzz::Face<'static>,
+    /// The font file, whose allocation stays in place when the shaper is moved
+    data: Rc<[u8]>,
+    /// Used for scaling positions the same way as the rasterized glyphs
+    font: rusttype::Font<'static>,
+}
+
+impl std::fmt::Debug for Shaper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Shaper({:?}, {} bytes)", self.font, self.data.len())
+    }
+}
+
+/// A glyph chosen by the shaper.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ShapedGlyph {
+    pub id: rusttype::GlyphId,
+    /// Byte offset of the first character in the text that the glyph was produced for
+    pub cluster: usize,
+    pub advance: f32,
+    /// Where the glyph is drawn relative to its position on the baseline
+    pub offset: Vector2<f32>,
+}
+
+impl Shaper {
+    /// Parse the font file both for rasterizing glyphs with rusttype and for shaping.
+    /// The shaper keeps its own copy of the data.
+    pub(super) fn new(data: Vec<u8>) -> Result<(rusttype::Font<'static>, Self), GraphicsError> {
+        let data: Rc<[u8]> = data.into();
+        let font = rusttype::Font::try_from_vec(data.to_vec()).ok_or(GraphicsError::InvalidFont)?;
+        // SAFETY: the bytes live in a heap allocation owned by the shaper, which is never
+        // mutated or handed out, and is only freed after the face has been dropped. The
+        // face is only lent out with the lifetime of the shaper.
+        let bytes: &'static [u8] = unsafe { &*(&*data as *const [u8]) };
+        let face = rustybuzz::Face::from_slice(bytes, 0).ok_or(GraphicsError::InvalidFont)?;
+        let shaper = Self {
+            face,
+            data,
+            font: font.clone(),
+        };
+        Ok((font, shaper))
+    }
+
+    /// Shape text written in a single direction, returning its glyphs in logical order,
+    /// i.e. from right to left for right-to-left text.
+    pub(super) fn shape(&self, size: f32, text: &str, rtl: bool) -> Vec<ShapedGlyph> {
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.guess_segment_properties();
+        buffer.set_direction(if rtl {
+            rustybuzz::Direction::RightToLeft
+        } else {
+            rustybuzz::Direction::LeftToRight
+        });
+        let output = rustybuzz::shape(&self.face, &[], buffer);
+
+        // Positions are given in font units, scaled the same way as by rusttype
+        let scale = self.font.scale_for_pixel_height(size);
+        let mut glyphs: Vec<_> = output
+            .glyph_infos()
+            .iter()
+            .zip(output.glyph_positions())
+            .map(|(info, position)| ShapedGlyph {
+                id: rusttype::GlyphId(info.codepoint as u16),
+                cluster: info.cluster as usize,
+                advance: position.x_advance as f32 * scale,
+                // The y axis of font units points up
+                offset: Vector2::new(
+                    position.x_offset as f32 * scale,
+                    -position.y_offset as f32 * scale,
+                ),
+            })
+            .collect();
+        if rtl {
+            glyphs.reverse();
+        }
+        glyphs
+    }
+}
@@ -1,8 +1,8 @@
-use std::{path::Path, rc::Rc};
+use std::{cell::Cell, path::Path, rc::Rc};
 
 use cgmath::Vector2;
 
-use crate::{CheckGl, Context};
+use crate::Context;
 
 use super::{context::Texture, Color, Rect};
 
@@ -17,6 +17,8 @@ struct Texture2DImpl {
     raw: Texture,
     width: u32,
     height: u32,
+    format: TextureFormat,
+    sampler: Cell<SamplerOptions>,
 }
 
 impl PartialEq for Texture2D {
@@ -59,9 +61,56 @@ impl Texture2D {
         Self::from_image(ctx, &image)
     }
 
+    /// Create a texture of the given size where all pixels are transparent black,
+    /// to be filled later using `update_region`.
+    ///
+    /// The texture is sampled linearly without mipmaps.
+    pub fn new_empty(
+        ctx: &mut Context,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+    ) -> Result<Self, super::GraphicsError> {
+        if width.max(height) >= std::i32::MAX as u32 {
+            return Err(super::GraphicsError::Backend(super::BackendError::TooLarge));
+        }
+        match format {
+            TextureFormat::Rgba8 => Self::from_image_with_sampler(
+                ctx,
+                &image::RgbaImage::new(width, height),
+                SamplerOptions::linear(),
+            ),
+            TextureFormat::R8 => {
+                let raw = Texture::new()?;
+                unsafe {
+                    Texture::bind(gl::TEXTURE_2D, &raw)?;
+                    Texture::image2d(
+                        gl::TEXTURE_2D,
+                        format,
+                        width as i32,
+                        height as i32,
+                        &vec![0; width as usize * height as usize],
+                    )?;
+                    Texture::unbind(gl::TEXTURE_2D)?;
+                }
+                Self::from_raw(raw, width, height, format, SamplerOptions::linear())
+            }
+        }
+    }
+
+    /// Create a texture from an image, using the default sampler options.
     pub fn from_image(
+        ctx: &mut Context,
+        image: &image::RgbaImage,
+    ) -> Result<Self, super::GraphicsError> {
+        Self::from_image_with_sampler(ctx, image, SamplerOptions::default())
+    }
+
+    /// Create a texture from an image that is sampled according to the given options.
+    pub fn from_image_with_sampler(
         _ctx: &mut Context,
         image: &image::RgbaImage,
+        sampler: SamplerOptions,
     ) -> Result<Self, super::GraphicsError> {
         let raw = Texture::new()?;
 
@@ -77,7 +126,7 @@ impl Texture2D {
                 image.height() as i32,
                 &*image,
             )?;
-            CheckGl!(gl::GenerateMipmap(gl::TEXTURE_2D))?;
+            sampler.apply(gl::TEXTURE_2D)?;
             Texture::unbind(gl::TEXTURE_2D)?;
         }
 
@@ -86,6 +135,33 @@ impl Texture2D {
                 raw,
                 width: image.width(),
                 height: image.height(),
+                format: TextureFormat::Rgba8,
+                sampler: Cell::new(sampler),
+            }),
+        })
+    }
+
+    /// Wrap a texture whose storage has already been created with the given dimensions,
+    /// and apply the sampler options to it.
+    pub(crate) fn from_raw(
+        raw: Texture,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        sampler: SamplerOptions,
+    ) -> Result<Self, super::GraphicsError> {
+        unsafe {
+            Texture::bind(gl::TEXTURE_2D, &raw)?;
+            sampler.apply(gl::TEXTURE_2D)?;
+            Texture::unbind(gl::TEXTURE_2D)?;
+        }
+        Ok(Texture2D {
+            inner: Rc::new(Texture2DImpl {
+                raw,
+                width,
+                height,
+                format,
+                sampler: Cell::new(sampler),
             }),
         })
     }
@@ -98,9 +174,299 @@ impl Texture2D {
         self.inner.height
     }
 
+    pub fn format(&self) -> TextureFormat {
+        self.inner.format
+    }
+
     pub fn raw(&self) -> &Texture {
         &self.inner.raw
     }
+
+    /// Replace the pixels in a region of the texture, where `(x, y)` is the top-left corner
+    /// of the region in pixels, and its size is the size of the image.
+    ///
+    /// Mipmaps are recomputed afterwards if the sampler options use them.
+    pub fn update_region(
+        &self,
+        ctx: &mut Context,
+        x: u32,
+        y: u32,
+        image: &image::RgbaImage,
+    ) -> Result<(), super::GraphicsError> {
+        let (width, height) = image.dimensions();
+        self.update_region_raw(ctx, TextureFormat::Rgba8, x, y, width, height, image)
+    }
+
+    /// Like `update_region`, but for textures in the `R8` format.
+    pub fn update_region_gray(
+        &self,
+        ctx: &mut Context,
+        x: u32,
+        y: u32,
+        image: &image::GrayImage,
+    ) -> Result<(), super::GraphicsError> {
+        let (width, height) = image.dimensions();
+        self.update_region_raw(ctx, TextureFormat::R8, x, y, width, height, image)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn update_region_raw(
+        &self,
+        ctx: &mut Context,
+        format: TextureFormat,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> Result<(), super::GraphicsError> {
+        if format != self.format() {
+            return Err(super::GraphicsError::TextureFormatMismatch {
+                expected: self.format(),
+                actual: format,
+            });
+        }
+        if !region_fits(self.width(), self.height(), x, y, width, height) {
+            return Err(super::GraphicsError::RegionOutOfBounds {
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+        unsafe {
+            Texture::bind(gl::TEXTURE_2D, &self.inner.raw)?;
+            Texture::subimage2d(
+                gl::TEXTURE_2D,
+                0,
+                format,
+                x as i32,
+                y as i32,
+                width as i32,
+                height as i32,
+                data,
+            )?;
+            Texture::unbind(gl::TEXTURE_2D)?;
+        }
+        self.generate_mipmaps(ctx)
+    }
+
+    /// The options determining how this texture is sampled.
+    pub fn sampler(&self) -> SamplerOptions {
+        self.inner.sampler.get()
+    }
+
+    /// Change how this texture is sampled.
+    ///
+    /// # Note
+    ///
+    /// The options are stored in the texture itself, so they affect everything
+    /// that shares this texture, like sprites, batches and texture views.
+    pub fn set_sampler(
+        &self,
+        _ctx: &mut Context,
+        sampler: SamplerOptions,
+    ) -> Result<(), super::GraphicsError> {
+        self.apply_sampler(sampler)?;
+        Ok(())
+    }
+
+    /// Like `set_sampler`, for callers that only report backend errors.
+    pub(crate) fn apply_sampler(&self, sampler: SamplerOptions) -> Result<(), super::BackendError> {
+        unsafe {
+            Texture::bind(gl::TEXTURE_2D, &self.inner.raw)?;
+            sampler.apply(gl::TEXTURE_2D)?;
+            Texture::unbind(gl::TEXTURE_2D)?;
+        }
+        self.inner.sampler.set(sampler);
+        Ok(())
+    }
+
+    /// Recompute the mipmaps from the current contents of the texture.
+    ///
+    /// Mipmaps are computed when the texture is created or its sampler options change,
+    /// but not when its contents are modified afterwards, e.g. by rendering to a canvas.
+    /// Does nothing if the sampler options don't use mipmaps.
+    pub fn generate_mipmaps(&self, _ctx: &mut Context) -> Result<(), super::GraphicsError> {
+        if self.sampler().mipmaps {
+            unsafe {
+                Texture::bind(gl::TEXTURE_2D, &self.inner.raw)?;
+                Texture::generate_mipmap(gl::TEXTURE_2D)?;
+                Texture::unbind(gl::TEXTURE_2D)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether a region of the given size at `(x, y)` lies within a texture of size `limit_x` x `limit_y`.
+fn region_fits(limit_x: u32, limit_y: u32, x: u32, y: u32, width: u32, height: u32) -> bool {
+    let fits = |offset: u32, size: u32, limit: u32| match offset.checked_add(size) {
+        Some(end) => end <= limit,
+        None => false,
+    };
+    fits(x, width, limit_x) && fits(y, height, limit_y)
+}
+
+/// The format in which a texture stores its pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureFormat {
+    /// Four 8-bit channels for red, green, blue and alpha.
+    Rgba8,
+    /// A single 8-bit channel, which is drawn as the opacity of white pixels,
+    /// e.g. the coverage of glyphs.
+    R8,
+}
+
+impl TextureFormat {
+    /// The number of bytes storing a single pixel.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            TextureFormat::Rgba8 => 4,
+            TextureFormat::R8 => 1,
+        }
+    }
+
+    /// The internal format and the format of the pixel data used when uploading.
+    pub(crate) fn to_gl(self) -> (i32, u32) {
+        match self {
+            TextureFormat::Rgba8 => (gl::RGBA as i32, gl::RGBA),
+            TextureFormat::R8 => (gl::R8 as i32, gl::RED),
+        }
+    }
+}
+
+/// How texels are combined when a texture is magnified or minified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterMode {
+    /// Use the closest texel, which keeps pixel art crisp.
+    Nearest,
+    /// Interpolate linearly between the closest texels.
+    Linear,
+}
+
+/// How texture coordinates outside of `[0, 1]` are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WrapMode {
+    /// Use the texel at the nearest edge.
+    ClampToEdge,
+    /// Tile the texture.
+    Repeat,
+    /// Tile the texture, mirroring every other repetition.
+    MirroredRepeat,
+}
+
+impl WrapMode {
+    fn to_gl(self) -> i32 {
+        match self {
+            WrapMode::ClampToEdge => gl::CLAMP_TO_EDGE as i32,
+            WrapMode::Repeat => gl::REPEAT as i32,
+            WrapMode::MirroredRepeat => gl::MIRRORED_REPEAT as i32,
+        }
+    }
+}
+
+/// Determines how a texture is sampled when drawing it.
+///
+/// The default matches how textures were sampled before sampler options existed: texture
+/// coordinates repeat and mipmaps are used for minification. The only difference is that
+/// minification interpolates within each mipmap level as well (`GL_LINEAR_MIPMAP_LINEAR`
+/// instead of OpenGL's default `GL_NEAREST_MIPMAP_LINEAR`).
+/// `SamplerOptions::linear` and `SamplerOptions::nearest` clamp to the edge instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SamplerOptions {
+    /// Filter used when the texture is drawn smaller than its original size.
+    pub min_filter: FilterMode,
+    /// Filter used when the texture is drawn larger than its original size.
+    pub mag_filter: FilterMode,
+    /// Wrapping of the horizontal texture coordinate.
+    pub wrap_s: WrapMode,
+    /// Wrapping of the vertical texture coordinate.
+    pub wrap_t: WrapMode,
+    /// Whether mipmaps are generated and used for minification.
+    pub mipmaps: bool,
+}
+
+impl Default for SamplerOptions {
+    fn default() -> Self {
+        Self::linear()
+            .with_wrap(WrapMode::Repeat)
+            .with_mipmaps(true)
+    }
+}
+
+impl SamplerOptions {
+    /// Linear filtering without mipmaps.
+    pub fn linear() -> Self {
+        Self::filtered(FilterMode::Linear)
+    }
+
+    /// Nearest filtering without mipmaps, e.g. for pixel art.
+    pub fn nearest() -> Self {
+        Self::filtered(FilterMode::Nearest)
+    }
+
+    fn filtered(filter: FilterMode) -> Self {
+        Self {
+            min_filter: filter,
+            mag_filter: filter,
+            wrap_s: WrapMode::ClampToEdge,
+            wrap_t: WrapMode::ClampToEdge,
+            mipmaps: false,
+        }
+    }
+
+    pub fn with_min_filter(mut self, filter: FilterMode) -> Self {
+        self.min_filter = filter;
+        self
+    }
+
+    pub fn with_mag_filter(mut self, filter: FilterMode) -> Self {
+        self.mag_filter = filter;
+        self
+    }
+
+    /// Use the same wrap mode for both texture coordinates.
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap_s = wrap;
+        self.wrap_t = wrap;
+        self
+    }
+
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    fn min_filter_gl(self) -> i32 {
+        let filter = match (self.min_filter, self.mipmaps) {
+            (FilterMode::Nearest, false) => gl::NEAREST,
+            (FilterMode::Linear, false) => gl::LINEAR,
+            (FilterMode::Nearest, true) => gl::NEAREST_MIPMAP_NEAREST,
+            (FilterMode::Linear, true) => gl::LINEAR_MIPMAP_LINEAR,
+        };
+        filter as i32
+    }
+
+    fn mag_filter_gl(self) -> i32 {
+        let filter = match self.mag_filter {
+            FilterMode::Nearest => gl::NEAREST,
+            FilterMode::Linear => gl::LINEAR,
+        };
+        filter as i32
+    }
+
+    /// Apply the options to the texture currently bound to `target`,
+    /// computing its mipmaps if necessary.
+    unsafe fn apply(self, target: gl::types::GLenum) -> Result<(), super::BackendError> {
+        if self.mipmaps {
+            Texture::generate_mipmap(target)?;
+        }
+        Texture::parameteri(target, gl::TEXTURE_MIN_FILTER, self.min_filter_gl())?;
+        Texture::parameteri(target, gl::TEXTURE_MAG_FILTER, self.mag_filter_gl())?;
+        Texture::parameteri(target, gl::TEXTURE_WRAP_S, self.wrap_s.to_gl())?;
+        Texture::parameteri(target, gl::TEXTURE_WRAP_T, self.wrap_t.to_gl())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -114,6 +480,23 @@ impl TextureView2D {
         Self { texture, source }
     }
 
+    /// Create a view of the given region of the texture in pixel coordinates.
+    pub fn from_pixels(texture: Texture2D, region: Rect<u32>) -> Self {
+        let width = texture.width() as f32;
+        let height = texture.height() as f32;
+        let source = Rect {
+            top_left: Vector2::new(
+                region.top_left.x as f32 / width,
+                region.top_left.y as f32 / height,
+            ),
+            bottom_right: Vector2::new(
+                region.bottom_right.x as f32 / width,
+                region.bottom_right.y as f32 / height,
+            ),
+        };
+        Self { texture, source }
+    }
+
     pub fn size(&self) -> Vector2<f32> {
         let uv_size = self.source.size();
         Vector2 {
@@ -131,3 +514,13 @@ impl From<Texture2D> for TextureView2D {
         }
     }
 }
+
+#[test]
+fn test_region_fits() {
+    assert!(region_fits(16, 8, 0, 0, 16, 8));
+    assert!(region_fits(16, 8, 4, 2, 12, 6));
+    assert!(region_fits(16, 8, 16, 8, 0, 0));
+    assert!(!region_fits(16, 8, 4, 2, 13, 6));
+    assert!(!region_fits(16, 8, 4, 3, 12, 6));
+    assert!(!region_fits(16, 8, std::u32::MAX, 0, 2, 1));
+}
@@ -1,6 +1,6 @@
 pub(crate) mod context;
 
-pub use context::{run, Context, ContextBuilder};
+pub use context::{run, step, Context, ContextBuilder};
 
 // expose the public interface of the various subsystems
 pub mod graphics;